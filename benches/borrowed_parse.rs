@@ -0,0 +1,90 @@
+//! Compares allocations and time of owned and borrowed parsing
+//!
+//! Run with `cargo bench --bench borrowed_parse`.
+
+use apex_spec::{parse_str, parse_str_borrowed};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 5_000;
+
+/// System allocator that counts allocation calls
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn document() -> String {
+    let mut doc = String::from("TASK\nRefactor the authentication module\n\nGOALS\n");
+    for i in 0..10 {
+        doc.push_str(&format!("Goal number {} is met\n", i));
+    }
+    doc.push_str("\nPLAN\n");
+    for i in 0..40 {
+        doc.push_str(&format!("Run step {} with code_search \"auth_{}\"\n", i, i));
+    }
+    doc.push_str("\nCONTEXT\n");
+    for i in 0..40 {
+        doc.push_str(&format!("  fn handler_{}() {{ authenticate(); }}\n", i));
+    }
+    doc.push_str("\nMETA\nversion=1.1\nauthor=bench\n");
+    doc
+}
+
+/// Allocation calls made by one run of `f`
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn time(label: &str, allocs: usize, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed();
+    println!("{:<20} {:>10.2?} per call {:>6} allocations", label, elapsed / ITERATIONS, allocs);
+    elapsed
+}
+
+fn main() {
+    let doc = document();
+    assert_eq!(parse_str_borrowed(&doc).unwrap().to_document(), parse_str(&doc).unwrap());
+
+    let owned_allocs = allocations(|| {
+        black_box(parse_str(black_box(&doc)).unwrap());
+    });
+    let borrowed_allocs = allocations(|| {
+        black_box(parse_str_borrowed(black_box(&doc)).unwrap());
+    });
+    assert!(borrowed_allocs < owned_allocs);
+
+    let owned = time("parse_str", owned_allocs, || {
+        black_box(parse_str(black_box(&doc)).unwrap());
+    });
+    let borrowed = time("parse_str_borrowed", borrowed_allocs, || {
+        black_box(parse_str_borrowed(black_box(&doc)).unwrap());
+    });
+    println!("speedup              {:>9.1}x", owned.as_secs_f64() / borrowed.as_secs_f64());
+}
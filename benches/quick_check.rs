@@ -0,0 +1,47 @@
+//! Compares the `quick_check` pre-filter with full parsing and validation
+//!
+//! Run with `cargo bench --bench quick_check`.
+
+use apex_spec::{parse_and_validate, quick_check};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20_000;
+
+fn document() -> String {
+    let mut doc = String::from("TASK\nRefactor the authentication module\n\nGOALS\n");
+    for i in 0..10 {
+        doc.push_str(&format!("Goal number {} is met\n", i));
+    }
+    doc.push_str("\nPLAN\n");
+    for i in 0..40 {
+        doc.push_str(&format!("Run step {} with code_search \"auth_{}\"\n", i, i));
+    }
+    doc.push_str("\nCONSTRAINTS\nNo Mocks\nReal DBs only\n< 300 LOC\nSHOULD: safe refactor\n");
+    doc.push_str("\nTOOLS\ncode_search(query)\nread_file(path)\n\nMETA\nversion=1.1\nauthor=bench\n");
+    doc
+}
+
+fn time(label: &str, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed();
+    println!("{:<20} {:>10.2?} per call", label, elapsed / ITERATIONS);
+    elapsed
+}
+
+fn main() {
+    let doc = document();
+    assert!(quick_check(&doc));
+    assert!(parse_and_validate(&doc).is_ok());
+
+    let quick = time("quick_check", || {
+        black_box(quick_check(black_box(&doc)));
+    });
+    let full = time("parse_and_validate", || {
+        let _ = black_box(parse_and_validate(black_box(&doc)));
+    });
+    println!("speedup              {:>9.1}x", full.as_secs_f64() / quick.as_secs_f64());
+}
@@ -2,7 +2,10 @@
 //!
 //! Core data structures for APEX documents, blocks, and spans.
 
+use crate::validate::canonical_meta_key;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::ops::Range;
 
 /// Source location span for error reporting
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -59,7 +62,7 @@ impl Default for Span {
 }
 
 /// Block type identifiers (uppercase keywords)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BlockKind {
     /// TASK - Required. Single-line task description.
     Task,
@@ -79,10 +82,17 @@ pub enum BlockKind {
     Context,
     /// META - Optional. Metadata key-value pairs.
     Meta,
+    /// WHEN - Optional, repeatable. Condition line followed by conditional steps.
+    When,
+    /// RETRY - Optional. Per-step retry policies.
+    Retry,
+    /// Extension block registered in a [`BlockRegistry`]. Optional, repeatable.
+    Custom(String),
 }
 
 impl BlockKind {
     /// Parse block kind from string (case-insensitive)
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_uppercase().as_str() {
             "TASK" => Some(BlockKind::Task),
@@ -94,12 +104,14 @@ impl BlockKind {
             "DIFF" => Some(BlockKind::Diff),
             "CONTEXT" => Some(BlockKind::Context),
             "META" => Some(BlockKind::Meta),
+            "WHEN" => Some(BlockKind::When),
+            "RETRY" => Some(BlockKind::Retry),
             _ => None,
         }
     }
 
     /// Get canonical uppercase name
-    pub fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &str {
         match self {
             BlockKind::Task => "TASK",
             BlockKind::Goals => "GOALS",
@@ -110,6 +122,9 @@ impl BlockKind {
             BlockKind::Diff => "DIFF",
             BlockKind::Context => "CONTEXT",
             BlockKind::Meta => "META",
+            BlockKind::When => "WHEN",
+            BlockKind::Retry => "RETRY",
+            BlockKind::Custom(name) => name,
         }
     }
 
@@ -120,7 +135,12 @@ impl BlockKind {
 
     /// Check if block can be empty
     pub fn allows_empty(&self) -> bool {
-        matches!(self, BlockKind::Context | BlockKind::Meta)
+        matches!(self, BlockKind::Context | BlockKind::Meta | BlockKind::Custom(_))
+    }
+
+    /// Check if block is a registered extension block
+    pub fn is_custom(&self) -> bool {
+        matches!(self, BlockKind::Custom(_))
     }
 }
 
@@ -130,8 +150,72 @@ impl std::fmt::Display for BlockKind {
     }
 }
 
+/// Extension block identifiers accepted by the parser
+///
+/// Registered names become [`BlockKind::Custom`] headers, so their content is
+/// carried through instead of being folded into the previous block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockRegistry {
+    names: BTreeSet<String>,
+}
+
+impl BlockRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder: register an extension block
+    pub fn with_block(mut self, name: &str) -> Self {
+        self.register(name);
+        self
+    }
+
+    /// Register an extension block
+    ///
+    /// Names must be uppercase identifiers (`A-Z`, `_`) and must not shadow a
+    /// built-in block. Returns `false` if the name was rejected.
+    pub fn register(&mut self, name: &str) -> bool {
+        let valid = is_block_identifier(name) && BlockKind::from_str(name).is_none();
+        if valid {
+            self.names.insert(name.to_string());
+        }
+        valid
+    }
+
+    /// Check if an extension block is registered (exact, uppercase)
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    /// Kind of a registered extension block (exact, uppercase)
+    pub fn get(&self, name: &str) -> Option<BlockKind> {
+        self.names.get(name).map(|name| BlockKind::Custom(name.clone()))
+    }
+
+    /// Resolve a header to a built-in or registered block kind (case-insensitive)
+    pub fn resolve(&self, name: &str) -> Option<BlockKind> {
+        BlockKind::from_str(name).or_else(|| self.get(&name.to_uppercase()))
+    }
+
+    /// Registered names in sorted order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(|s| s.as_str())
+    }
+}
+
+/// Check if `s` has the shape of a block header: uppercase letters and `_`,
+/// starting with a letter
+pub fn is_block_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_uppercase())
+        && s.chars().all(|c| c.is_ascii_uppercase() || c == '_')
+}
+
 /// A single block in an APEX document
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Equality ignores the byte range: LF and CRLF sources of the same document
+/// compare equal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     /// Block type
     pub kind: BlockKind,
@@ -139,12 +223,55 @@ pub struct Block {
     pub lines: Vec<String>,
     /// Source location
     pub span: Span,
+    /// Byte offset of the header line in the parsed input
+    #[serde(default)]
+    pub byte_start: usize,
+    /// Byte offset just past the last line's content (line break excluded)
+    #[serde(default)]
+    pub byte_end: usize,
+    /// Source line of each entry in `lines`
+    ///
+    /// Empty when the lines directly follow the header, one per source line;
+    /// set when lines were skipped (comments) or moved. See [`Block::source_line`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line_numbers: Vec<usize>,
+}
+
+impl PartialEq for Block {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.lines == other.lines
+            && self.span == other.span
+            && self.line_numbers == other.line_numbers
+    }
 }
 
+impl Eq for Block {}
+
 impl Block {
-    /// Create a new block
+    /// Create a new block (byte range left empty)
     pub fn new(kind: BlockKind, lines: Vec<String>, span: Span) -> Self {
-        Self { kind, lines, span }
+        Self {
+            kind,
+            lines,
+            span,
+            byte_start: 0,
+            byte_end: 0,
+            line_numbers: Vec::new(),
+        }
+    }
+
+    /// Source line (1-indexed) of `lines[idx]`
+    pub fn source_line(&self, idx: usize) -> usize {
+        source_line(&self.line_numbers, self.span, idx)
+    }
+
+    /// Byte range of the block in the parsed input, header included
+    ///
+    /// `&input[block.byte_range()]` is the block's source text. Empty for
+    /// blocks that were not parsed from text.
+    pub fn byte_range(&self) -> Range<usize> {
+        self.byte_start..self.byte_end
     }
 
     /// Check if block content is empty
@@ -165,6 +292,20 @@ impl Block {
     pub fn content(&self) -> String {
         self.content_lines().join("\n")
     }
+
+    /// Lines verbatim, without leading and trailing blank separator lines
+    ///
+    /// Inner blank lines and per-line whitespace are kept.
+    pub fn raw_lines(&self) -> &[String] {
+        let start = self.lines.iter().position(|l| !l.trim().is_empty()).unwrap_or(self.lines.len());
+        let end = self.lines.iter().rposition(|l| !l.trim().is_empty()).map_or(start, |i| i + 1);
+        &self.lines[start..end]
+    }
+
+    /// Join [`Block::raw_lines`] as a single string (for DIFF and CONTEXT)
+    pub fn raw_content(&self) -> String {
+        self.raw_lines().join("\n")
+    }
 }
 
 /// Complete APEX document (parsed AST)
@@ -210,6 +351,16 @@ impl ApexDocument {
         self.blocks.iter().filter(|b| b.kind == kind).count()
     }
 
+    /// Iterate over all blocks in document order, including duplicates
+    pub fn iter_blocks(&self) -> impl Iterator<Item = &Block> {
+        self.blocks.iter()
+    }
+
+    /// Iterate over `(kind, block)` pairs in document order
+    pub fn entries(&self) -> impl Iterator<Item = (BlockKind, &Block)> {
+        self.blocks.iter().map(|b| (b.kind.clone(), b))
+    }
+
     // --- Convenience accessors ---
 
     pub fn task(&self) -> Option<&Block> {
@@ -247,6 +398,39 @@ impl ApexDocument {
     pub fn meta(&self) -> Option<&Block> {
         self.get_block(BlockKind::Meta)
     }
+
+    pub fn retry(&self) -> Option<&Block> {
+        self.get_block(BlockKind::Retry)
+    }
+
+    /// All WHEN blocks (the block may appear more than once)
+    pub fn when_blocks(&self) -> Vec<&Block> {
+        self.get_blocks(BlockKind::When)
+    }
+
+    /// All registered extension blocks, in document order
+    pub fn custom_blocks(&self) -> Vec<&Block> {
+        self.blocks.iter().filter(|b| b.kind.is_custom()).collect()
+    }
+
+    // --- Line queries ---
+
+    /// Block owning a 1-based source line
+    ///
+    /// Header and content lines belong to their block. Lines in a gap between
+    /// two blocks (e.g. skipped comments) belong to the preceding block; lines
+    /// before the first header or past the last block's span return `None`.
+    pub fn block_at_line(&self, line: usize) -> Option<&Block> {
+        let idx = self.blocks.iter().rposition(|b| b.span.start_line <= line)?;
+        let block = &self.blocks[idx];
+        let is_last = idx + 1 == self.blocks.len();
+        (!is_last || line <= block.span.end_line).then_some(block)
+    }
+
+    /// Kind of the block owning a 1-based source line (see [`Self::block_at_line`])
+    pub fn block_kind_at_line(&self, line: usize) -> Option<&BlockKind> {
+        self.block_at_line(line).map(|b| &b.kind)
+    }
 }
 
 impl Default for ApexDocument {
@@ -255,6 +439,190 @@ impl Default for ApexDocument {
     }
 }
 
+/// Fluent construction of an [`ApexDocument`] without source text
+///
+/// Blocks are emitted in canonical order (TASK, GOALS, PLAN, CONSTRAINTS,
+/// TOOLS, META), skipping empty ones, with spans as if the document had been
+/// written out with one blank line between blocks.
+#[derive(Debug, Clone, Default)]
+pub struct ApexDocumentBuilder {
+    task: Vec<String>,
+    goals: Vec<String>,
+    steps: Vec<String>,
+    constraints: Vec<String>,
+    tools: Vec<String>,
+    meta: Vec<(String, String)>,
+}
+
+impl ApexDocumentBuilder {
+    /// Create an empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the TASK line (replaces any previous task)
+    pub fn task(mut self, task: &str) -> Self {
+        self.task = vec![task.to_string()];
+        self
+    }
+
+    /// Add a GOALS entry
+    pub fn goal(mut self, goal: &str) -> Self {
+        self.goals.push(goal.to_string());
+        self
+    }
+
+    /// Add a PLAN step
+    pub fn step(mut self, step: &str) -> Self {
+        self.steps.push(step.to_string());
+        self
+    }
+
+    /// Add a CONSTRAINTS rule
+    pub fn constraint(mut self, constraint: &str) -> Self {
+        self.constraints.push(constraint.to_string());
+        self
+    }
+
+    /// Add a TOOLS declaration
+    pub fn tool(mut self, tool: &str) -> Self {
+        self.tools.push(tool.to_string());
+        self
+    }
+
+    /// Set a META entry (replaces an earlier value for the same key, compared
+    /// case-insensitively)
+    pub fn meta(mut self, key: &str, value: &str) -> Self {
+        let canonical = canonical_meta_key(key);
+        match self.meta.iter_mut().find(|(k, _)| canonical_meta_key(k) == canonical) {
+            Some(entry) => entry.1 = value.to_string(),
+            None => self.meta.push((key.to_string(), value.to_string())),
+        }
+        self
+    }
+
+    /// Assemble the document
+    pub fn build(self) -> ApexDocument {
+        let version = self
+            .meta
+            .iter()
+            .find(|(k, _)| canonical_meta_key(k) == "version")
+            .map(|(_, v)| v.clone());
+        let meta = self.meta.into_iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        let sections = [
+            (BlockKind::Task, self.task),
+            (BlockKind::Goals, self.goals),
+            (BlockKind::Plan, self.steps),
+            (BlockKind::Constraints, self.constraints),
+            (BlockKind::Tools, self.tools),
+            (BlockKind::Meta, meta),
+        ];
+
+        let mut blocks = Vec::new();
+        let mut line = 1;
+        for (kind, lines) in sections {
+            if lines.is_empty() {
+                continue;
+            }
+            let span = Span::new(line, line + lines.len());
+            line += lines.len() + 2;
+            blocks.push(Block::new(kind, lines, span));
+        }
+
+        ApexDocument { blocks, version }
+    }
+}
+
+/// A block borrowing its content lines from the parser input
+///
+/// Equality ignores the byte range, as for [`Block`].
+#[derive(Debug, Clone)]
+pub struct BlockRef<'a> {
+    /// Block type
+    pub kind: BlockKind,
+    /// Raw content lines (without the header)
+    pub lines: Vec<&'a str>,
+    /// Source location
+    pub span: Span,
+    /// Byte range in the parser input, as in [`Block::byte_range`]
+    pub byte_range: Range<usize>,
+    /// Source line of each entry in `lines`, as in [`Block::line_numbers`]
+    pub line_numbers: Vec<usize>,
+}
+
+impl PartialEq for BlockRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.lines == other.lines
+            && self.span == other.span
+            && self.line_numbers == other.line_numbers
+    }
+}
+
+impl Eq for BlockRef<'_> {}
+
+impl<'a> BlockRef<'a> {
+    /// Get non-empty trimmed lines
+    pub fn content_lines(&self) -> Vec<&'a str> {
+        self.lines
+            .iter()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    /// Source line (1-indexed) of `lines[idx]`
+    pub fn source_line(&self, idx: usize) -> usize {
+        source_line(&self.line_numbers, self.span, idx)
+    }
+
+    /// Convert into an owned [`Block`]
+    pub fn to_block(&self) -> Block {
+        Block {
+            byte_start: self.byte_range.start,
+            byte_end: self.byte_range.end,
+            line_numbers: self.line_numbers.clone(),
+            ..Block::new(
+                self.kind.clone(),
+                self.lines.iter().map(|l| l.to_string()).collect(),
+                self.span,
+            )
+        }
+    }
+}
+
+/// Explicit line number, or the header line plus the offset for contiguous lines
+fn source_line(line_numbers: &[usize], span: Span, idx: usize) -> usize {
+    line_numbers.get(idx).copied().unwrap_or(span.start_line + 1 + idx)
+}
+
+/// Zero-copy APEX document borrowing from the parser input
+///
+/// Produced by [`crate::parser::parse_str_borrowed`]; call
+/// [`ApexDocumentRef::to_document`] to get an [`ApexDocument`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ApexDocumentRef<'a> {
+    /// All blocks in document order
+    pub blocks: Vec<BlockRef<'a>>,
+}
+
+impl<'a> ApexDocumentRef<'a> {
+    /// Get first block of given kind
+    pub fn get_block(&self, kind: BlockKind) -> Option<&BlockRef<'a>> {
+        self.blocks.iter().find(|b| b.kind == kind)
+    }
+
+    /// Get all blocks of given kind
+    pub fn get_blocks(&self, kind: BlockKind) -> Vec<&BlockRef<'a>> {
+        self.blocks.iter().filter(|b| b.kind == kind).collect()
+    }
+
+    /// Convert into an owned [`ApexDocument`]
+    pub fn to_document(&self) -> ApexDocument {
+        ApexDocument::with_blocks(self.blocks.iter().map(BlockRef::to_block).collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -264,6 +632,7 @@ mod tests {
         assert_eq!(BlockKind::from_str("TASK"), Some(BlockKind::Task));
         assert_eq!(BlockKind::from_str("task"), Some(BlockKind::Task));
         assert_eq!(BlockKind::from_str("Task"), Some(BlockKind::Task));
+        assert_eq!(BlockKind::from_str("WHEN"), Some(BlockKind::When));
         assert_eq!(BlockKind::from_str("UNKNOWN"), None);
     }
 
@@ -283,6 +652,51 @@ mod tests {
         assert!(!block.is_empty());
     }
 
+    #[test]
+    fn test_document_builder() {
+        use crate::validate::{validate_with_mode, ValidationMode};
+
+        let doc = ApexDocumentBuilder::new()
+            .task("Fix search")
+            .goal("Better recall")
+            .step("Scan code")
+            .step("Fix param")
+            .constraint("No Mocks")
+            .tool("code_search \"query\"")
+            .meta("version", "1.0")
+            .meta("Version", "1.1")
+            .build();
+
+        let kinds: Vec<_> = doc.blocks.iter().map(|b| b.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["TASK", "GOALS", "PLAN", "CONSTRAINTS", "TOOLS", "META"]);
+        assert_eq!(doc.version.as_deref(), Some("1.1"));
+        assert_eq!(doc.plan().unwrap().span, Span::new(7, 9));
+        assert_eq!(doc.block_kind_at_line(8), Some(&BlockKind::Plan));
+
+        let validated = validate_with_mode(doc, ValidationMode::Strict, None).unwrap();
+        assert!(validated.warnings.is_empty());
+        assert_eq!(validated.plan.unwrap().steps(), vec!["Scan code", "Fix param"]);
+        assert_eq!(validated.constraints.unwrap().canonical_rules(), vec!["no_mocks"]);
+    }
+
+    #[test]
+    fn test_block_at_line() {
+        let doc = ApexDocument::with_blocks(vec![
+            Block::new(BlockKind::Task, vec!["Do it".to_string()], Span::new(2, 3)),
+            Block::new(BlockKind::Plan, vec!["Step 1".to_string(), "".to_string()], Span::new(6, 8)),
+        ]);
+
+        assert_eq!(doc.block_at_line(1), None);
+        assert_eq!(doc.block_kind_at_line(2), Some(&BlockKind::Task));
+        assert_eq!(doc.block_kind_at_line(3), Some(&BlockKind::Task));
+        // Gap lines belong to the preceding block
+        assert_eq!(doc.block_kind_at_line(5), Some(&BlockKind::Task));
+        assert_eq!(doc.block_kind_at_line(6), Some(&BlockKind::Plan));
+        assert_eq!(doc.block_kind_at_line(8), Some(&BlockKind::Plan));
+        assert_eq!(doc.block_at_line(9), None);
+        assert_eq!(ApexDocument::new().block_at_line(1), None);
+    }
+
     #[test]
     fn test_document_accessors() {
         let doc = ApexDocument::with_blocks(vec![
@@ -295,4 +709,46 @@ mod tests {
         assert!(doc.goals().is_none());
         assert_eq!(doc.count_blocks(BlockKind::Task), 1);
     }
+
+    #[test]
+    fn test_block_iteration_order() {
+        let doc = ApexDocument::with_blocks(vec![
+            Block::new(BlockKind::Task, vec!["X".to_string()], Span::line(1)),
+            Block::new(BlockKind::Plan, vec!["A".to_string()], Span::line(3)),
+            Block::new(BlockKind::Custom("NOTES".to_string()), Vec::new(), Span::line(5)),
+            Block::new(BlockKind::Plan, vec!["B".to_string()], Span::line(7)),
+        ]);
+
+        let lines: Vec<usize> = doc.iter_blocks().map(|b| b.span.start_line).collect();
+        assert_eq!(lines, vec![1, 3, 5, 7]);
+
+        let kinds: Vec<BlockKind> = doc.entries().map(|(kind, _)| kind).collect();
+        assert_eq!(
+            kinds,
+            vec![BlockKind::Task, BlockKind::Plan, BlockKind::Custom("NOTES".to_string()), BlockKind::Plan]
+        );
+        assert!(doc.entries().all(|(kind, block)| kind == block.kind));
+    }
+
+    #[test]
+    fn test_block_registry() {
+        let mut registry = BlockRegistry::new().with_block("BUDGET");
+        assert!(registry.register("OWNER"));
+        assert!(!registry.register("owner"));
+        assert!(!registry.register("PLAN"));
+        assert!(!registry.register("_X"));
+
+        assert_eq!(registry.resolve("PLAN"), Some(BlockKind::Plan));
+        assert_eq!(registry.resolve("budget"), Some(BlockKind::Custom("BUDGET".to_string())));
+        assert_eq!(registry.resolve("COST"), None);
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["BUDGET", "OWNER"]);
+        assert_eq!(BlockKind::Custom("OWNER".to_string()).as_str(), "OWNER");
+
+        // Custom kinds survive a serde round trip
+        let kind = BlockKind::Custom("OWNER".to_string());
+        let json = serde_json::to_string(&kind).unwrap();
+        assert_eq!(json, r#"{"Custom":"OWNER"}"#);
+        let decoded: BlockKind = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, kind);
+    }
 }
@@ -0,0 +1,123 @@
+//! Batch Validation
+//!
+//! Parses and validates many documents at once, collecting every failure
+//! instead of stopping at the first one.
+
+use crate::errors::ApexError;
+use crate::parser::parse_str;
+use crate::validate::{validate_with_mode, ValidationMode, Warning};
+
+/// Outcome for a single document in a batch
+#[derive(Debug, Clone)]
+pub struct DocumentReport {
+    /// Caller-supplied name (e.g. file path)
+    pub name: String,
+    /// Parse or validation error, if the document was rejected
+    pub error: Option<ApexError>,
+    /// Validation warnings (empty when rejected)
+    pub warnings: Vec<Warning>,
+}
+
+impl DocumentReport {
+    /// Check if the document parsed and validated
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Aggregated results of [`validate_batch`], in input order
+#[derive(Debug, Clone, Default)]
+pub struct BatchReport {
+    /// Per-document results
+    pub documents: Vec<DocumentReport>,
+}
+
+impl BatchReport {
+    /// Check if every document is valid
+    pub fn is_all_valid(&self) -> bool {
+        self.documents.iter().all(DocumentReport::is_valid)
+    }
+
+    /// Number of rejected documents
+    pub fn error_count(&self) -> usize {
+        self.documents.iter().filter(|d| !d.is_valid()).count()
+    }
+
+    /// Total warnings across all documents
+    pub fn warning_count(&self) -> usize {
+        self.documents.iter().map(|d| d.warnings.len()).sum()
+    }
+
+    /// Iterate over per-document results
+    pub fn iter(&self) -> std::slice::Iter<'_, DocumentReport> {
+        self.documents.iter()
+    }
+
+    /// Rejected documents only
+    pub fn failures(&self) -> impl Iterator<Item = &DocumentReport> {
+        self.documents.iter().filter(|d| !d.is_valid())
+    }
+}
+
+impl<'a> IntoIterator for &'a BatchReport {
+    type Item = &'a DocumentReport;
+    type IntoIter = std::slice::Iter<'a, DocumentReport>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Parse and validate `(name, content)` pairs, continuing past failures
+pub fn validate_batch(inputs: &[(&str, &str)], mode: ValidationMode) -> BatchReport {
+    let documents = inputs
+        .iter()
+        .map(|(name, content)| {
+            let result = parse_str(content).and_then(|doc| validate_with_mode(doc, mode, None));
+            match result {
+                Ok(validated) => DocumentReport {
+                    name: name.to_string(),
+                    error: None,
+                    warnings: validated.warnings,
+                },
+                Err(err) => DocumentReport {
+                    name: name.to_string(),
+                    error: Some(err),
+                    warnings: Vec::new(),
+                },
+            }
+        })
+        .collect();
+
+    BatchReport { documents }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ApexErrorKind;
+
+    #[test]
+    fn test_validate_batch() {
+        let inputs = [
+            ("ok.apex", "TASK\nDo it\nMETA\nversion=1.1"),
+            ("no_task.apex", "PLAN\nStep 1"),
+            ("warn.apex", "TASK\nDo it"),
+            ("dup.apex", "TASK\nDo it\nGOALS\nA\nGOALS\nB"),
+        ];
+        let report = validate_batch(&inputs, ValidationMode::Strict);
+
+        assert!(!report.is_all_valid());
+        assert_eq!(report.error_count(), 2);
+        assert_eq!(report.documents.len(), 4);
+
+        let names: Vec<_> = report.failures().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["no_task.apex", "dup.apex"]);
+        assert_eq!(
+            report.documents[1].error.as_ref().unwrap().kind,
+            ApexErrorKind::MissingTask
+        );
+        assert_eq!(report.warning_count(), 1);
+        assert_eq!((&report).into_iter().filter(|d| d.is_valid()).count(), 2);
+    }
+}
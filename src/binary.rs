@@ -0,0 +1,307 @@
+//! Compact Binary Encoding
+//!
+//! A dependency-free, self-describing byte format for storing parsed
+//! documents in bulk. Integers are little-endian `u32`; strings are a length
+//! prefix followed by UTF-8 bytes:
+//!
+//! ```text
+//! magic "APX" | format version u8 | has_version u8 [| version str]
+//! block count | per block: kind tag u8 [| custom name str]
+//!             | start_line | end_line | start_col | end_col
+//!             | byte_start | byte_end
+//!             | line number count | line numbers
+//!             | line count | lines (str each)
+//! ```
+
+use crate::ast::{is_block_identifier, ApexDocument, Block, BlockKind, Span};
+use crate::errors::{ApexError, ApexErrorKind, ApexResult};
+
+const MAGIC: &[u8; 3] = b"APX";
+const FORMAT_VERSION: u8 = 1;
+
+/// Tag for [`BlockKind::Custom`], followed by the block name
+const CUSTOM_TAG: u8 = 0xFF;
+
+/// Built-in block kinds in tag order
+const KIND_TAGS: [BlockKind; 11] = [
+    BlockKind::Task,
+    BlockKind::Goals,
+    BlockKind::Plan,
+    BlockKind::Constraints,
+    BlockKind::Validation,
+    BlockKind::Tools,
+    BlockKind::Diff,
+    BlockKind::Context,
+    BlockKind::Meta,
+    BlockKind::When,
+    BlockKind::Retry,
+];
+
+impl ApexDocument {
+    /// Encode the document in the compact binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(FORMAT_VERSION);
+        match &self.version {
+            Some(version) => {
+                out.push(1);
+                write_str(&mut out, version);
+            }
+            None => out.push(0),
+        }
+
+        write_len(&mut out, self.blocks.len());
+        for block in &self.blocks {
+            match &block.kind {
+                BlockKind::Custom(name) => {
+                    out.push(CUSTOM_TAG);
+                    write_str(&mut out, name);
+                }
+                kind => {
+                    let tag = KIND_TAGS.iter().position(|k| k == kind).expect("built-in kind has a tag");
+                    out.push(tag as u8);
+                }
+            }
+            let span = block.span;
+            let location = [span.start_line, span.end_line, span.start_col, span.end_col, block.byte_start, block.byte_end];
+            for value in location {
+                write_len(&mut out, value);
+            }
+            write_len(&mut out, block.line_numbers.len());
+            for &number in &block.line_numbers {
+                write_len(&mut out, number);
+            }
+            write_len(&mut out, block.lines.len());
+            for line in &block.lines {
+                write_str(&mut out, line);
+            }
+        }
+        out
+    }
+
+    /// Decode a document written by [`ApexDocument::to_bytes`]
+    ///
+    /// Fails on a bad header, unknown kind tag, invalid UTF-8, truncated
+    /// input or trailing bytes.
+    pub fn from_bytes(bytes: &[u8]) -> ApexResult<ApexDocument> {
+        let mut reader = Reader { bytes, pos: 0 };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(corrupt("missing APX header"));
+        }
+        let format = reader.u8()?;
+        if format != FORMAT_VERSION {
+            return Err(corrupt(&format!("unsupported format version {}", format)));
+        }
+        let version = match reader.u8()? {
+            0 => None,
+            1 => Some(reader.string()?),
+            flag => return Err(corrupt(&format!("invalid version flag {}", flag))),
+        };
+
+        let block_count = reader.len()?;
+        let mut blocks = Vec::new();
+        for _ in 0..block_count {
+            let kind = match reader.u8()? {
+                CUSTOM_TAG => {
+                    let name = reader.string()?;
+                    if !is_block_identifier(&name) || BlockKind::from_str(&name).is_some() {
+                        return Err(corrupt(&format!("invalid custom block name '{}'", name)));
+                    }
+                    BlockKind::Custom(name)
+                }
+                tag => KIND_TAGS
+                    .get(tag as usize)
+                    .cloned()
+                    .ok_or_else(|| corrupt(&format!("unknown block tag {}", tag)))?,
+            };
+            let span = Span {
+                start_line: reader.len()?,
+                end_line: reader.len()?,
+                start_col: reader.len()?,
+                end_col: reader.len()?,
+            };
+            let (byte_start, byte_end) = (reader.len()?, reader.len()?);
+            let mut line_numbers = Vec::new();
+            for _ in 0..reader.len()? {
+                line_numbers.push(reader.len()?);
+            }
+            let line_count = reader.len()?;
+            let mut lines = Vec::new();
+            for _ in 0..line_count {
+                lines.push(reader.string()?);
+            }
+            blocks.push(Block {
+                byte_start,
+                byte_end,
+                line_numbers,
+                ..Block::new(kind, lines, span)
+            });
+        }
+
+        if reader.pos != bytes.len() {
+            return Err(corrupt(&format!("{} trailing bytes", bytes.len() - reader.pos)));
+        }
+        Ok(ApexDocument { blocks, version })
+    }
+}
+
+fn write_len(out: &mut Vec<u8>, len: usize) {
+    let len = u32::try_from(len).expect("length fits in u32");
+    out.extend_from_slice(&len.to_le_bytes());
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_len(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn corrupt(detail: &str) -> ApexError {
+    ApexError::new(ApexErrorKind::ParseError, format!("Corrupted binary document: {}", detail))
+}
+
+/// Bounds-checked cursor over the encoded bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> ApexResult<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| corrupt("unexpected end of input"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> ApexResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn len(&mut self) -> ApexResult<usize> {
+        let raw: [u8; 4] = self.take(4)?.try_into().expect("4 bytes");
+        Ok(u32::from_le_bytes(raw) as usize)
+    }
+
+    fn string(&mut self) -> ApexResult<String> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| corrupt("invalid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    /// Deterministic xorshift generator, so failures reproduce
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    fn text(rng: &mut Rng) -> String {
+        const ALPHABET: &[char] = &['a', 'Z', '0', ' ', '=', '(', '"', '\t', 'é', '→', '\u{2028}'];
+        (0..rng.below(12)).map(|_| ALPHABET[rng.below(ALPHABET.len())]).collect()
+    }
+
+    fn random_document(rng: &mut Rng) -> ApexDocument {
+        let blocks = (0..rng.below(6))
+            .map(|_| {
+                let tag = rng.below(KIND_TAGS.len() + 1);
+                let custom = ["BUDGET", "OWNER", "RISK_LOG"][rng.below(3)];
+                let kind = KIND_TAGS.get(tag).cloned().unwrap_or_else(|| BlockKind::Custom(custom.to_string()));
+                let lines: Vec<String> = (0..rng.below(5)).map(|_| text(rng)).collect();
+                let line_numbers = match rng.below(2) {
+                    0 => Vec::new(),
+                    _ => (0..lines.len()).map(|_| rng.below(1000)).collect(),
+                };
+                let span = Span {
+                    start_line: rng.below(1000),
+                    end_line: rng.below(1000),
+                    start_col: rng.below(80),
+                    end_col: rng.below(80),
+                };
+                Block {
+                    byte_start: rng.below(1000),
+                    byte_end: rng.below(1000),
+                    line_numbers,
+                    ..Block::new(kind, lines, span)
+                }
+            })
+            .collect();
+        let version = (rng.below(2) == 1).then(|| text(rng));
+        ApexDocument { blocks, version }
+    }
+
+    #[test]
+    fn test_round_trip_random_documents() {
+        let mut rng = Rng(0x5EED_CAFE);
+        for _ in 0..500 {
+            let doc = random_document(&mut rng);
+            let bytes = doc.to_bytes();
+            let decoded = ApexDocument::from_bytes(&bytes).unwrap();
+            assert_eq!(decoded, doc);
+            let ranges = |d: &ApexDocument| d.blocks.iter().map(Block::byte_range).collect::<Vec<_>>();
+            assert_eq!(ranges(&decoded), ranges(&doc));
+
+            // Every strict prefix is rejected rather than misread
+            let cut = rng.below(bytes.len());
+            assert!(ApexDocument::from_bytes(&bytes[..cut]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_round_trip_parsed_document() {
+        let doc = parse_str("TASK\nShip it\nPLAN\nBuild\n\nTest\nMETA\nversion=1.1").unwrap();
+        assert_eq!(ApexDocument::from_bytes(&doc.to_bytes()).unwrap(), doc);
+    }
+
+    #[test]
+    fn test_rejects_corrupt_input() {
+        let mut bytes = parse_str("TASK\nX").unwrap().to_bytes();
+        assert!(ApexDocument::from_bytes(b"JSON").is_err());
+
+        bytes.push(0);
+        let err = ApexDocument::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::ParseError);
+        assert!(err.message.contains("trailing"));
+
+        bytes.pop();
+        let mut other_format = bytes.clone();
+        other_format[MAGIC.len()] = FORMAT_VERSION + 1;
+        let err = ApexDocument::from_bytes(&other_format).unwrap_err();
+        assert!(err.message.contains("unsupported format version 2"));
+
+        // Block tag sits after magic, format, version flag and block count
+        bytes[9] = 42;
+        assert!(ApexDocument::from_bytes(&bytes).unwrap_err().message.contains("unknown block tag 42"));
+
+        // Custom block names must be uppercase identifiers
+        for name in ["budget", "BUD-ET"] {
+            let budget = Block::new(BlockKind::Custom("BUDGET".to_string()), Vec::new(), Span::line(1));
+            let doc = ApexDocument::with_blocks(vec![budget]);
+            let mut bytes = doc.to_bytes();
+            let at = bytes.windows(6).position(|w| w == b"BUDGET").unwrap();
+            bytes[at..at + 6].copy_from_slice(name.as_bytes());
+            let err = ApexDocument::from_bytes(&bytes).unwrap_err();
+            assert!(err.message.contains("invalid custom block name"), "{}", name);
+        }
+    }
+}
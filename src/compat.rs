@@ -0,0 +1,94 @@
+//! APEX v1.0 → v1.1 Compatibility
+//!
+//! Migration helpers that rewrite v1.0 documents into canonical v1.1 form:
+//!
+//! - `version=1.1` added to META (META created if absent)
+//! - CONSTRAINTS lines canonicalized in place
+//! - `raw` format marker added to DIFF blocks without one
+
+use crate::ast::{ApexDocument, Block, BlockKind, Span};
+use crate::sem::canonicalize_constraint;
+use crate::validate::canonical_meta_key;
+use crate::APEX_VERSION;
+
+/// Upgrade a v1.0 document to v1.1 form
+///
+/// Returns the upgraded document and a changelog describing each change.
+/// The input document is left untouched.
+pub fn upgrade_to_v1_1(doc: &ApexDocument) -> (ApexDocument, Vec<String>) {
+    let mut upgraded = doc.clone();
+    let mut changelog = Vec::new();
+
+    for block in &mut upgraded.blocks {
+        match block.kind {
+            BlockKind::Constraints => canonicalize_constraints(block, &mut changelog),
+            BlockKind::Diff => add_diff_marker(block, &mut changelog),
+            _ => {}
+        }
+    }
+
+    set_version(&mut upgraded, &mut changelog);
+    upgraded.version = Some(APEX_VERSION.to_string());
+
+    (upgraded, changelog)
+}
+
+fn canonicalize_constraints(block: &mut Block, changelog: &mut Vec<String>) {
+    for line in &mut block.lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let canonical = canonicalize_constraint(trimmed);
+        if canonical != trimmed {
+            changelog.push(format!("Canonicalized constraint '{}' to '{}'", trimmed, canonical));
+            *line = canonical;
+        }
+    }
+}
+
+fn add_diff_marker(block: &mut Block, changelog: &mut Vec<String>) {
+    let first = block.content_lines().first().map(|l| l.to_lowercase());
+    if matches!(first.as_deref(), Some("unified") | Some("raw")) {
+        return;
+    }
+    block.lines.insert(0, "raw".to_string());
+    changelog.push(format!(
+        "Added 'raw' format marker to DIFF block at line {}",
+        block.span.start_line
+    ));
+}
+
+fn set_version(doc: &mut ApexDocument, changelog: &mut Vec<String>) {
+    let target = format!("version={}", APEX_VERSION);
+
+    if let Some(meta) = doc.blocks.iter_mut().find(|b| b.kind == BlockKind::Meta) {
+        let existing = meta.lines.iter().position(|l| meta_key(l).as_deref() == Some("version"));
+        match existing {
+            Some(idx) if meta.lines[idx].trim() == target => {}
+            Some(idx) => {
+                changelog.push(format!("Replaced '{}' with '{}' in META", meta.lines[idx].trim(), target));
+                meta.lines[idx] = target;
+            }
+            None => {
+                changelog.push(format!("Added '{}' to META", target));
+                meta.lines.push(target);
+            }
+        }
+        return;
+    }
+
+    let next_line = doc.blocks.iter().map(|b| b.span.end_line).max().unwrap_or(0) + 2;
+    doc.blocks.push(Block::new(
+        BlockKind::Meta,
+        vec![target.clone()],
+        Span::new(next_line, next_line + 1),
+    ));
+    changelog.push(format!("Created META block with '{}'", target));
+}
+
+/// Canonical key of a `key=value` / `key: value` META line
+pub(crate) fn meta_key(line: &str) -> Option<String> {
+    let idx = line.find(['=', ':'])?;
+    Some(canonical_meta_key(&line[..idx]))
+}
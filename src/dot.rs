@@ -0,0 +1,75 @@
+//! Graphviz DOT Export
+//!
+//! Renders an execution plan's dependency graph for `dot -Tsvg`: one node per
+//! step, labeled with its number and a truncated description, and an edge
+//! from each dependency to its dependent. Steps bound to a tool are drawn as
+//! filled boxes.
+
+use crate::interpreter::ExecutionPlan;
+use std::fmt::Write;
+
+/// Longest description (in characters) shown in a node label
+const MAX_LABEL_CHARS: usize = 40;
+
+impl ExecutionPlan {
+    /// Render the step dependency graph as Graphviz DOT
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph plan {\n    rankdir=TB;\n    node [shape=ellipse];\n");
+        for step in &self.steps {
+            let label = format!("{}. {}", step.step_number, truncate(&step.description));
+            let _ = match &step.tool {
+                Some(tool) => writeln!(
+                    out,
+                    "    step{} [label=\"{}\", shape=box, style=filled, fillcolor=lightblue, tooltip=\"{}\"];",
+                    step.step_number,
+                    escape(&label),
+                    escape(&tool.name)
+                ),
+                None => writeln!(out, "    step{} [label=\"{}\"];", step.step_number, escape(&label)),
+            };
+        }
+        for step in &self.steps {
+            for dep in &step.depends_on {
+                let _ = writeln!(out, "    step{} -> step{};", dep, step.step_number);
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Shorten a description to [`MAX_LABEL_CHARS`], marking the cut with `...`
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_LABEL_CHARS {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(MAX_LABEL_CHARS - 3).collect();
+    format!("{}...", kept.trim_end())
+}
+
+/// Escape a string for a double-quoted DOT attribute
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_full;
+
+    #[test]
+    fn test_to_dot() {
+        let input = "TASK\nShip\nPLAN\nSearch code for \"auth\"\nEdit files [after: 1]\nRun the full integration test suite against staging [after: 1, 2]\nTOOLS\ncode_search";
+        let plan = parse_full(input).unwrap();
+        let dot = plan.to_dot();
+
+        let deps: usize = plan.steps.iter().map(|s| s.depends_on.len()).sum();
+        assert_eq!(dot.matches(" -> ").count(), deps);
+        assert!(dot.contains("    step1 -> step3;\n"));
+        assert!(dot.contains("    step2 -> step3;\n"));
+
+        assert!(dot.starts_with("digraph plan {\n"));
+        assert!(dot.contains("label=\"1. Search code for \\\"auth\\\"\", shape=box"));
+        assert!(dot.contains("    step2 [label=\"2. Edit files\"];\n"));
+        assert!(dot.contains("label=\"3. Run the full integration test suite a...\""));
+    }
+}
@@ -0,0 +1,154 @@
+//! Semantic Document Equality
+//!
+//! Compares documents by meaning rather than by bytes, for checking
+//! generated plans against golden expectations. Unlike the derived
+//! `PartialEq`, [`ApexDocument::semantically_eq`] ignores:
+//!
+//! - Header casing and blank lines (both are gone after parsing)
+//! - Leading/trailing whitespace and runs of inner whitespace in every line
+//! - Block order, and how a kind's content is split across repeated blocks
+//! - TASK line breaks (lines are joined with a single space)
+//! - Order and duplicates of GOALS, CONSTRAINTS and TOOLS entries
+//! - Goal and step phrasing differences removed by [`canonicalize`] (case,
+//!   punctuation), and PLAN step markers (`1.`, `-`) and indentation
+//! - Constraint phrasing removed by [`canonicalize_constraint`], and an
+//!   explicit `MUST:` prefix (the default severity)
+//! - TOOLS arguments (only tool names are compared)
+//! - META entry order and key casing
+//!
+//! PLAN step order is significant, as are the lines of every other block
+//! (VALIDATION, DIFF, CONTEXT, WHEN, RETRY and custom blocks), which are
+//! compared whitespace-normalized in document order.
+
+use crate::ast::{ApexDocument, BlockKind};
+use crate::sem::{canonicalize, canonicalize_constraint, Severity};
+use crate::tool_registry::extract_tool_name;
+use crate::validate::{canonical_meta_key, split_meta_line, strip_step_marker};
+use std::collections::BTreeSet;
+
+/// Formatting-independent summary of a document
+#[derive(Debug, PartialEq)]
+struct SemanticKey {
+    task: String,
+    goals: BTreeSet<String>,
+    steps: Vec<String>,
+    constraints: BTreeSet<(Severity, String)>,
+    tools: BTreeSet<String>,
+    meta: BTreeSet<(String, String)>,
+    other: Vec<(String, Vec<String>)>,
+}
+
+impl ApexDocument {
+    /// Check if two documents mean the same, ignoring formatting
+    ///
+    /// See the [module docs](crate::equivalence) for exactly what is ignored.
+    pub fn semantically_eq(&self, other: &ApexDocument) -> bool {
+        SemanticKey::of(self) == SemanticKey::of(other)
+    }
+}
+
+impl SemanticKey {
+    fn of(doc: &ApexDocument) -> Self {
+        let lines = |kind: BlockKind| -> Vec<String> {
+            doc.get_blocks(kind)
+                .into_iter()
+                .flat_map(|b| b.content_lines())
+                .map(collapse_whitespace)
+                .collect()
+        };
+
+        let meta = lines(BlockKind::Meta)
+            .into_iter()
+            .map(|line| match split_meta_line(&line) {
+                Some((key, value)) => (canonical_meta_key(key), value.to_string()),
+                None => (line, String::new()),
+            })
+            .collect();
+
+        let other = doc
+            .blocks
+            .iter()
+            .filter(|b| {
+                !matches!(
+                    b.kind,
+                    BlockKind::Task
+                        | BlockKind::Goals
+                        | BlockKind::Plan
+                        | BlockKind::Constraints
+                        | BlockKind::Tools
+                        | BlockKind::Meta
+                )
+            })
+            .map(|b| {
+                let content = b.content_lines().into_iter().map(collapse_whitespace).collect();
+                (b.kind.as_str().to_string(), content)
+            })
+            .collect();
+
+        Self {
+            task: lines(BlockKind::Task).join(" "),
+            goals: lines(BlockKind::Goals).iter().map(|g| canonicalize(g)).collect(),
+            steps: lines(BlockKind::Plan)
+                .iter()
+                .map(|s| canonicalize(strip_step_marker(s)))
+                .collect(),
+            constraints: lines(BlockKind::Constraints)
+                .iter()
+                .map(|c| {
+                    let (severity, rule) = Severity::split_prefix(c);
+                    (severity, canonicalize_constraint(rule))
+                })
+                .collect(),
+            tools: lines(BlockKind::Tools)
+                .iter()
+                .map(|t| extract_tool_name(t).to_string())
+                .collect(),
+            meta,
+            other,
+        }
+    }
+}
+
+/// Trim a line and collapse inner whitespace runs to single spaces
+fn collapse_whitespace(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse_str, parse_str_with_mode, ParseMode};
+
+    #[test]
+    fn test_semantically_eq_ignores_formatting() {
+        let golden = parse_str(
+            "TASK\nFix the login bug\nGOALS\nTests pass\nNo regressions\nPLAN\nReproduce the bug\nFix the handler\n\
+             CONSTRAINTS\nno_mocks\nSHOULD: real dbs\nTOOLS\ncode_search(query)\nMETA\nversion=1.1\nauthor=a",
+        )
+        .unwrap();
+        let generated = parse_str_with_mode(
+            "meta\nAuthor: a\nversion = 1.1\n\ntask\n  Fix   the login bug\n\ngoals\nno regressions.\nTests pass\nTests pass\n\
+             \nplan\n1. Reproduce the bug\n2. Fix the handler!\n\nconstraints\nMUST: No Mocks\nSHOULD: Real DBs\n\
+             \ntools\ncode_search(\"login\")\n",
+            ParseMode::Tolerant,
+        )
+        .unwrap()
+        .document;
+
+        assert_ne!(golden, generated);
+        assert!(golden.semantically_eq(&generated));
+        assert!(generated.semantically_eq(&golden));
+    }
+
+    #[test]
+    fn test_semantically_eq_detects_differences() {
+        let base = parse_str("TASK\nShip\nPLAN\nBuild\nTest\nCONSTRAINTS\nno mocks\nVALIDATION\ncargo test").unwrap();
+        let differs = |input: &str| !base.semantically_eq(&parse_str(input).unwrap());
+
+        assert!(differs("TASK\nShip it\nPLAN\nBuild\nTest\nCONSTRAINTS\nno mocks\nVALIDATION\ncargo test"));
+        assert!(differs("TASK\nShip\nPLAN\nTest\nBuild\nCONSTRAINTS\nno mocks\nVALIDATION\ncargo test"));
+        assert!(differs("TASK\nShip\nPLAN\nBuild\nTest\nCONSTRAINTS\nSHOULD: no mocks\nVALIDATION\ncargo test"));
+        assert!(differs("TASK\nShip\nPLAN\nBuild\nTest\nCONSTRAINTS\nno mocks\nVALIDATION\ncargo build"));
+        assert!(differs("TASK\nShip\nPLAN\nBuild\nTest\nCONSTRAINTS\nno mocks"));
+        assert!(!differs("TASK\nShip\nVALIDATION\ncargo   test\nCONSTRAINTS\nNo Mocks\nPLAN\n- Build\n- Test"));
+    }
+}
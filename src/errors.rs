@@ -2,6 +2,7 @@
 //!
 //! Unified error handling across parse, validate, and interpret phases.
 
+use crate::ast::Span;
 use std::fmt;
 
 /// Error kind categories
@@ -15,6 +16,8 @@ pub enum ApexErrorKind {
     MissingTask,
     /// Multiple TASK blocks found
     MultipleTasks,
+    /// Optional block appears more than once
+    DuplicateBlock,
     /// Required block is empty
     EmptyRequiredBlock,
     /// Unknown block identifier
@@ -25,6 +28,14 @@ pub enum ApexErrorKind {
     ConstraintViolation,
     /// Validation condition failed
     ValidationFailure,
+    /// PLAN step annotation references a step that does not exist
+    InvalidStepReference,
+    /// Step dependencies form a cycle
+    DependencyCycle,
+    /// Documents cannot be merged without overwriting content
+    MergeConflict,
+    /// Reading input or a referenced file failed
+    Io,
     /// Internal error (should not happen)
     InternalError,
 }
@@ -36,11 +47,16 @@ impl fmt::Display for ApexErrorKind {
             ApexErrorKind::ParseError => write!(f, "ParseError"),
             ApexErrorKind::MissingTask => write!(f, "MissingTask"),
             ApexErrorKind::MultipleTasks => write!(f, "MultipleTasks"),
+            ApexErrorKind::DuplicateBlock => write!(f, "DuplicateBlock"),
             ApexErrorKind::EmptyRequiredBlock => write!(f, "EmptyRequiredBlock"),
             ApexErrorKind::UnknownBlock => write!(f, "UnknownBlock"),
             ApexErrorKind::InvalidToolName => write!(f, "InvalidToolName"),
             ApexErrorKind::ConstraintViolation => write!(f, "ConstraintViolation"),
             ApexErrorKind::ValidationFailure => write!(f, "ValidationFailure"),
+            ApexErrorKind::InvalidStepReference => write!(f, "InvalidStepReference"),
+            ApexErrorKind::DependencyCycle => write!(f, "DependencyCycle"),
+            ApexErrorKind::MergeConflict => write!(f, "MergeConflict"),
+            ApexErrorKind::Io => write!(f, "Io"),
             ApexErrorKind::InternalError => write!(f, "InternalError"),
         }
     }
@@ -57,6 +73,8 @@ pub struct ApexError {
     pub line: Option<usize>,
     /// Column number (1-indexed)
     pub column: Option<usize>,
+    /// Full source extent of the offending item, see [`ApexError::span`]
+    pub(crate) span: Option<Span>,
 }
 
 impl ApexError {
@@ -67,6 +85,7 @@ impl ApexError {
             message: message.into(),
             line: None,
             column: None,
+            span: None,
         }
     }
 
@@ -82,6 +101,18 @@ impl ApexError {
         self
     }
 
+    /// Create error covering a source span (line is set to the span start)
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.line = Some(span.start_line);
+        self.span = Some(span);
+        self
+    }
+
+    /// Full source extent of the offending item (may cover several lines)
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
     // --- Convenience constructors ---
 
     /// Parse error at optional line
@@ -109,6 +140,29 @@ impl ApexError {
             .with_line(line)
     }
 
+    /// Duplicate optional block
+    pub fn duplicate_block(kind: &str, line: usize) -> Self {
+        Self::new(
+            ApexErrorKind::DuplicateBlock,
+            format!("APEX document contains multiple {} blocks", kind),
+        )
+        .with_line(line)
+    }
+
+    /// Tool not found in registry, located at its TOOLS declaration
+    pub fn unknown_tool(message: impl Into<String>, span: Span) -> Self {
+        Self::new(ApexErrorKind::InvalidToolName, message).with_span(span)
+    }
+
+    /// META version outside the supported range, located at the META block
+    pub fn unsupported_version(version: &str, span: Span) -> Self {
+        Self::new(
+            ApexErrorKind::ValidationFailure,
+            format!("Unsupported APEX version: {}", version),
+        )
+        .with_span(span)
+    }
+
     /// Empty required block
     pub fn empty_block(name: &str, line: Option<usize>) -> Self {
         let mut err = Self::new(
@@ -144,13 +198,99 @@ impl ApexError {
             format!("Validation failed: {}", condition),
         )
     }
+
+    /// No declared tool matches a step (strict tool matching)
+    pub fn unmatched_step_tool(step: usize, description: &str) -> Self {
+        Self::new(
+            ApexErrorKind::InvalidToolName,
+            format!("No tool matches step {}: '{}'", step, description),
+        )
+    }
+
+    /// Step annotation references an unknown step
+    pub fn invalid_step_reference(step: usize, referenced: usize) -> Self {
+        Self::new(
+            ApexErrorKind::InvalidStepReference,
+            format!("Step {} references unknown step {}", step, referenced),
+        )
+    }
+
+    /// Step condition references the step it is attached to
+    pub fn self_step_reference(step: usize) -> Self {
+        Self::new(
+            ApexErrorKind::InvalidStepReference,
+            format!("Step {} condition references itself", step),
+        )
+    }
+
+    /// Dependency cycle among the given steps
+    pub fn dependency_cycle(steps: &[usize]) -> Self {
+        let list: Vec<String> = steps.iter().map(|s| s.to_string()).collect();
+        Self::new(
+            ApexErrorKind::DependencyCycle,
+            format!("Dependency cycle among steps: {}", list.join(", ")),
+        )
+    }
+}
+
+/// Lines of source shown before and after the offending line
+const CONTEXT_LINES: usize = 2;
+
+impl ApexError {
+    /// Render the error with the offending source line and a caret marker
+    ///
+    /// Produces a rustc-style report with up to two lines of context on each
+    /// side. The caret covers the span's columns when the span is on a single
+    /// line, otherwise it points at `column` (or the first non-blank
+    /// character). Without a usable line number only the headline is printed.
+    pub fn render_with_source(&self, input: &str) -> String {
+        let mut out = format!("error[{}]: {}\n", self.kind, self.message);
+        let lines = crate::parser::lexer::split_lines(input);
+        let Some(line) = self.line.filter(|l| *l >= 1 && *l <= lines.len()) else {
+            return out;
+        };
+
+        let text = lines[line - 1];
+        let (start_col, width) = match self.span {
+            Some(span) if span.start_line == line && span.end_line == line && span.end_col >= span.start_col => {
+                (self.column.unwrap_or(span.start_col), span.end_col + 1 - span.start_col)
+            }
+            _ => {
+                let first = text.chars().take_while(|c| c.is_whitespace()).count() + 1;
+                (self.column.unwrap_or(first), 1)
+            }
+        };
+
+        let first = line.saturating_sub(CONTEXT_LINES).max(1);
+        let last = (line + CONTEXT_LINES).min(lines.len());
+        let gutter = last.to_string().len();
+
+        out.push_str(&format!("{:>gutter$}--> line {}, column {}\n", "", line, start_col));
+        out.push_str(&format!("{:>gutter$} |\n", ""));
+        for n in first..=last {
+            out.push_str(&format!("{:>gutter$} | {}\n", n, lines[n - 1]));
+            if n == line {
+                out.push_str(&format!(
+                    "{:>gutter$} | {}{}\n",
+                    "",
+                    " ".repeat(start_col.saturating_sub(1)),
+                    "^".repeat(width.max(1))
+                ));
+            }
+        }
+        out
+    }
 }
 
 impl fmt::Display for ApexError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "[{}] {}", self.kind, self.message)?;
-        if let Some(line) = self.line {
-            write!(f, " (line {})", line)?;
+        match (self.span, self.line) {
+            (Some(span), _) if span.end_line > span.start_line => {
+                write!(f, " (lines {}-{})", span.start_line, span.end_line)?;
+            }
+            (_, Some(line)) => write!(f, " (line {})", line)?,
+            _ => {}
         }
         Ok(())
     }
@@ -178,4 +318,43 @@ mod tests {
         assert_eq!(err.line, Some(42));
         assert!(err.to_string().contains("line 42"));
     }
+
+    #[test]
+    fn test_render_with_source() {
+        let input = "TASK\nDo it\nTOOLS\n  code_serch(\"x\")\nMETA\nversion=1.1";
+        let mut span = Span::line(4);
+        span.start_col = 3;
+        span.end_col = 12;
+        let err = ApexError::new(ApexErrorKind::InvalidToolName, "Unknown tool 'code_serch'").with_span(span);
+
+        let expected = [
+            "error[InvalidToolName]: Unknown tool 'code_serch'",
+            " --> line 4, column 3",
+            "  |",
+            "2 | Do it",
+            "3 | TOOLS",
+            "4 |   code_serch(\"x\")",
+            "  |   ^^^^^^^^^^",
+            "5 | META",
+            "6 | version=1.1",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(err.render_with_source(input), expected);
+
+        let err = ApexError::parse("bad header", Some(1));
+        assert!(err.render_with_source(input).contains("1 | TASK\n  | ^\n"));
+
+        let err = ApexError::missing_task();
+        assert_eq!(err.render_with_source(input), "error[MissingTask]: APEX document must contain exactly one TASK block\n");
+        assert_eq!(ApexError::parse("x", Some(99)).render_with_source(input).lines().count(), 1);
+    }
+
+    #[test]
+    fn test_error_with_span() {
+        let err = ApexError::parse("bad item", None).with_span(Span::new(4, 6));
+        assert_eq!(err.line, Some(4));
+        assert_eq!(err.span(), Some(Span::new(4, 6)));
+        assert!(err.to_string().contains("lines 4-6"));
+    }
 }
@@ -7,9 +7,15 @@
 //! Per APEX v1.1, execution state is stored out-of-band (not in APEX syntax).
 //! This module provides types for tracking step status and checkpointing.
 
-use crate::errors::ApexResult;
-use crate::validate::{ValidatedDocument, ToolDeclaration};
+use crate::errors::{ApexError, ApexErrorKind, ApexResult};
+use crate::sem::canonicalize_constraint;
+use crate::tool_registry::{parse_mcp_tool, split_tool_namespace, ToolCategory, ToolRegistry, TOOL_CATEGORIES};
+use crate::validate::{split_after, ValidatedDocument, ToolDeclaration};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::time::Duration;
 
 // ============================================================
 // v1.1 Execution State Model
@@ -29,12 +35,17 @@ pub enum StepStatus {
     Failed,
     /// Step was skipped (due to earlier failure or constraint)
     Skipped,
+    /// Step was abandoned because the run was cancelled
+    Cancelled,
 }
 
 impl StepStatus {
     /// Check if step is in a terminal state
     pub fn is_terminal(&self) -> bool {
-        matches!(self, StepStatus::Complete | StepStatus::Failed | StepStatus::Skipped)
+        matches!(
+            self,
+            StepStatus::Complete | StepStatus::Failed | StepStatus::Skipped | StepStatus::Cancelled
+        )
     }
 
     /// Check if step can be resumed
@@ -43,6 +54,33 @@ impl StepStatus {
     }
 }
 
+/// Result recorded for a completed step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub enum StepResult {
+    /// No result (step not completed, or the tool returned nothing)
+    #[default]
+    Empty,
+    /// Plain-text output
+    Text(String),
+    /// Structured output, kept as JSON through checkpoints
+    Json(serde_json::Value),
+}
+
+impl StepResult {
+    /// Check if there is no result
+    pub fn is_empty(&self) -> bool {
+        matches!(self, StepResult::Empty)
+    }
+
+    /// Text output, if the result is `Text`
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            StepResult::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
 /// Execution state for tracking plan progress (v1.1)
 ///
 /// This is stored out-of-band, not in APEX syntax.
@@ -54,13 +92,19 @@ pub struct ExecutionState {
     /// Index of last completed step (0 = none completed)
     pub checkpoint: usize,
     /// Tool results for completed steps
-    pub tool_results: Vec<Option<String>>,
+    pub tool_results: Vec<StepResult>,
     /// Validation outcomes
     pub validation_outcomes: Vec<bool>,
     /// Whether execution is paused
     pub paused: bool,
     /// Error message if execution failed
     pub error: Option<String>,
+    /// Retry attempts made for each step
+    #[serde(default)]
+    pub attempts: Vec<u32>,
+    /// Step indices (0-based) in the order they were started
+    #[serde(default)]
+    pub execution_order: Vec<usize>,
 }
 
 impl ExecutionState {
@@ -69,10 +113,12 @@ impl ExecutionState {
         Self {
             step_states: vec![StepStatus::Pending; num_steps],
             checkpoint: 0,
-            tool_results: vec![None; num_steps],
+            tool_results: vec![StepResult::Empty; num_steps],
             validation_outcomes: Vec::new(),
             paused: false,
             error: None,
+            attempts: vec![0; num_steps],
+            execution_order: Vec::new(),
         }
     }
 
@@ -81,25 +127,42 @@ impl ExecutionState {
         self.checkpoint
     }
 
-    /// Check if execution is complete
+    /// Check if execution is complete (every step terminal, run not cancelled)
     pub fn is_complete(&self) -> bool {
-        self.step_states.iter().all(|s| s.is_terminal())
+        self.step_states.iter().all(|s| s.is_terminal()) && !self.is_cancelled()
     }
 
-    /// Check if execution failed
+    /// Check if execution failed (cancellation is not a failure)
     pub fn is_failed(&self) -> bool {
         self.step_states.iter().any(|s| matches!(s, StepStatus::Failed))
     }
 
+    /// Check if execution was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.step_states.iter().any(|s| matches!(s, StepStatus::Cancelled))
+    }
+
+    /// Cancel the run: mark every non-terminal step `Cancelled`
+    ///
+    /// Completed, failed and skipped steps keep their status.
+    pub fn cancel(&mut self) {
+        for status in self.step_states.iter_mut().filter(|s| !s.is_terminal()) {
+            *status = StepStatus::Cancelled;
+        }
+        self.paused = false;
+        self.error = Some("cancelled".to_string());
+    }
+
     /// Mark a step as running
     pub fn start_step(&mut self, step: usize) {
         if step < self.step_states.len() {
             self.step_states[step] = StepStatus::Running;
+            self.execution_order.push(step);
         }
     }
 
-    /// Mark a step as complete with optional result
-    pub fn complete_step(&mut self, step: usize, result: Option<String>) {
+    /// Mark a step as complete with its result
+    pub fn complete_step(&mut self, step: usize, result: StepResult) {
         if step < self.step_states.len() {
             self.step_states[step] = StepStatus::Complete;
             self.tool_results[step] = result;
@@ -107,6 +170,11 @@ impl ExecutionState {
         }
     }
 
+    /// Mark a step as complete with optional text result
+    pub fn complete_step_text(&mut self, step: usize, result: Option<String>) {
+        self.complete_step(step, result.map_or(StepResult::Empty, StepResult::Text));
+    }
+
     /// Mark a step as failed with error
     pub fn fail_step(&mut self, step: usize, error: String) {
         if step < self.step_states.len() {
@@ -115,12 +183,176 @@ impl ExecutionState {
         }
     }
 
+    /// Retry a step if attempts remain
+    ///
+    /// Increments the attempt counter and resets the step to `Pending` while
+    /// fewer than `max_retries` retries were made; otherwise the step is left
+    /// `Failed`. Returns whether a retry was scheduled.
+    pub fn retry_step(&mut self, step: usize, max_retries: u32) -> bool {
+        if step >= self.step_states.len() {
+            return false;
+        }
+        if self.attempts.len() < self.step_states.len() {
+            self.attempts.resize(self.step_states.len(), 0);
+        }
+
+        if self.attempts[step] < max_retries {
+            self.attempts[step] += 1;
+            self.step_states[step] = StepStatus::Pending;
+            true
+        } else {
+            self.step_states[step] = StepStatus::Failed;
+            false
+        }
+    }
+
+    /// Mark a step as failed because it exceeded its timeout
+    pub fn timeout_step(&mut self, step: usize) {
+        self.fail_step(step, "timeout".to_string());
+    }
+
     /// Skip a step
     pub fn skip_step(&mut self, step: usize) {
         if step < self.step_states.len() {
             self.step_states[step] = StepStatus::Skipped;
         }
     }
+
+    /// Write state to `path` as a JSON checkpoint
+    pub fn save_checkpoint(&self, path: &Path) -> ApexResult<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            ApexError::new(ApexErrorKind::InternalError, format!("Failed to serialize checkpoint: {}", e))
+        })?;
+        std::fs::write(path, json).map_err(|e| {
+            ApexError::new(
+                ApexErrorKind::InternalError,
+                format!("Failed to write checkpoint {}: {}", path.display(), e),
+            )
+        })
+    }
+
+    /// Read a JSON checkpoint written by [`ExecutionState::save_checkpoint`]
+    ///
+    /// Fails if the file is unreadable, not valid JSON, or internally inconsistent.
+    pub fn load_checkpoint(path: &Path) -> ApexResult<Self> {
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            ApexError::new(
+                ApexErrorKind::InternalError,
+                format!("Failed to read checkpoint {}: {}", path.display(), e),
+            )
+        })?;
+        let state: Self = serde_json::from_str(&json).map_err(|e| {
+            ApexError::new(
+                ApexErrorKind::InternalError,
+                format!("Corrupted checkpoint {}: {}", path.display(), e),
+            )
+        })?;
+
+        if state.step_states.len() != state.tool_results.len() {
+            return Err(ApexError::new(
+                ApexErrorKind::InternalError,
+                format!(
+                    "Corrupted checkpoint {}: {} step states but {} tool results",
+                    path.display(),
+                    state.step_states.len(),
+                    state.tool_results.len()
+                ),
+            ));
+        }
+
+        Ok(state)
+    }
+}
+
+/// Snapshot of run progress for progress bars and metrics
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgressSummary {
+    /// Total number of steps
+    pub total: usize,
+    /// Steps not yet started
+    pub pending: usize,
+    /// Steps currently running
+    pub running: usize,
+    /// Steps completed successfully
+    pub complete: usize,
+    /// Steps that failed
+    pub failed: usize,
+    /// Steps that were skipped
+    pub skipped: usize,
+    /// Steps abandoned by cancellation
+    #[serde(default)]
+    pub cancelled: usize,
+    /// Share of steps in a terminal state (0.0-100.0; 100.0 for empty plans)
+    pub percent_complete: f64,
+    /// Index (0-based) of the first running step
+    pub running_step: Option<usize>,
+    /// Description of the running step (set by [`ExecutionState::progress_for`])
+    pub running_description: Option<String>,
+}
+
+/// Terminal steps split by outcome (0-based indices)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElapsedSteps {
+    /// Steps that completed successfully
+    pub completed: Vec<usize>,
+    /// Steps that were skipped
+    pub skipped: Vec<usize>,
+    /// Steps that failed
+    pub failed: Vec<usize>,
+    /// Steps abandoned by cancellation
+    #[serde(default)]
+    pub cancelled: Vec<usize>,
+}
+
+impl ExecutionState {
+    /// Count steps per status and locate the running step
+    pub fn progress(&self) -> ProgressSummary {
+        let count = |status: StepStatus| self.step_states.iter().filter(|s| **s == status).count();
+        let total = self.step_states.len();
+        let terminal = self.step_states.iter().filter(|s| s.is_terminal()).count();
+
+        ProgressSummary {
+            total,
+            pending: count(StepStatus::Pending),
+            running: count(StepStatus::Running),
+            complete: count(StepStatus::Complete),
+            failed: count(StepStatus::Failed),
+            skipped: count(StepStatus::Skipped),
+            cancelled: count(StepStatus::Cancelled),
+            percent_complete: if total == 0 {
+                100.0
+            } else {
+                terminal as f64 * 100.0 / total as f64
+            },
+            running_step: self.step_states.iter().position(|s| *s == StepStatus::Running),
+            running_description: None,
+        }
+    }
+
+    /// Like [`ExecutionState::progress`], with the running step's description
+    pub fn progress_for(&self, plan: &ExecutionPlan) -> ProgressSummary {
+        let mut summary = self.progress();
+        summary.running_description = summary
+            .running_step
+            .and_then(|i| plan.steps.get(i))
+            .map(|s| s.description.clone());
+        summary
+    }
+
+    /// Terminal steps, distinguishing completed from skipped and failed
+    pub fn elapsed_steps(&self) -> ElapsedSteps {
+        let mut elapsed = ElapsedSteps::default();
+        for (i, status) in self.step_states.iter().enumerate() {
+            match status {
+                StepStatus::Complete => elapsed.completed.push(i),
+                StepStatus::Skipped => elapsed.skipped.push(i),
+                StepStatus::Failed => elapsed.failed.push(i),
+                StepStatus::Cancelled => elapsed.cancelled.push(i),
+                StepStatus::Pending | StepStatus::Running => {}
+            }
+        }
+        elapsed
+    }
 }
 
 impl Default for ExecutionState {
@@ -129,6 +361,119 @@ impl Default for ExecutionState {
     }
 }
 
+// ============================================================
+// Conditional Execution
+// ============================================================
+
+/// Guard polarity for a conditional step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConditionKind {
+    /// `{if: ...}` - run only when the guard holds
+    If,
+    /// `{unless: ...}` - run only when the guard does not hold
+    Unless,
+}
+
+/// Outcome of a referenced step tested by a condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StepOutcome {
+    /// Step completed successfully
+    Success,
+    /// Step failed
+    Failed,
+    /// Step was skipped
+    Skipped,
+}
+
+impl StepOutcome {
+    /// Parse outcome keyword (`success`, `failed`, `skipped`)
+    pub fn from_keyword(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "success" | "succeeded" | "complete" => Some(StepOutcome::Success),
+            "failed" | "failure" => Some(StepOutcome::Failed),
+            "skipped" => Some(StepOutcome::Skipped),
+            _ => None,
+        }
+    }
+
+    /// Get canonical keyword
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepOutcome::Success => "success",
+            StepOutcome::Failed => "failed",
+            StepOutcome::Skipped => "skipped",
+        }
+    }
+
+    /// Check if a step status matches this outcome
+    pub fn matches(&self, status: StepStatus) -> bool {
+        matches!(
+            (self, status),
+            (StepOutcome::Success, StepStatus::Complete)
+                | (StepOutcome::Failed, StepStatus::Failed)
+                | (StepOutcome::Skipped, StepStatus::Skipped)
+        )
+    }
+}
+
+/// Conditional execution guard on a PLAN step
+///
+/// Written as a trailing annotation on the step line:
+/// `Deploy build {if: step2.success}` or `Notify team {unless: step3.failed}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepCondition {
+    /// Guard polarity
+    pub kind: ConditionKind,
+    /// Referenced step number (1-indexed)
+    pub step: usize,
+    /// Outcome tested on the referenced step
+    pub outcome: StepOutcome,
+}
+
+impl StepCondition {
+    /// Parse annotation body such as `if: step1.success`
+    pub fn parse(s: &str) -> Option<Self> {
+        let (kind_str, guard) = s.split_once(':')?;
+        let kind = match kind_str.trim().to_lowercase().as_str() {
+            "if" => ConditionKind::If,
+            "unless" => ConditionKind::Unless,
+            _ => return None,
+        };
+
+        let (step_ref, outcome_str) = guard.trim().split_once('.')?;
+        let step_ref = step_ref.trim().to_lowercase();
+        let step = step_ref.strip_prefix("step")?.trim().parse::<usize>().ok()?;
+        let outcome = StepOutcome::from_keyword(outcome_str)?;
+
+        Some(Self { kind, step, outcome })
+    }
+
+    /// Evaluate guard against execution state
+    ///
+    /// Returns `None` while the referenced step has not reached a terminal state.
+    pub fn evaluate(&self, state: &ExecutionState) -> Option<bool> {
+        let status = *state.step_states.get(self.step.checked_sub(1)?)?;
+        if !status.is_terminal() {
+            return None;
+        }
+        let holds = self.outcome.matches(status);
+        Some(match self.kind {
+            ConditionKind::If => holds,
+            ConditionKind::Unless => !holds,
+        })
+    }
+}
+
+impl std::fmt::Display for StepCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            ConditionKind::If => "if",
+            ConditionKind::Unless => "unless",
+        };
+        write!(f, "{{{}: step{}.{}}}", kind, self.step, self.outcome.as_str())
+    }
+}
+
 /// Tool invocation in execution plan
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolInvocation {
@@ -149,6 +494,168 @@ impl ToolInvocation {
             arguments: None,
         }
     }
+
+    /// Namespace of a dotted tool name (`memory` for `memory.query`)
+    pub fn namespace(&self) -> Option<&str> {
+        split_tool_namespace(&self.name).map(|(namespace, _)| namespace)
+    }
+
+    /// Name with its raw arguments, as declared: `bash(cargo test)`
+    fn signature(&self) -> String {
+        match self.raw_arguments.as_deref().map(str::trim) {
+            Some(args) if !args.is_empty() => format!("{}({})", self.name, args),
+            _ => self.name.clone(),
+        }
+    }
+
+    /// Parse `raw_arguments` into `arguments`
+    ///
+    /// `key=value` pairs become a JSON object and positional arguments a JSON
+    /// array. Numbers, booleans and `null` are coerced; quoted strings are
+    /// unquoted. Mixing named and positional arguments is rejected.
+    pub fn parse_arguments(&mut self) -> ApexResult<()> {
+        let raw = match self.raw_arguments {
+            Some(ref raw) if !raw.trim().is_empty() => raw.clone(),
+            _ => {
+                self.arguments = None;
+                return Ok(());
+            }
+        };
+        let malformed = || {
+            ApexError::new(
+                ApexErrorKind::InvalidToolName,
+                format!("Malformed arguments for tool '{}': {}", self.name, raw),
+            )
+        };
+
+        let mut named = serde_json::Map::new();
+        let mut positional = Vec::new();
+        for part in split_arguments(&raw).ok_or_else(malformed)? {
+            match find_unquoted(&part, '=') {
+                Some(idx) => {
+                    let key = part[..idx].trim();
+                    let valid_key = !key.is_empty()
+                        && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+                    if !valid_key || named.contains_key(key) {
+                        return Err(malformed());
+                    }
+                    let value = coerce_argument(part[idx + 1..].trim()).ok_or_else(malformed)?;
+                    named.insert(key.to_string(), value);
+                }
+                None => positional.push(coerce_argument(&part).ok_or_else(malformed)?),
+            }
+        }
+
+        self.arguments = match (named.is_empty(), positional.is_empty()) {
+            (false, true) => Some(serde_json::Value::Object(named)),
+            (true, false) => Some(serde_json::Value::Array(positional)),
+            _ => return Err(malformed()),
+        };
+        Ok(())
+    }
+}
+
+/// Split on top-level commas; `None` on unterminated quotes or empty items
+fn split_arguments(raw: &str) -> Option<Vec<String>> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut scanner = QuoteScanner::default();
+
+    for c in raw.chars() {
+        if scanner.unquoted(c) && c == ',' {
+            parts.push(std::mem::take(&mut current).trim().to_string());
+            continue;
+        }
+        current.push(c);
+    }
+    if scanner.quote.is_some() {
+        return None;
+    }
+    parts.push(current.trim().to_string());
+
+    if parts.iter().any(|p| p.is_empty()) {
+        return None;
+    }
+    Some(parts)
+}
+
+/// Byte index of the first `target` outside quotes
+fn find_unquoted(s: &str, target: char) -> Option<usize> {
+    let mut scanner = QuoteScanner::default();
+    s.char_indices()
+        .find(|&(_, c)| scanner.unquoted(c) && c == target)
+        .map(|(i, _)| i)
+}
+
+/// Quote state of an argument string scanned one char at a time
+///
+/// Single and double quotes delimit strings; a backslash inside a string
+/// escapes the next char.
+#[derive(Default)]
+struct QuoteScanner {
+    quote: Option<char>,
+    escaped: bool,
+}
+
+impl QuoteScanner {
+    /// Feed the next char; `true` if it is outside quotes and not a delimiter
+    fn unquoted(&mut self, c: char) -> bool {
+        if self.escaped {
+            self.escaped = false;
+            return false;
+        }
+        match self.quote {
+            Some(_) if c == '\\' => self.escaped = true,
+            Some(q) if c == q => self.quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => self.quote = Some(c),
+            None => return true,
+        }
+        false
+    }
+}
+
+/// Coerce a single argument value; `None` if malformed
+fn coerce_argument(value: &str) -> Option<serde_json::Value> {
+    use serde_json::Value;
+
+    let first = value.chars().next()?;
+    if first == '"' || first == '\'' {
+        if value.len() < 2 || !value.ends_with(first) {
+            return None;
+        }
+        let mut out = String::new();
+        let mut chars = value[1..value.len() - 1].chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                match chars.next()? {
+                    'n' => out.push('\n'),
+                    't' => out.push('\t'),
+                    other => out.push(other),
+                }
+            } else if c == first {
+                // Unescaped quote inside a quoted string
+                return None;
+            } else {
+                out.push(c);
+            }
+        }
+        return Some(Value::String(out));
+    }
+
+    match value {
+        "true" => return Some(Value::Bool(true)),
+        "false" => return Some(Value::Bool(false)),
+        "null" => return Some(Value::Null),
+        _ => {}
+    }
+    if let Ok(n) = value.parse::<i64>() {
+        return Some(Value::from(n));
+    }
+    match value.parse::<f64>() {
+        Ok(f) if f.is_finite() => Some(Value::from(f)),
+        _ => Some(Value::String(value.to_string())),
+    }
 }
 
 /// Single execution step
@@ -162,6 +669,20 @@ pub struct ExecutionStep {
     pub tool: Option<ToolInvocation>,
     /// Dependencies (step numbers that must complete first)
     pub depends_on: Vec<usize>,
+    /// Conditional execution guard (if any)
+    pub condition: Option<StepCondition>,
+    /// Free-text WHEN condition, evaluated by the runtime (if any)
+    #[serde(default)]
+    pub when: Option<String>,
+    /// Retries allowed after the first attempt (from RETRY)
+    #[serde(default)]
+    pub max_retries: u32,
+    /// How strongly the step matched its tool (0.0 = no tool, 1.0 = certain)
+    #[serde(default)]
+    pub tool_confidence: f32,
+    /// Wall-clock budget from a `{timeout: 30s}` annotation
+    #[serde(default)]
+    pub timeout: Option<Duration>,
 }
 
 impl ExecutionStep {
@@ -172,12 +693,18 @@ impl ExecutionStep {
             description,
             tool: None,
             depends_on: Vec::new(),
+            condition: None,
+            when: None,
+            max_retries: 0,
+            tool_confidence: 0.0,
+            timeout: None,
         }
     }
 
     /// Add tool invocation
     pub fn with_tool(mut self, tool: ToolInvocation) -> Self {
         self.tool = Some(tool);
+        self.tool_confidence = 1.0;
         self
     }
 
@@ -186,6 +713,25 @@ impl ExecutionStep {
         self.depends_on.push(step);
         self
     }
+
+    /// Add conditional execution guard
+    pub fn with_condition(mut self, condition: StepCondition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    /// Steps that must be terminal before this one can run
+    ///
+    /// Includes explicit dependencies and the step referenced by the condition.
+    pub fn prerequisites(&self) -> Vec<usize> {
+        let mut prereqs = self.depends_on.clone();
+        if let Some(cond) = self.condition {
+            if !prereqs.contains(&cond.step) {
+                prereqs.push(cond.step);
+            }
+        }
+        prereqs
+    }
 }
 
 /// Complete execution plan
@@ -228,10 +774,382 @@ impl ExecutionPlan {
             .filter(|s| s.depends_on.contains(&step_number))
             .collect()
     }
+
+    /// Distinct tool names bound to steps, in order of first use
+    pub fn required_tools(&self) -> Vec<&str> {
+        let mut tools: Vec<&str> = Vec::new();
+        for tool in self.steps.iter().filter_map(|s| s.tool.as_ref()) {
+            if !tools.contains(&tool.name.as_str()) {
+                tools.push(&tool.name);
+            }
+        }
+        tools
+    }
+
+    /// Tools bound to steps but not declared in `available_tools`
+    pub fn missing_tool_declarations(&self) -> Vec<&str> {
+        self.required_tools()
+            .into_iter()
+            .filter(|name| !self.available_tools.iter().any(|t| t.name == *name))
+            .collect()
+    }
+
+    /// Check that all dependencies reference existing steps and form no cycle
+    pub fn validate_dependencies(&self) -> ApexResult<()> {
+        self.topological_order().map(|_| ())
+    }
+
+    /// Step numbers in an order respecting dependencies (Kahn's algorithm)
+    ///
+    /// When several steps are ready at once, the lowest step number goes first,
+    /// so the order is deterministic.
+    pub fn topological_order(&self) -> ApexResult<Vec<usize>> {
+        let mut in_degree: HashMap<usize, usize> =
+            self.steps.iter().map(|s| (s.step_number, 0)).collect();
+        let mut dependents: HashMap<usize, Vec<usize>> = HashMap::new();
+
+        for step in &self.steps {
+            for dep in step.prerequisites() {
+                if !in_degree.contains_key(&dep) {
+                    return Err(ApexError::invalid_step_reference(step.step_number, dep));
+                }
+                dependents.entry(dep).or_default().push(step.step_number);
+            }
+        }
+        for step in &self.steps {
+            *in_degree.get_mut(&step.step_number).unwrap() += step.prerequisites().len();
+        }
+
+        let mut ready: BTreeSet<usize> = in_degree
+            .iter()
+            .filter(|(_, &deg)| deg == 0)
+            .map(|(&n, _)| n)
+            .collect();
+        let mut order = Vec::with_capacity(self.steps.len());
+
+        while let Some(current) = ready.pop_first() {
+            order.push(current);
+            for dependent in dependents.get(&current).into_iter().flatten() {
+                let deg = in_degree.get_mut(dependent).unwrap();
+                *deg -= 1;
+                if *deg == 0 {
+                    ready.insert(*dependent);
+                }
+            }
+        }
+
+        if order.len() < self.steps.len() {
+            let mut remaining: Vec<usize> = in_degree
+                .into_iter()
+                .filter(|(n, _)| !order.contains(n))
+                .map(|(n, _)| n)
+                .collect();
+            remaining.sort_unstable();
+            return Err(ApexError::dependency_cycle(&remaining));
+        }
+
+        Ok(order)
+    }
+
+    /// Step numbers grouped into waves that can run concurrently
+    ///
+    /// A step's wave is one past the latest wave among its prerequisites, so
+    /// every step runs after everything it depends on. Steps within a wave
+    /// are sorted by step number. Fails like [`Self::topological_order`].
+    pub fn execution_waves(&self) -> ApexResult<Vec<Vec<usize>>> {
+        let order = self.topological_order()?;
+        let by_number: HashMap<usize, &ExecutionStep> =
+            self.steps.iter().map(|s| (s.step_number, s)).collect();
+        let mut wave_of: HashMap<usize, usize> = HashMap::new();
+        let mut waves: Vec<Vec<usize>> = Vec::new();
+
+        for number in order {
+            let wave = by_number[&number]
+                .prerequisites()
+                .iter()
+                .map(|dep| wave_of[dep] + 1)
+                .max()
+                .unwrap_or(0);
+            wave_of.insert(number, wave);
+            if waves.len() <= wave {
+                waves.resize_with(wave + 1, Vec::new);
+            }
+            waves[wave].push(number);
+        }
+
+        for wave in &mut waves {
+            wave.sort_unstable();
+        }
+        Ok(waves)
+    }
+
+    /// Stable identity hash for caching execution results
+    ///
+    /// Covers the task, the canonical constraint set (order-insensitive), the
+    /// ordered steps (description, dependency set, condition, WHEN guard,
+    /// timeout, retries and bound tool with its arguments) and the set of
+    /// declared tools with their arguments. Goals, validation and META
+    /// (author, timestamps) are ignored. Uses 64-bit FNV-1a, so the value is
+    /// identical across platforms and Rust versions.
+    pub fn content_hash(&self) -> u64 {
+        let mut constraints: Vec<String> = self.constraints.iter().map(|c| canonicalize_constraint(c)).collect();
+        constraints.sort();
+        constraints.dedup();
+        let mut tools: Vec<String> = self.available_tools.iter().map(ToolInvocation::signature).collect();
+        tools.sort_unstable();
+        tools.dedup();
+
+        let mut hasher = Fnv1a::new();
+        hasher.write_field(self.task.trim());
+        hasher.write_list(constraints.iter().map(String::as_str));
+        hasher.write(&(self.steps.len() as u64).to_le_bytes());
+        for step in &self.steps {
+            let mut deps = step.depends_on.clone();
+            deps.sort_unstable();
+            deps.dedup();
+            let deps: Vec<String> = deps.iter().map(usize::to_string).collect();
+            hasher.write_field(step.description.trim());
+            hasher.write_list(deps.iter().map(String::as_str));
+            hasher.write_field(&step.condition.map(|c| c.to_string()).unwrap_or_default());
+            hasher.write_field(step.when.as_deref().unwrap_or(""));
+            hasher.write_field(&step.timeout.map(|t| t.as_millis().to_string()).unwrap_or_default());
+            hasher.write_field(&step.max_retries.to_string());
+            hasher.write_field(&step.tool.as_ref().map(ToolInvocation::signature).unwrap_or_default());
+        }
+        hasher.write_list(tools.iter().map(String::as_str));
+        hasher.finish()
+    }
+
+    /// [`Self::content_hash`] as 16 lowercase hex digits
+    pub fn content_hash_hex(&self) -> String {
+        format!("{:016x}", self.content_hash())
+    }
+
+    /// Dry-run the plan without invoking tools
+    ///
+    /// Steps are visited in [`Self::topological_order`] (document order if the
+    /// dependencies are invalid) and marked `Complete` with a synthetic result.
+    /// Guarded steps whose condition does not hold are `Skipped`. The first step
+    /// with an unmet dependency (missing or cyclic) is marked `Failed` and every
+    /// remaining step `Skipped`.
+    pub fn simulate(&self) -> ExecutionState {
+        let mut state = ExecutionState::new(self.steps.len());
+        let index_of: HashMap<usize, usize> = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(idx, s)| (s.step_number, idx))
+            .collect();
+        let order = self
+            .topological_order()
+            .unwrap_or_else(|_| self.steps.iter().map(|s| s.step_number).collect());
+
+        for number in order {
+            let idx = index_of[&number];
+            let step = &self.steps[idx];
+
+            let unmet = step.prerequisites().into_iter().find(|dep| {
+                index_of
+                    .get(dep)
+                    .is_none_or(|&i| !state.step_states[i].is_terminal())
+            });
+            if let Some(dep) = unmet {
+                state.fail_step(idx, format!("Step {} has unmet dependency on step {}", number, dep));
+                for other in 0..self.steps.len() {
+                    if state.step_states[other] == StepStatus::Pending {
+                        state.skip_step(other);
+                    }
+                }
+                break;
+            }
+
+            if step.condition.and_then(|c| c.evaluate(&state)) == Some(false) {
+                state.skip_step(idx);
+                continue;
+            }
+
+            state.start_step(idx);
+            let target = step.tool.as_ref().map_or(step.description.as_str(), |t| t.name.as_str());
+            state.complete_step(idx, StepResult::Text(format!("dry-run: {}", target)));
+        }
+
+        state
+    }
+
+    /// Select the next step to run (0-based index) given the current state
+    ///
+    /// A step is ready once all of its dependencies are terminal. Unguarded
+    /// steps are blocked by a failed dependency. Guarded steps whose condition
+    /// does not hold are marked `Skipped` in `state` and passed over.
+    pub fn next_step(&self, state: &mut ExecutionState) -> Option<usize> {
+        loop {
+            let mut skipped_any = false;
+
+            for (idx, step) in self.steps.iter().enumerate() {
+                if state.step_states.get(idx) != Some(&StepStatus::Pending) {
+                    continue;
+                }
+
+                let dep_states: Vec<StepStatus> = step
+                    .depends_on
+                    .iter()
+                    .filter_map(|d| d.checked_sub(1).and_then(|i| state.step_states.get(i)).copied())
+                    .collect();
+                if !dep_states.iter().all(|s| s.is_terminal()) {
+                    continue;
+                }
+
+                match step.condition {
+                    None => {
+                        if dep_states.contains(&StepStatus::Failed) {
+                            continue;
+                        }
+                        return Some(idx);
+                    }
+                    Some(cond) => match cond.evaluate(state) {
+                        Some(true) => return Some(idx),
+                        Some(false) => {
+                            state.skip_step(idx);
+                            skipped_any = true;
+                        }
+                        None => continue,
+                    },
+                }
+            }
+
+            if !skipped_any {
+                return None;
+            }
+        }
+    }
+}
+
+/// 64-bit FNV-1a over length-prefixed fields
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    /// Length prefix keeps `["ab", "c"]` distinct from `["a", "bc"]`
+    fn write_field(&mut self, field: &str) {
+        self.write(&(field.len() as u64).to_le_bytes());
+        self.write(field.as_bytes());
+    }
+
+    fn write_list<'a>(&mut self, items: impl IntoIterator<Item = &'a str>) {
+        let items: Vec<&str> = items.into_iter().collect();
+        self.write(&(items.len() as u64).to_le_bytes());
+        for item in items {
+            self.write_field(item);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// JSON Schema (draft 2020-12) for the serialized form of [`ExecutionPlan`]
+///
+/// Mirrors the serde representation: `Option` fields accept `null` and are
+/// not required, `#[serde(default)]` fields are not required, and unit enums
+/// serialize as their variant names. Unknown properties are allowed, as serde
+/// ignores them.
+pub fn execution_plan_json_schema() -> serde_json::Value {
+    let string_list = serde_json::json!({ "type": "array", "items": { "type": "string" } });
+
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "ExecutionPlan",
+        "type": "object",
+        "required": ["task", "goals", "constraints", "steps", "validation", "available_tools"],
+        "properties": {
+            "task": { "type": "string" },
+            "goals": string_list,
+            "constraints": string_list,
+            "steps": { "type": "array", "items": { "$ref": "#/$defs/ExecutionStep" } },
+            "validation": string_list,
+            "available_tools": { "type": "array", "items": { "$ref": "#/$defs/ToolInvocation" } }
+        },
+        "$defs": {
+            "ExecutionStep": {
+                "type": "object",
+                "required": ["step_number", "description", "depends_on"],
+                "properties": {
+                    "step_number": { "type": "integer", "minimum": 1 },
+                    "description": { "type": "string" },
+                    "tool": {
+                        "anyOf": [{ "$ref": "#/$defs/ToolInvocation" }, { "type": "null" }]
+                    },
+                    "depends_on": {
+                        "type": "array",
+                        "items": { "type": "integer", "minimum": 1 }
+                    },
+                    "condition": {
+                        "anyOf": [{ "$ref": "#/$defs/StepCondition" }, { "type": "null" }]
+                    },
+                    "when": { "type": ["string", "null"] },
+                    "max_retries": { "type": "integer", "minimum": 0 },
+                    "tool_confidence": { "type": "number", "minimum": 0, "maximum": 1 },
+                    "timeout": {
+                        "anyOf": [
+                            {
+                                "type": "object",
+                                "required": ["secs", "nanos"],
+                                "properties": {
+                                    "secs": { "type": "integer", "minimum": 0 },
+                                    "nanos": { "type": "integer", "minimum": 0 }
+                                }
+                            },
+                            { "type": "null" }
+                        ]
+                    }
+                }
+            },
+            "ToolInvocation": {
+                "type": "object",
+                "required": ["name"],
+                "properties": {
+                    "name": { "type": "string" },
+                    "raw_arguments": { "type": ["string", "null"] },
+                    "arguments": {}
+                }
+            },
+            "StepCondition": {
+                "type": "object",
+                "required": ["kind", "step", "outcome"],
+                "properties": {
+                    "kind": { "enum": ["If", "Unless"] },
+                    "step": { "type": "integer", "minimum": 1 },
+                    "outcome": { "enum": ["Success", "Failed", "Skipped"] }
+                }
+            }
+        }
+    })
 }
 
 /// Build execution plan from validated document
 pub fn build_execution_plan(doc: &ValidatedDocument) -> ApexResult<ExecutionPlan> {
+    build_execution_plan_with_config(doc, &InterpreterConfig::default())
+}
+
+/// Build execution plan from validated document with interpreter configuration
+pub fn build_execution_plan_with_config(
+    doc: &ValidatedDocument,
+    config: &InterpreterConfig,
+) -> ApexResult<ExecutionPlan> {
     let task = doc.task.line.clone();
 
     let goals = doc
@@ -243,7 +1161,7 @@ pub fn build_execution_plan(doc: &ValidatedDocument) -> ApexResult<ExecutionPlan
     let constraints = doc
         .constraints
         .as_ref()
-        .map(|c| c.rules.clone())
+        .map(|c| c.rules.iter().map(|r| r.canonical.clone()).collect())
         .unwrap_or_default();
 
     let validation = doc
@@ -253,106 +1171,599 @@ pub fn build_execution_plan(doc: &ValidatedDocument) -> ApexResult<ExecutionPlan
         .unwrap_or_default();
 
     // Parse available tools
-    let available_tools: Vec<ToolInvocation> = doc
-        .tools
-        .as_ref()
-        .map(|t| t.tools.iter().map(ToolInvocation::from_declaration).collect())
-        .unwrap_or_default();
+    let available_tools = available_tools(doc, config);
 
     // Build steps from PLAN
-    let steps = build_steps(doc, &available_tools)?;
+    let steps = build_steps(doc, &available_tools, config)?;
+    if steps.is_empty() && !config.allow_empty_plan {
+        return Err(ApexError::empty_block("PLAN", None));
+    }
+    if config.strict_tool_matching {
+        if let Some(step) = steps.iter().find(|s| s.tool.is_none()) {
+            return Err(ApexError::unmatched_step_tool(step.step_number, &step.description));
+        }
+    }
 
-    Ok(ExecutionPlan {
+    let plan = ExecutionPlan {
         task,
         goals,
         constraints,
         steps,
         validation,
         available_tools,
-    })
-}
-
-/// Build execution steps from plan and match with tools
-fn build_steps(doc: &ValidatedDocument, tools: &[ToolInvocation]) -> ApexResult<Vec<ExecutionStep>> {
-    let mut steps = Vec::new();
+    };
+    plan.validate_dependencies()?;
 
-    if let Some(ref plan) = doc.plan {
-        for (i, step_desc) in plan.steps.iter().enumerate() {
-            let step_number = i + 1;
-            let mut step = ExecutionStep::new(step_number, step_desc.clone());
+    Ok(plan)
+}
 
-            // Try to match tool to step
-            // Strategy 1: 1:1 index matching if tools count == steps count
-            if tools.len() == plan.steps.len() {
-                step.tool = Some(tools[i].clone());
-            } else {
-                // Strategy 2: Heuristic matching by keyword
-                step.tool = match_tool_to_step(step_desc, tools);
-            }
+/// Tool invocations declared in the TOOLS block
+///
+/// Names are normalized to canonical form when the config carries a registry.
+fn available_tools(doc: &ValidatedDocument, config: &InterpreterConfig) -> Vec<ToolInvocation> {
+    let mut tools: Vec<ToolInvocation> = doc
+        .tools
+        .as_ref()
+        .map(|t| t.tools.iter().map(ToolInvocation::from_declaration).collect())
+        .unwrap_or_default();
 
-            // Simple sequential dependencies (each step depends on previous)
-            if step_number > 1 {
-                step.depends_on.push(step_number - 1);
+    if let Some(ref registry) = config.registry {
+        for tool in &mut tools {
+            if let Some(canonical) = registry.resolve(&tool.name) {
+                tool.name = canonical.to_string();
             }
-
-            steps.push(step);
         }
     }
 
-    Ok(steps)
+    tools
 }
 
-/// Heuristic tool matching based on step description keywords
-fn match_tool_to_step(step_desc: &str, tools: &[ToolInvocation]) -> Option<ToolInvocation> {
-    let lower = step_desc.to_lowercase();
+impl ValidatedDocument {
+    /// Tool name each PLAN step binds to, without building the full plan
+    ///
+    /// Returns `(step_number, tool_name)` pairs using the same matching as
+    /// [`build_execution_plan_with_config`].
+    pub fn tool_bindings(&self, config: &InterpreterConfig) -> Vec<(usize, Option<String>)> {
+        let Some(ref plan) = self.plan else {
+            return Vec::new();
+        };
+        let tools = available_tools(self, config);
+        let registry = config.inline_registry();
+
+        plan.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let step_line = &entry.description;
+                let step_desc = split_step_annotations(step_line)
+                    .map(|(desc, _)| desc)
+                    .unwrap_or_else(|_| step_line.clone());
+                let tool = bind_tool(i, &step_desc, plan.entries.len(), &tools, &registry, config);
+                (i + 1, tool.map(|(t, _)| t.name))
+            })
+            .collect()
+    }
+}
 
-    for tool in tools {
-        let tool_name_lower = tool.name.to_lowercase();
+impl ValidatedDocument {
+    /// Warnings for TOOLS/PLAN mismatches, checked against the default registry
+    ///
+    /// See [`ValidatedDocument::check_tool_coverage_with_registry`].
+    pub fn check_tool_coverage(&self) -> Vec<String> {
+        self.check_tool_coverage_with_registry(&ToolRegistry::new())
+    }
 
-        // Check if step mentions tool name
-        if lower.contains(&tool_name_lower) {
-            return Some(tool.clone());
+    /// Warnings for TOOLS/PLAN mismatches
+    ///
+    /// Reports declared tools that no PLAN step refers to (per the heuristic
+    /// matcher), PLAN steps calling a `registry` tool (`grep(...)`) or naming
+    /// an `mcp__*` tool that is not declared in TOOLS, and inline calls to
+    /// unknown tools (see [`ValidatedDocument::unknown_inline_tool_calls`]).
+    /// Plain words such as "run bash" are not treated as tool uses.
+    pub fn check_tool_coverage_with_registry(&self, registry: &ToolRegistry) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let declared = self.declared_tool_names();
+        let steps = self.step_descriptions();
+
+        for name in &declared {
+            let used = steps
+                .iter()
+                .any(|s| score_tool(&s.to_lowercase(), name, None) > TOOL_MATCH_THRESHOLD);
+            if !used {
+                warnings.push(format!("Declared tool '{}' is not used by any PLAN step", name));
+            }
         }
 
-        // Check common patterns
-        if lower.contains("read") && tool_name_lower.contains("read") {
-            return Some(tool.clone());
-        }
-        if lower.contains("write") && tool_name_lower.contains("write") {
-            return Some(tool.clone());
-        }
-        if lower.contains("search") && tool_name_lower.contains("search") {
-            return Some(tool.clone());
+        for (i, step) in steps.iter().enumerate() {
+            let mut reported: Vec<&str> = Vec::new();
+            for token in step_tool_mentions(step) {
+                let known = registry.tools().contains(token) || parse_mcp_tool(token).is_some();
+                if known && !declared.contains(&token) && !reported.contains(&token) {
+                    warnings.push(format!("Step {} uses undeclared tool '{}'", i + 1, token));
+                    reported.push(token);
+                }
+            }
         }
-        if lower.contains("edit") && tool_name_lower.contains("edit") {
-            return Some(tool.clone());
+
+        warnings.extend(self.unknown_inline_tool_calls(registry));
+        warnings
+    }
+
+    /// Warnings for inline `name(...)` calls in PLAN steps to tools that are
+    /// neither declared in TOOLS nor known to `registry`
+    ///
+    /// Catches tools invented inside a step instead of being declared.
+    pub fn unknown_inline_tool_calls(&self, registry: &ToolRegistry) -> Vec<String> {
+        let declared = self.declared_tool_names();
+        let mut warnings = Vec::new();
+        for (i, step) in self.step_descriptions().iter().enumerate() {
+            let mut reported: Vec<&str> = Vec::new();
+            for (name, _) in scan_inline_calls(step) {
+                let known = declared.contains(&name) || registry.resolve(name).is_some();
+                if !known && !reported.contains(&name) {
+                    warnings.push(format!("Step {} calls undeclared/unknown tool '{}'", i + 1, name));
+                    reported.push(name);
+                }
+            }
         }
+        warnings
     }
 
-    None
-}
+    /// Names of the tools declared in TOOLS
+    fn declared_tool_names(&self) -> Vec<&str> {
+        self.tools
+            .as_ref()
+            .map(|t| t.tools.iter().map(|d| d.name.as_str()).collect())
+            .unwrap_or_default()
+    }
 
-/// Configuration for plan building
-#[derive(Debug, Clone)]
-pub struct InterpreterConfig {
-    /// Allow empty plans (TASK without PLAN)
-    pub allow_empty_plan: bool,
-    /// Strict tool matching (error if tool not found for step)
-    pub strict_tool_matching: bool,
-    /// Infer sequential dependencies
-    pub infer_dependencies: bool,
+    /// PLAN step descriptions with annotations stripped
+    fn step_descriptions(&self) -> Vec<String> {
+        self.plan
+            .as_ref()
+            .map(|p| {
+                p.entries
+                    .iter()
+                    .map(|entry| {
+                        split_step_annotations(&entry.description)
+                            .map(|(desc, _)| desc)
+                            .unwrap_or_else(|_| entry.description.clone())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
 }
 
-impl Default for InterpreterConfig {
-    fn default() -> Self {
-        Self {
-            allow_empty_plan: true,
-            strict_tool_matching: false,
-            infer_dependencies: true,
+/// Build execution steps from plan and match with tools
+fn build_steps(
+    doc: &ValidatedDocument,
+    tools: &[ToolInvocation],
+    config: &InterpreterConfig,
+) -> ApexResult<Vec<ExecutionStep>> {
+    let mut steps = Vec::new();
+    let registry = config.inline_registry();
+
+    if let Some(ref plan) = doc.plan {
+        let parents = plan.parent_indices();
+        for (i, entry) in plan.entries.iter().enumerate() {
+            let step_number = i + 1;
+            let (step_desc, annotations) =
+                split_step_annotations(&entry.description).map_err(|err| err.with_line(entry.line))?;
+            let StepAnnotations {
+                condition,
+                after: explicit_deps,
+                timeout,
+            } = annotations;
+
+            if let Some(ref cond) = condition {
+                if cond.step == step_number {
+                    return Err(ApexError::self_step_reference(step_number));
+                }
+                if cond.step == 0 || cond.step > plan.entries.len() {
+                    return Err(ApexError::invalid_step_reference(step_number, cond.step));
+                }
+            }
+
+            let mut step = ExecutionStep::new(step_number, step_desc.clone());
+            step.condition = condition;
+            step.timeout = timeout;
+
+            if let Some((tool, confidence)) = bind_tool(i, &step_desc, plan.entries.len(), tools, &registry, config) {
+                step.tool = Some(tool);
+                step.tool_confidence = confidence;
+            }
+
+            // Explicit `[after: ...]` dependencies, else (when inferring)
+            // sub-steps depend on their parent and top-level steps on the previous
+            // top-level step and all its sub-steps
+            if let Some(deps) = explicit_deps {
+                step.depends_on = deps;
+            } else if config.infer_dependencies {
+                if let Some(parent) = parents[i] {
+                    step.depends_on.push(parent + 1);
+                } else if let Some(previous) = parents[..i].iter().rposition(Option::is_none) {
+                    step.depends_on.extend(previous + 1..=i);
+                }
+            }
+
+            steps.push(step);
+        }
+    }
+
+    // WHEN blocks append conditional steps after the PLAN
+    for when in &doc.when {
+        for step_desc in &when.steps {
+            let step_number = steps.len() + 1;
+            let mut step = ExecutionStep::new(step_number, step_desc.clone());
+            step.when = Some(when.condition.clone());
+            let matched = inline_tool_call(step_desc, tools, &registry)
+                .or_else(|| match_tool_to_step(step_desc, tools, config.registry.as_ref()));
+            if let Some((tool, confidence)) = matched {
+                step.tool = Some(tool);
+                step.tool_confidence = confidence;
+            }
+            if config.infer_dependencies && step_number > 1 {
+                step.depends_on.push(step_number - 1);
+            }
+            steps.push(step);
+        }
+    }
+
+    if let Some(ref retry) = doc.retry {
+        for step in &mut steps {
+            step.max_retries = retry.max_retries_for(step.step_number);
+        }
+    }
+
+    Ok(steps)
+}
+
+/// Trailing annotations of a PLAN step
+#[derive(Default)]
+struct StepAnnotations {
+    condition: Option<StepCondition>,
+    after: Option<Vec<usize>>,
+    timeout: Option<Duration>,
+}
+
+/// Strip trailing step annotations (condition, timeout and `[after: ...]`) in any order
+fn split_step_annotations(line: &str) -> ApexResult<(String, StepAnnotations)> {
+    let mut desc = line.trim_end().to_string();
+    let mut annotations = StepAnnotations::default();
+
+    loop {
+        if annotations.condition.is_none() {
+            let (rest, cond) = split_condition(&desc)?;
+            if cond.is_some() {
+                desc = rest;
+                annotations.condition = cond;
+                continue;
+            }
+        }
+        if annotations.timeout.is_none() {
+            let (rest, timeout) = split_timeout(&desc)?;
+            if timeout.is_some() {
+                desc = rest;
+                annotations.timeout = timeout;
+                continue;
+            }
+        }
+        if annotations.after.is_none() {
+            let (rest, after) = split_after(&desc)?;
+            if after.is_some() {
+                desc = rest;
+                annotations.after = after;
+                continue;
+            }
+        }
+        break;
+    }
+
+    Ok((desc, annotations))
+}
+
+/// Split a trailing `{timeout: 30s}` annotation (units `s`, `m`, `h`) from a step line
+fn split_timeout(line: &str) -> ApexResult<(String, Option<Duration>)> {
+    let trimmed = line.trim_end();
+    let Some(open_idx) = trimmed.strip_suffix('}').and_then(|t| t.rfind('{')) else {
+        return Ok((line.to_string(), None));
+    };
+
+    let body = &trimmed[open_idx + 1..trimmed.len() - 1];
+    let Some((keyword, value)) = body.split_once(':') else {
+        return Ok((line.to_string(), None));
+    };
+    if !keyword.trim().eq_ignore_ascii_case("timeout") {
+        return Ok((line.to_string(), None));
+    }
+
+    let invalid = || ApexError::parse(format!("Invalid step timeout: {{{}}}", body.trim()), None);
+    let value = value.trim().to_lowercase();
+    let digits = value.len() - value.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let amount: u64 = value[..digits].parse().map_err(|_| invalid())?;
+    let seconds = match value[digits..].trim() {
+        "s" => amount,
+        "m" => amount.checked_mul(60).ok_or_else(invalid)?,
+        "h" => amount.checked_mul(3600).ok_or_else(invalid)?,
+        _ => return Err(invalid()),
+    };
+
+    Ok((trimmed[..open_idx].trim_end().to_string(), Some(Duration::from_secs(seconds))))
+}
+
+/// Split a trailing `{if: ...}` / `{unless: ...}` annotation from a step line
+///
+/// Braced suffixes that are not condition annotations are left in the description.
+fn split_condition(line: &str) -> ApexResult<(String, Option<StepCondition>)> {
+    let trimmed = line.trim_end();
+    if !trimmed.ends_with('}') {
+        return Ok((line.to_string(), None));
+    }
+    let Some(open_idx) = trimmed.rfind('{') else {
+        return Ok((line.to_string(), None));
+    };
+
+    let body = &trimmed[open_idx + 1..trimmed.len() - 1];
+    let keyword = body.split(':').next().unwrap_or("").trim().to_lowercase();
+    if keyword != "if" && keyword != "unless" {
+        return Ok((line.to_string(), None));
+    }
+
+    let condition = StepCondition::parse(body).ok_or_else(|| {
+        ApexError::parse(format!("Invalid step condition: {{{}}}", body.trim()), None)
+    })?;
+    Ok((trimmed[..open_idx].trim_end().to_string(), Some(condition)))
+}
+
+/// Match a tool to the step at `index` using the configured strategy
+///
+/// An inline call in the description (`grep("TODO")`) takes priority over
+/// declared tools. Returns the tool with its confidence; inline calls and
+/// index bindings are certain (1.0).
+fn bind_tool(
+    index: usize,
+    step_desc: &str,
+    step_count: usize,
+    tools: &[ToolInvocation],
+    inline_registry: &ToolRegistry,
+    config: &InterpreterConfig,
+) -> Option<(ToolInvocation, f32)> {
+    if let Some(inline) = inline_tool_call(step_desc, tools, inline_registry) {
+        return Some(inline);
+    }
+
+    let by_index = || tools.get(index).map(|t| (t.clone(), 1.0));
+    let registry = config.registry.as_ref();
+    match config.tool_matching {
+        // Strategy 1: 1:1 index matching if tools count == steps count
+        // Strategy 2: Heuristic matching by score
+        ToolMatchStrategy::Auto => {
+            if tools.len() == step_count {
+                by_index()
+            } else {
+                match_tool_to_step(step_desc, tools, registry)
+            }
+        }
+        ToolMatchStrategy::Index => by_index(),
+        ToolMatchStrategy::Heuristic => match_tool_to_step(step_desc, tools, registry),
+    }
+}
+
+/// Tool invocation written inline in a step description, e.g. `grep("TODO")`
+///
+/// Only names that are declared in TOOLS or known to `registry` (see
+/// [`InterpreterConfig::inline_registry`]) count, so prose like `foo()` is ignored.
+fn inline_tool_call(
+    step_desc: &str,
+    tools: &[ToolInvocation],
+    registry: &ToolRegistry,
+) -> Option<(ToolInvocation, f32)> {
+    let (name, args) = scan_inline_calls(step_desc).into_iter().find(|(name, _)| {
+        tools.iter().any(|t| t.name == *name) || registry.resolve(name).is_some()
+    })?;
+    let name = registry.resolve(name).unwrap_or(name).to_string();
+
+    let mut tool = ToolInvocation {
+        name,
+        raw_arguments: (!args.trim().is_empty()).then(|| args.trim().to_string()),
+        arguments: None,
+    };
+    // Best effort: arguments stay unparsed (None) if malformed
+    let _ = tool.parse_arguments();
+    Some((tool, 1.0))
+}
+
+/// All `name(args)` calls in `text`, in order, with balanced parentheses
+///
+/// Names are runs of letters, digits, `_`, `.` and `-` starting with a
+/// letter; quotes inside the arguments may contain parentheses.
+fn scan_inline_calls(text: &str) -> Vec<(&str, &str)> {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-');
+    let mut calls = Vec::new();
+
+    for (open, _) in text.match_indices('(') {
+        let start = text[..open]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_name_char(*c))
+            .last()
+            .map_or(open, |(i, _)| i);
+        let name = &text[start..open];
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+
+        let mut depth = 0;
+        let mut quote: Option<char> = None;
+        let mut close = None;
+        for (i, c) in text[open..].char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '(') => depth += 1,
+                (None, ')') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(open + i);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(close) = close {
+            calls.push((name, &text[open + 1..close]));
+        }
+    }
+
+    calls
+}
+
+/// [`score_tool`] a heuristic match must exceed (any single signal does)
+const TOOL_MATCH_THRESHOLD: f32 = 0.2;
+
+/// Score weight: the step mentions the tool name
+const NAME_MENTION_SCORE: f32 = 1.0;
+/// Score weight: the step mentions a keyword of the tool's category
+const CATEGORY_KEYWORD_SCORE: f32 = 0.5;
+/// Score weight: the step and tool name share an action verb
+const VERB_MATCH_SCORE: f32 = 0.3;
+
+/// Action verbs compared between step descriptions and tool names
+const TOOL_VERBS: &[&str] = &["read", "write", "search", "edit"];
+
+/// Highest-scoring tool for a step description
+///
+/// Ties go to the tool declared first. Returns `None` when no tool scores
+/// above [`TOOL_MATCH_THRESHOLD`].
+fn match_tool_to_step(
+    step_desc: &str,
+    tools: &[ToolInvocation],
+    registry: Option<&ToolRegistry>,
+) -> Option<(ToolInvocation, f32)> {
+    let lower = step_desc.to_lowercase();
+    let mut best: Option<(&ToolInvocation, f32)> = None;
+    for tool in tools {
+        let score = score_tool(&lower, &tool.name, registry);
+        if score > TOOL_MATCH_THRESHOLD && best.is_none_or(|(_, s)| score > s) {
+            best = Some((tool, score));
+        }
+    }
+    best.map(|(tool, score)| (tool.clone(), score))
+}
+
+/// Score how strongly a (lowercased) step description refers to a tool
+///
+/// Signals add up, capped at 1.0: a mention of the tool name, a keyword of
+/// the tool's category (e.g. "file" for `read_file`), and an action verb
+/// shared with the tool name. A category keyword alone is enough to bind a
+/// tool, so "Run tests" matches `bash` (category `system`).
+fn score_tool(step_lower: &str, tool_name: &str, registry: Option<&ToolRegistry>) -> f32 {
+    let tool_name_lower = tool_name.to_lowercase();
+    let mut score = 0.0;
+
+    if step_lower.contains(&tool_name_lower) {
+        score += NAME_MENTION_SCORE;
+    }
+    if TOOL_VERBS
+        .iter()
+        .any(|verb| step_lower.contains(verb) && tool_name_lower.contains(verb))
+    {
+        score += VERB_MATCH_SCORE;
+    }
+
+    if let Some(category) = tool_category(tool_name, registry) {
+        let mentioned = step_lower
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|word| {
+                let singular = word.strip_suffix('s').unwrap_or(word);
+                category.keywords().iter().any(|kw| *kw == word || *kw == singular)
+            });
+        if mentioned {
+            score += CATEGORY_KEYWORD_SCORE;
+        }
+    }
+
+    f32::min(score, 1.0)
+}
+
+/// Category from the registry, or the built-in table when there is none
+fn tool_category(name: &str, registry: Option<&ToolRegistry>) -> Option<ToolCategory> {
+    match registry {
+        Some(registry) => registry.category(name),
+        None => TOOL_CATEGORIES
+            .iter()
+            .find(|(tool, _)| *tool == name)
+            .map(|(_, cat)| *cat)
+            .or_else(|| parse_mcp_tool(name).map(|_| ToolCategory::Mcp)),
+    }
+}
+
+/// Tools a step unambiguously refers to, in order
+///
+/// Only call-shaped names (`grep("TODO")`, `memory.query()`) and `mcp__x__y`
+/// names count; a plain word like "bash" or "grep" in prose does not.
+fn step_tool_mentions(step_desc: &str) -> Vec<&str> {
+    let calls = scan_inline_calls(step_desc).into_iter().map(|(name, _)| name);
+    let mcp = step_desc
+        .split(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')))
+        .map(|t| t.trim_matches(|c| c == '.' || c == '-'))
+        .filter(|t| parse_mcp_tool(t).is_some());
+    let mut mentions: Vec<&str> = calls.chain(mcp).collect();
+    // Both are slices of `step_desc`, so address order is document order
+    mentions.sort_by_key(|name| name.as_ptr() as usize);
+    mentions
+}
+
+/// How PLAN steps are bound to declared tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToolMatchStrategy {
+    /// Index matching when tool and step counts are equal, heuristic otherwise
+    #[default]
+    Auto,
+    /// Bind step N to the Nth declared tool
+    Index,
+    /// Bind by keywords in the step description
+    Heuristic,
+}
+
+/// Configuration for plan building
+#[derive(Debug, Clone)]
+pub struct InterpreterConfig {
+    /// Allow empty plans (TASK without PLAN)
+    pub allow_empty_plan: bool,
+    /// Strict tool matching (error if tool not found for step)
+    pub strict_tool_matching: bool,
+    /// Infer dependencies for steps without `[after: ...]` (parent step, or
+    /// the previous top-level step and its sub-steps); when false such steps are independent
+    pub infer_dependencies: bool,
+    /// Step-to-tool matching strategy
+    pub tool_matching: ToolMatchStrategy,
+    /// Registry used to normalize tool aliases to canonical names
+    pub registry: Option<ToolRegistry>,
+}
+
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        Self {
+            allow_empty_plan: true,
+            strict_tool_matching: false,
+            infer_dependencies: true,
+            tool_matching: ToolMatchStrategy::Auto,
+            registry: None,
         }
     }
 }
 
+impl InterpreterConfig {
+    /// Registry that resolves inline tool calls: the configured one, else the default
+    fn inline_registry(&self) -> Cow<'_, ToolRegistry> {
+        self.registry.as_ref().map_or_else(|| Cow::Owned(ToolRegistry::new()), Cow::Borrowed)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -364,6 +1775,53 @@ mod tests {
         validate(doc).unwrap()
     }
 
+    fn invocation(raw: &str) -> ToolInvocation {
+        ToolInvocation {
+            name: "read_file".to_string(),
+            raw_arguments: Some(raw.to_string()),
+            arguments: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let mut tool = invocation(r#"path="x", retries=3, force=true, ratio=0.5"#);
+        tool.parse_arguments().unwrap();
+        assert_eq!(
+            tool.arguments,
+            Some(serde_json::json!({"path": "x", "retries": 3, "force": true, "ratio": 0.5}))
+        );
+
+        let mut tool = invocation(r#""a, b", 'c', 42, plain"#);
+        tool.parse_arguments().unwrap();
+        assert_eq!(tool.arguments, Some(serde_json::json!(["a, b", "c", 42, "plain"])));
+
+        let mut tool = invocation(r#"msg="say \"hi\"""#);
+        tool.parse_arguments().unwrap();
+        assert_eq!(tool.arguments, Some(serde_json::json!({"msg": "say \"hi\""})));
+
+        // Escaped quotes do not end the string, so `=` inside it is not a key separator
+        let mut tool = invocation(r#"k="a\"=b""#);
+        tool.parse_arguments().unwrap();
+        assert_eq!(tool.arguments, Some(serde_json::json!({"k": "a\"=b"})));
+        let mut tool = invocation(r#""a\"=b", 'c\'=d'"#);
+        tool.parse_arguments().unwrap();
+        assert_eq!(tool.arguments, Some(serde_json::json!(["a\"=b", "c'=d"])));
+
+        let mut tool = ToolInvocation { raw_arguments: None, ..invocation("") };
+        tool.parse_arguments().unwrap();
+        assert_eq!(tool.arguments, None);
+    }
+
+    #[test]
+    fn test_parse_arguments_malformed() {
+        for raw in [r#"path="x"#, "a,,b", "=3", "a=1, a=2", "x, key=1", r#"a="x"y"#] {
+            let err = invocation(raw).parse_arguments().unwrap_err();
+            assert_eq!(err.kind, ApexErrorKind::InvalidToolName, "{}", raw);
+            assert!(err.message.contains(raw), "{}", err.message);
+        }
+    }
+
     #[test]
     fn test_minimal_plan() {
         let validated = parse_and_validate("TASK\nDo the thing");
@@ -409,6 +1867,40 @@ All tests pass
         assert_eq!(plan.steps[2].depends_on, vec![2]);
     }
 
+    #[test]
+    fn test_json_schema_matches_serialized_plan() {
+        let input = "TASK\nShip\nPLAN\nSearch code\nDeploy {if: step1.success}\nTOOLS\ncode_search(\"x\")\nRETRY\nall: 2";
+        let plan = build_execution_plan(&parse_and_validate(input)).unwrap();
+        let value = serde_json::to_value(&plan).unwrap();
+        let schema = execution_plan_json_schema();
+        let defs = &schema["$defs"];
+
+        fn assert_keys(value: &serde_json::Value, schema: &serde_json::Value) {
+            let props = schema["properties"].as_object().unwrap();
+            let object = value.as_object().unwrap();
+            let mut actual: Vec<_> = object.keys().collect();
+            let mut expected: Vec<_> = props.keys().collect();
+            actual.sort();
+            expected.sort();
+            assert_eq!(actual, expected);
+            for required in schema["required"].as_array().unwrap() {
+                assert!(object.contains_key(required.as_str().unwrap()));
+            }
+        }
+
+        assert_keys(&value, &schema);
+        let step = &value["steps"][1];
+        assert_keys(step, &defs["ExecutionStep"]);
+        assert_keys(&step["condition"], &defs["StepCondition"]);
+        assert_keys(&value["available_tools"][0], &defs["ToolInvocation"]);
+        assert_eq!(step["condition"]["kind"], "If");
+        assert_eq!(step["condition"]["outcome"], "Success");
+
+        // The schema's shape round-trips through serde
+        let round: ExecutionPlan = serde_json::from_value(value).unwrap();
+        assert_eq!(round.steps[1].max_retries, 2);
+    }
+
     #[test]
     fn test_tool_matching_1_to_1() {
         let input = r#"TASK
@@ -456,4 +1948,740 @@ extra_tool()
         assert!(plan.steps[1].tool.is_some()); // "read" -> read_file
         assert!(plan.steps[2].tool.is_some()); // "edit" -> edit_file
     }
+
+    #[test]
+    fn test_inline_tool_calls() {
+        assert_eq!(
+            scan_inline_calls(r#"Run grep("TODO (later)") across src, then f(x)"#),
+            vec![("grep", r#""TODO (later)""#), ("f", "x")]
+        );
+        assert!(scan_inline_calls("Refactor (carefully)").is_empty());
+
+        let input = r#"TASK
+X
+
+PLAN
+Run grep("TODO") across src
+Read read_file(path="src/lib.rs", limit=10)
+Refactor helper() usage
+Edit the file
+
+TOOLS
+edit_file
+bash
+"#;
+        let plan = build_execution_plan(&parse_and_validate(input)).unwrap();
+
+        let grep = plan.steps[0].tool.as_ref().unwrap();
+        assert_eq!(grep.name, "grep");
+        assert_eq!(grep.raw_arguments.as_deref(), Some(r#""TODO""#));
+        assert_eq!(grep.arguments, Some(serde_json::json!(["TODO"])));
+        assert_eq!(plan.steps[0].tool_confidence, 1.0);
+
+        let read = plan.steps[1].tool.as_ref().unwrap();
+        assert_eq!(read.arguments, Some(serde_json::json!({"path": "src/lib.rs", "limit": 10})));
+
+        // Unknown names are prose; declared-tool matching still applies
+        assert!(plan.steps[2].tool.is_none());
+        assert_eq!(plan.steps[3].tool.as_ref().unwrap().name, "edit_file");
+
+        // Inline calls may use registry tools that TOOLS never declared
+        assert_eq!(plan.required_tools(), vec!["grep", "read_file", "edit_file"]);
+        assert_eq!(plan.missing_tool_declarations(), vec!["grep", "read_file"]);
+    }
+
+    #[test]
+    fn test_tool_matching_scores() {
+        let input = "TASK\nX\nPLAN\nSearch and read the file\nQuery memory.query for notes\nSummarize\nTOOLS\ncode_search\nread_file\nmemory.query\nbash\nglob";
+        let plan = build_execution_plan(&parse_and_validate(input)).unwrap();
+        let bound: Vec<_> = plan
+            .steps
+            .iter()
+            .map(|s| (s.tool.as_ref().map(|t| t.name.as_str()), s.tool_confidence))
+            .collect();
+
+        // read_file: "file" category keyword + "read" verb beats code_search's verb only
+        assert_eq!(bound[0], (Some("read_file"), 0.8));
+        assert_eq!(bound[1], (Some("memory.query"), 1.0));
+        assert_eq!(bound[2], (None, 0.0));
+
+        // Ties go to the first declared tool
+        let tools: Vec<ToolInvocation> = ["code_read", "read_file"]
+            .iter()
+            .map(|n| ToolInvocation { name: n.to_string(), raw_arguments: None, arguments: None })
+            .collect();
+        let (tool, score) = match_tool_to_step("Read the settings", &tools, None).unwrap();
+        assert_eq!((tool.name.as_str(), score), ("code_read", 0.3));
+
+        // A category keyword alone binds ("run" is a system keyword)
+        let tools = [ToolInvocation { name: "bash".to_string(), raw_arguments: None, arguments: None }];
+        let (tool, score) = match_tool_to_step("Run tests", &tools, None).unwrap();
+        assert_eq!((tool.name.as_str(), score), ("bash", 0.5));
+    }
+
+    #[test]
+    fn test_interpreter_config_honored() {
+        let validated = parse_and_validate("TASK\nX\nPLAN\nRead the file\nEdit the file [after: 1]\nSummarize\nTOOLS\nread_file\nedit_file");
+
+        let default_plan = build_execution_plan(&validated).unwrap();
+        assert_eq!(default_plan.steps[2].depends_on, vec![2]);
+
+        let config = InterpreterConfig { infer_dependencies: false, ..InterpreterConfig::default() };
+        let plan = build_execution_plan_with_config(&validated, &config).unwrap();
+        let deps: Vec<_> = plan.steps.iter().map(|s| s.depends_on.clone()).collect();
+        assert_eq!(deps, vec![vec![], vec![1], vec![]]);
+
+        let strict = InterpreterConfig { strict_tool_matching: true, ..InterpreterConfig::default() };
+        let err = build_execution_plan_with_config(&validated, &strict).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InvalidToolName);
+        assert!(err.message.contains("step 3"));
+
+        let no_plan = parse_and_validate("TASK\nX");
+        let config = InterpreterConfig { allow_empty_plan: false, ..InterpreterConfig::default() };
+        let err = build_execution_plan_with_config(&no_plan, &config).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::EmptyRequiredBlock);
+    }
+
+    #[test]
+    fn test_tool_bindings_match_plan() {
+        let input = r#"TASK
+Analyze code
+
+PLAN
+Search for function definitions
+Read the main file {if: step1.success}
+Summarize findings
+
+TOOLS
+grep_search(pattern)
+read_file(path)
+edit_file(path, changes)
+extra_tool()
+"#;
+        let validated = parse_and_validate(input);
+        let config = InterpreterConfig::default();
+        let plan = build_execution_plan_with_config(&validated, &config).unwrap();
+        let bindings = validated.tool_bindings(&config);
+
+        let from_plan: Vec<(usize, Option<String>)> = plan
+            .steps
+            .iter()
+            .map(|s| (s.step_number, s.tool.as_ref().map(|t| t.name.clone())))
+            .collect();
+        assert_eq!(bindings, from_plan);
+        assert_eq!(bindings[0], (1, Some("grep_search".to_string())));
+        assert_eq!(bindings[2], (3, None));
+
+        // Index strategy binds positionally regardless of counts
+        let config = InterpreterConfig {
+            tool_matching: ToolMatchStrategy::Index,
+            ..InterpreterConfig::default()
+        };
+        let bindings = validated.tool_bindings(&config);
+        assert_eq!(bindings[2], (3, Some("edit_file".to_string())));
+    }
+
+    #[test]
+    fn test_tool_aliases_normalized() {
+        let input = r#"TASK
+Find usages
+
+PLAN
+Search for callers
+Read the main file
+
+TOOLS
+ripgrep_search("callers")
+read_file(path)
+"#;
+        let validated = parse_and_validate(input);
+        let mut registry = ToolRegistry::new();
+        registry.add_alias("ripgrep_search", "grep").unwrap();
+        let config = InterpreterConfig {
+            registry: Some(registry),
+            ..InterpreterConfig::default()
+        };
+
+        let plan = build_execution_plan_with_config(&validated, &config).unwrap();
+        assert_eq!(plan.available_tools[0].name, "grep");
+        assert_eq!(plan.steps[0].tool.as_ref().unwrap().name, "grep");
+        assert_eq!(plan.available_tools[1].name, "read_file");
+
+        // Without a registry, names are left as declared
+        let plan = build_execution_plan(&validated).unwrap();
+        assert_eq!(plan.available_tools[0].name, "ripgrep_search");
+    }
+
+    #[test]
+    fn test_tool_coverage() {
+        let input = r#"TASK
+Fix bug
+
+PLAN
+Search for the failing test
+Run bash("cargo test") to reproduce
+Call mcp__jira__comment with the fix. Then grep("panic") again.
+Read the main file and grep for callers
+
+TOOLS
+code_search "failing"
+read_file(path)
+web_fetch(url)
+"#;
+        let validated = parse_and_validate(input);
+        let warnings = validated.check_tool_coverage();
+
+        assert_eq!(
+            warnings,
+            vec![
+                "Declared tool 'web_fetch' is not used by any PLAN step",
+                "Step 2 uses undeclared tool 'bash'",
+                "Step 3 uses undeclared tool 'mcp__jira__comment'",
+                "Step 3 uses undeclared tool 'grep'",
+            ]
+        );
+
+        let validated = parse_and_validate("TASK\nDo it\nPLAN\nRead config\nTOOLS\nread_file(path)");
+        assert!(validated.check_tool_coverage().is_empty());
+
+        // Tools named in prose are not uses; a custom registry is honored
+        let validated = parse_and_validate("TASK\nDo it\nPLAN\nRun the shell script\nbash is fine\nTOOLS\nread_file(path)");
+        let warnings = validated.check_tool_coverage();
+        assert_eq!(warnings, vec!["Declared tool 'read_file' is not used by any PLAN step"]);
+        let validated = parse_and_validate("TASK\nDo it\nPLAN\nCall deploy(prod)\nTOOLS\nread_file(path)");
+        let mut registry = ToolRegistry::new();
+        registry.add_tool("deploy");
+        assert!(validated.check_tool_coverage_with_registry(&registry).contains(&"Step 1 uses undeclared tool 'deploy'".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_inline_tool_calls() {
+        let input = "TASK\nX\nPLAN\nRun grep(\"TODO\")\nCall fetch_tickets(\"open\") then fetch_tickets(\"closed\")\nUse deploy_app(env) and mcp__ci__run()\nTOOLS\ndeploy_app(env)";
+        let validated = parse_and_validate(input);
+
+        let expected = "Step 2 calls undeclared/unknown tool 'fetch_tickets'";
+        assert_eq!(validated.warning_messages(), vec![expected]);
+        assert!(validated.check_tool_coverage().contains(&expected.to_string()));
+
+        let mut registry = ToolRegistry::new();
+        registry.add_tool("fetch_tickets");
+        assert!(validated.unknown_inline_tool_calls(&registry).is_empty());
+    }
+
+    #[test]
+    fn test_when_steps() {
+        let input = r#"TASK
+Update schema
+
+PLAN
+Diff schema
+Write report
+
+WHEN
+schema changed
+Run migration
+Verify schema
+"#;
+        let validated = parse_and_validate(input);
+        let plan = build_execution_plan(&validated).unwrap();
+
+        assert_eq!(plan.steps.len(), 4);
+        assert!(plan.steps[1].when.is_none());
+        assert_eq!(plan.steps[2].description, "Run migration");
+        assert_eq!(plan.steps[2].when.as_deref(), Some("schema changed"));
+        assert_eq!(plan.steps[2].depends_on, vec![2]);
+        assert_eq!(plan.steps[3].step_number, 4);
+        assert_eq!(plan.steps[3].when.as_deref(), Some("schema changed"));
+    }
+
+    #[test]
+    fn test_retry_policies_on_steps() {
+        let input = r#"TASK
+Deploy
+
+PLAN
+Build
+Upload artifacts
+Notify
+
+RETRY
+all: 1 retries
+step 2: 3 retries, backoff exponential
+"#;
+        let validated = parse_and_validate(input);
+        let plan = build_execution_plan(&validated).unwrap();
+
+        assert_eq!(plan.steps[0].max_retries, 1);
+        assert_eq!(plan.steps[1].max_retries, 3);
+        assert_eq!(plan.steps[2].max_retries, 1);
+
+        let plan = build_execution_plan(&parse_and_validate("TASK\nDo\nPLAN\nA")).unwrap();
+        assert_eq!(plan.steps[0].max_retries, 0);
+    }
+
+    #[test]
+    fn test_cancel_partial_run() {
+        let mut state = ExecutionState::new(4);
+        state.start_step(0);
+        state.complete_step(0, StepResult::Empty);
+        state.skip_step(1);
+        state.start_step(2);
+        state.paused = true;
+
+        state.cancel();
+
+        assert_eq!(
+            state.step_states,
+            vec![StepStatus::Complete, StepStatus::Skipped, StepStatus::Cancelled, StepStatus::Cancelled]
+        );
+        assert!(!state.paused);
+        assert_eq!(state.error.as_deref(), Some("cancelled"));
+        assert!(state.is_cancelled());
+        assert!(!state.is_complete());
+        assert!(!state.is_failed());
+        assert!(state.step_states.iter().all(|s| s.is_terminal() && !s.can_resume()));
+        assert_eq!(state.progress().cancelled, 2);
+        assert_eq!(state.elapsed_steps().cancelled, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_retry_step_until_exhausted() {
+        let mut state = ExecutionState::new(2);
+
+        state.fail_step(1, "timeout".to_string());
+        assert!(state.retry_step(1, 2));
+        assert_eq!(state.step_states[1], StepStatus::Pending);
+        assert_eq!(state.attempts[1], 1);
+
+        state.fail_step(1, "timeout".to_string());
+        assert!(state.retry_step(1, 2));
+        assert_eq!(state.attempts[1], 2);
+
+        // Retries exhausted: step stays failed
+        state.fail_step(1, "timeout".to_string());
+        assert!(!state.retry_step(1, 2));
+        assert_eq!(state.step_states[1], StepStatus::Failed);
+        assert_eq!(state.attempts[1], 2);
+        assert!(state.is_failed());
+
+        // No retries allowed at all
+        state.fail_step(0, "boom".to_string());
+        assert!(!state.retry_step(0, 0));
+        assert_eq!(state.step_states[0], StepStatus::Failed);
+    }
+
+    #[test]
+    fn test_step_condition_parsing() {
+        let input = r#"TASK
+Ship release
+
+PLAN
+Build artifacts
+Run tests
+Publish release {if: step2.success}
+Open incident {unless: step2.success}
+Notify team {if: step3.skipped}
+"#;
+        let validated = parse_and_validate(input);
+        let plan = build_execution_plan(&validated).unwrap();
+
+        assert!(plan.steps[0].condition.is_none());
+        assert_eq!(plan.steps[2].description, "Publish release");
+        assert_eq!(
+            plan.steps[2].condition,
+            Some(StepCondition { kind: ConditionKind::If, step: 2, outcome: StepOutcome::Success })
+        );
+        assert_eq!(plan.steps[3].condition.unwrap().kind, ConditionKind::Unless);
+        assert_eq!(plan.steps[4].condition.unwrap().outcome, StepOutcome::Skipped);
+        assert_eq!(plan.steps[2].condition.unwrap().to_string(), "{if: step2.success}");
+    }
+
+    #[test]
+    fn test_step_condition_unknown_step() {
+        let validated = parse_and_validate("TASK\nDo it\nPLAN\nFirst\nSecond {if: step7.success}");
+        let err = build_execution_plan(&validated).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InvalidStepReference);
+
+        let validated = parse_and_validate("TASK\nDo it\nPLAN\nFirst {if: step1.failed}");
+        let err = build_execution_plan(&validated).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InvalidStepReference);
+        assert!(err.message.contains("references itself"));
+    }
+
+    #[test]
+    fn test_step_condition_malformed() {
+        let validated = parse_and_validate("TASK\nDo it\nPLAN\nFirst\nSecond {if: first.maybe}");
+        let err = build_execution_plan(&validated).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::ParseError);
+
+        // Non-condition braces stay in the description
+        let validated = parse_and_validate("TASK\nDo it\nPLAN\nRender {name}");
+        let plan = build_execution_plan(&validated).unwrap();
+        assert_eq!(plan.steps[0].description, "Render {name}");
+        assert!(plan.steps[0].condition.is_none());
+    }
+
+    #[test]
+    fn test_scheduler_skips_unmet_condition() {
+        let input = r#"TASK
+Ship release
+
+PLAN
+Run tests
+Publish release {if: step1.success}
+Open incident {if: step1.failed}
+"#;
+        let validated = parse_and_validate(input);
+        let plan = build_execution_plan(&validated).unwrap();
+        let mut state = ExecutionState::new(plan.step_count());
+
+        assert_eq!(plan.next_step(&mut state), Some(0));
+        state.start_step(0);
+        assert_eq!(plan.next_step(&mut state), None); // step 1 still running
+        state.complete_step(0, StepResult::Empty);
+
+        assert_eq!(plan.next_step(&mut state), Some(1));
+        state.start_step(1);
+        state.complete_step(1, StepResult::Empty);
+
+        // Guard on step 3 is not met: it is skipped and nothing remains
+        assert_eq!(plan.next_step(&mut state), None);
+        assert_eq!(state.step_states[2], StepStatus::Skipped);
+        assert!(state.is_complete());
+    }
+
+    #[test]
+    fn test_scheduler_runs_failure_branch() {
+        let input = r#"TASK
+Ship release
+
+PLAN
+Run tests
+Publish release {if: step1.success}
+Open incident {unless: step1.success}
+"#;
+        let validated = parse_and_validate(input);
+        let plan = build_execution_plan(&validated).unwrap();
+        let mut state = ExecutionState::new(plan.step_count());
+
+        state.fail_step(0, "tests failed".to_string());
+
+        // Step 2 is skipped by its guard, step 3 runs once step 2 is terminal
+        assert_eq!(plan.next_step(&mut state), Some(2));
+        assert_eq!(state.step_states[1], StepStatus::Skipped);
+    }
+
+    fn checkpoint_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("apex_{}_{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_progress_summary() {
+        let plan = build_execution_plan(&parse_and_validate("TASK\nX\nPLAN\nA\nB\nC\nD")).unwrap();
+        let mut state = ExecutionState::new(4);
+        assert_eq!(state.progress().percent_complete, 0.0);
+
+        state.complete_step(0, StepResult::Empty);
+        state.skip_step(1);
+        state.start_step(2);
+
+        let summary = state.progress_for(&plan);
+        assert_eq!((summary.complete, summary.skipped, summary.running, summary.pending), (1, 1, 1, 1));
+        assert_eq!(summary.percent_complete, 50.0);
+        assert_eq!(summary.running_step, Some(2));
+        assert_eq!(summary.running_description.as_deref(), Some("C"));
+        assert_eq!(state.progress().running_description, None);
+
+        state.fail_step(2, "boom".to_string());
+        let elapsed = state.elapsed_steps();
+        assert_eq!(elapsed.completed, vec![0]);
+        assert_eq!(elapsed.skipped, vec![1]);
+        assert_eq!(elapsed.failed, vec![2]);
+        assert_eq!(ExecutionState::new(0).progress().percent_complete, 100.0);
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let path = checkpoint_path("checkpoint_round_trip");
+        let mut state = ExecutionState::new(3);
+        state.complete_step(0, StepResult::Json(serde_json::json!({"files": ["a.rs"], "matches": 2})));
+        state.complete_step_text(2, Some("ok".to_string()));
+        state.fail_step(1, "boom".to_string());
+
+        state.save_checkpoint(&path).unwrap();
+        let restored = ExecutionState::load_checkpoint(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.step_states, state.step_states);
+        assert_eq!(restored.tool_results, state.tool_results);
+        assert_eq!(restored.tool_results[0], StepResult::Json(serde_json::json!({"files": ["a.rs"], "matches": 2})));
+        assert_eq!(restored.tool_results[2].as_text(), Some("ok"));
+        assert_eq!(restored.checkpoint, 3);
+        assert_eq!(restored.error, Some("boom".to_string()));
+    }
+
+    #[test]
+    fn test_checkpoint_load_errors() {
+        let err = ExecutionState::load_checkpoint(&checkpoint_path("checkpoint_missing")).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InternalError);
+
+        let path = checkpoint_path("checkpoint_corrupted");
+        std::fs::write(&path, "{ not json").unwrap();
+        let err = ExecutionState::load_checkpoint(&path).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InternalError);
+        assert!(err.message.contains("Corrupted checkpoint"));
+
+        let mut state = ExecutionState::new(2);
+        state.tool_results.pop();
+        std::fs::write(&path, serde_json::to_string(&state).unwrap()).unwrap();
+        let err = ExecutionState::load_checkpoint(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind, ApexErrorKind::InternalError);
+        assert!(err.message.contains("2 step states but 1 tool results"));
+    }
+
+    fn plan_with_steps(steps: Vec<ExecutionStep>) -> ExecutionPlan {
+        ExecutionPlan {
+            task: "Test".to_string(),
+            goals: Vec::new(),
+            constraints: Vec::new(),
+            steps,
+            validation: Vec::new(),
+            available_tools: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_topological_order_sequential() {
+        let validated = parse_and_validate("TASK\nDo it\nPLAN\nA\nB\nC");
+        let plan = build_execution_plan(&validated).unwrap();
+        assert_eq!(plan.topological_order().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_topological_order_ties_by_step_number() {
+        // 4 depends on 3 and 1; 2 and 3 are independent
+        let plan = plan_with_steps(vec![
+            ExecutionStep::new(1, "A".to_string()),
+            ExecutionStep::new(2, "B".to_string()).depends_on(4),
+            ExecutionStep::new(3, "C".to_string()),
+            ExecutionStep::new(4, "D".to_string()).depends_on(3).depends_on(1),
+        ]);
+        assert_eq!(plan.topological_order().unwrap(), vec![1, 3, 4, 2]);
+    }
+
+    #[test]
+    fn test_nested_plan_dependencies() {
+        let input = "TASK\nX\nPLAN\nPrepare\n  Fetch deps\n  Configure\nBuild\n  Link [after: 3]";
+        let plan = build_execution_plan(&parse_and_validate(input)).unwrap();
+
+        let deps: Vec<_> = plan.steps.iter().map(|s| s.depends_on.clone()).collect();
+        assert_eq!(deps, vec![vec![], vec![1], vec![1], vec![1, 2, 3], vec![3]]);
+        assert_eq!(plan.steps[4].description, "Link");
+    }
+
+    #[test]
+    fn test_dependencies_after_nested_subtrees() {
+        let input = "TASK\nX\nPLAN\nFetch\n  A\n    B\nBuild\n  Compile\n  Link\nShip";
+        let plan = build_execution_plan(&parse_and_validate(input)).unwrap();
+
+        // Top-level steps wait for the previous top-level step's whole subtree
+        let deps: Vec<_> = plan.steps.iter().map(|s| s.depends_on.clone()).collect();
+        assert_eq!(deps, vec![vec![], vec![1], vec![2], vec![1, 2, 3], vec![4], vec![4], vec![4, 5, 6]]);
+        assert_eq!(
+            plan.execution_waves().unwrap(),
+            vec![vec![1], vec![2], vec![3], vec![4], vec![5, 6], vec![7]]
+        );
+    }
+
+    #[test]
+    fn test_execution_waves() {
+        let sequential = build_execution_plan(&parse_and_validate("TASK\nX\nPLAN\nA\nB\nC")).unwrap();
+        assert_eq!(sequential.execution_waves().unwrap(), vec![vec![1], vec![2], vec![3]]);
+
+        let input = "TASK\nX\nPLAN\nFetch\nBuild backend [after: 1]\nBuild frontend [after: 1]\nTest [after: 2, 3]\nLint [after: 1]\nPublish [after: 4] {if: step5.success}";
+        let plan = build_execution_plan(&parse_and_validate(input)).unwrap();
+        assert_eq!(
+            plan.execution_waves().unwrap(),
+            vec![vec![1], vec![2, 3, 5], vec![4], vec![6]]
+        );
+
+        let mut cyclic = sequential.clone();
+        cyclic.steps[0].depends_on = vec![3];
+        let err = cyclic.execution_waves().unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::DependencyCycle);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let a = crate::parse_full("TASK\nShip it\nPLAN\nBuild\nTest\nCONSTRAINTS\nNo Mocks\nreal dbs\nTOOLS\nbash\nread_file\nMETA\nauthor=a\ncreated_at=2024-01-01").unwrap();
+        // Lowercase headers, reordered/rephrased constraints, different META
+        // (tool order is kept: steps without a named tool bind positionally)
+        let b_input = "task\nShip it\nplan\nBuild\nTest\nconstraints\nreal_dbs\nNO_MOCKS\ntools\nbash\nread_file\nmeta\nauthor=b";
+        let parsed = crate::parser::parse_str_with_mode(b_input, crate::parser::ParseMode::Tolerant).unwrap();
+        let b = build_execution_plan(&validate(parsed.document).unwrap()).unwrap();
+
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert_eq!(a.content_hash_hex().len(), 16);
+
+        let reordered = crate::parse_full("TASK\nShip it\nPLAN\nTest\nBuild\nCONSTRAINTS\nno_mocks\nreal_dbs\nTOOLS\nbash\nread_file").unwrap();
+        assert_ne!(a.content_hash(), reordered.content_hash());
+
+        let other_task = crate::parse_full("TASK\nShip it now\nPLAN\nBuild\nTest\nCONSTRAINTS\nno_mocks\nreal_dbs\nTOOLS\nbash\nread_file").unwrap();
+        assert_ne!(a.content_hash(), other_task.content_hash());
+
+        // Scheduling details and tool arguments are part of the identity
+        let base = "TASK\nShip it\nPLAN\nBuild\nLint\nTest\nTOOLS\nbash\nread_file";
+        let hash = |input: &str| crate::parse_full(input).unwrap().content_hash();
+        let variants = [
+            base.replace("Test", "Test [after: 1]"),
+            base.replace("Test", "Test {if: step1.success}"),
+            base.replace("Test", "Test {timeout: 30s}"),
+            format!("{}\nRETRY\nstep 3: 3 retries", base),
+            base.replace("bash", "bash(\"cargo test\")"),
+        ];
+        let mut hashes: Vec<u64> = variants.iter().map(|v| hash(v)).collect();
+        hashes.push(hash(base));
+        hashes.sort_unstable();
+        hashes.dedup();
+        assert_eq!(hashes.len(), variants.len() + 1);
+
+        // Dependency order is not part of the identity
+        assert_eq!(
+            hash(&base.replace("Test", "Test [after: 2, 1]")),
+            hash(&base.replace("Test", "Test [after: 1, 2]"))
+        );
+    }
+
+    #[test]
+    fn test_simulate() {
+        let input = "TASK\nX\nPLAN\nFetch\nBuild [after: 1]\nLint [after: 1]\nRollback [after: 2] {if: step2.failed}\nShip [after: 2, 3]\nTOOLS\nbash(\"cargo build\")";
+        let plan = build_execution_plan(&parse_and_validate(input)).unwrap();
+        let state = plan.simulate();
+
+        assert_eq!(state.execution_order, vec![0, 1, 2, 4]);
+        assert_eq!(state.step_states[3], StepStatus::Skipped);
+        assert!(state.is_complete() && !state.is_failed());
+        assert_eq!(state.tool_results[0].as_text(), Some("dry-run: Fetch"));
+        assert!(state.tool_results[3].is_empty());
+
+        // Unmet dependency: the cycle's first step fails, the rest are skipped
+        let cyclic = plan_with_steps(vec![
+            ExecutionStep::new(1, "A".to_string()),
+            ExecutionStep::new(2, "B".to_string()).depends_on(3),
+            ExecutionStep::new(3, "C".to_string()).depends_on(2),
+            ExecutionStep::new(4, "D".to_string()),
+        ]);
+        let state = cyclic.simulate();
+        assert_eq!(state.execution_order, vec![0]);
+        assert_eq!(
+            state.step_states,
+            vec![StepStatus::Complete, StepStatus::Failed, StepStatus::Skipped, StepStatus::Skipped]
+        );
+        assert!(state.error.unwrap().contains("step 3"));
+    }
+
+    #[test]
+    fn test_topological_order_cycle() {
+        let plan = plan_with_steps(vec![
+            ExecutionStep::new(1, "A".to_string()),
+            ExecutionStep::new(2, "B".to_string()).depends_on(3),
+            ExecutionStep::new(3, "C".to_string()).depends_on(2),
+        ]);
+        let err = plan.topological_order().unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::DependencyCycle);
+        assert!(err.message.contains("2, 3"));
+
+        let validation_err = plan.validate_dependencies().unwrap_err();
+        assert_eq!(validation_err.kind, err.kind);
+        assert_eq!(validation_err.message, err.message);
+    }
+
+    #[test]
+    fn test_explicit_dependencies() {
+        let input = r#"TASK
+Ship release
+
+PLAN
+Step 1: Build backend
+Step 2: Build frontend [after: 1]
+Step 3: Lint
+Step 4: Run tests [after: 1, 2]
+Step 5: Publish [after: step 3, step 4] {if: step4.success}
+Step 6: Tag [release]
+"#;
+        let validated = parse_and_validate(input);
+        let plan = build_execution_plan(&validated).unwrap();
+
+        assert!(plan.steps[0].depends_on.is_empty());
+        assert_eq!(plan.steps[1].depends_on, vec![1]);
+        assert_eq!(plan.steps[1].description, "Build frontend");
+        // Unannotated steps keep sequential inference
+        assert_eq!(plan.steps[2].depends_on, vec![2]);
+        assert_eq!(plan.steps[3].depends_on, vec![1, 2]);
+        assert_eq!(plan.steps[3].description, "Run tests");
+        assert_eq!(plan.steps[4].depends_on, vec![3, 4]);
+        assert_eq!(plan.steps[4].description, "Publish");
+        assert!(plan.steps[4].condition.is_some());
+        // Non-dependency brackets stay in the description
+        assert_eq!(plan.steps[5].description, "Tag [release]");
+        assert_eq!(plan.steps[5].depends_on, vec![5]);
+    }
+
+    #[test]
+    fn test_explicit_dependencies_invalid() {
+        let validated = parse_and_validate("TASK\nDo it\nPLAN\nA\nB [after: one]");
+        let err = build_execution_plan(&validated).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::ParseError);
+
+        let validated = parse_and_validate("TASK\nDo it\nPLAN\nA\nB [after: 5]");
+        let err = build_execution_plan(&validated).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InvalidStepReference);
+
+        let validated = parse_and_validate("TASK\nDo it\nPLAN\nA [after: 2]\nB");
+        let err = build_execution_plan(&validated).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::DependencyCycle);
+    }
+
+    #[test]
+    fn test_step_timeouts() {
+        let input = "TASK\nX\nPLAN\nBuild {timeout: 30s}\nTest [after: 1] {timeout: 5 m}\nDeploy {TIMEOUT: 2h} {if: step2.success}\nNotify\nLog {note: x}";
+        let plan = build_execution_plan(&parse_and_validate(input)).unwrap();
+
+        let timeouts: Vec<_> = plan.steps.iter().map(|s| s.timeout).collect();
+        assert_eq!(
+            timeouts,
+            vec![
+                Some(Duration::from_secs(30)),
+                Some(Duration::from_secs(300)),
+                Some(Duration::from_secs(7200)),
+                None,
+                None,
+            ]
+        );
+        assert_eq!(plan.steps[1].description, "Test");
+        assert_eq!(plan.steps[1].depends_on, vec![1]);
+        assert!(plan.steps[2].condition.is_some());
+        assert_eq!(plan.steps[4].description, "Log {note: x}");
+
+        for bad in ["A {timeout: 10d}", "A {timeout: s}", "A {timeout: 1.5s}"] {
+            let validated = parse_and_validate(&format!("TASK\nX\nPLAN\nSetup\n\n{}", bad));
+            let err = build_execution_plan(&validated).unwrap_err();
+            assert_eq!(err.kind, ApexErrorKind::ParseError, "{}", bad);
+            assert_eq!(err.line, Some(6), "{}", bad);
+        }
+
+        let mut state = ExecutionState::new(2);
+        state.start_step(0);
+        state.timeout_step(0);
+        assert_eq!(state.step_states[0], StepStatus::Failed);
+        assert_eq!(state.error.as_deref(), Some("timeout"));
+    }
+
+    #[test]
+    fn test_topological_order_unknown_dependency() {
+        let plan = plan_with_steps(vec![ExecutionStep::new(1, "A".to_string()).depends_on(9)]);
+        let err = plan.topological_order().unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InvalidStepReference);
+    }
 }
@@ -52,6 +52,19 @@
 //! assert_eq!(validated.task.line, "Do something");
 //! ```
 //!
+//! [`parse_and_validate`] uses legacy mode without a registry; use
+//! [`validate_str`] to pick the mode and registry and get warnings back:
+//!
+//! ```rust
+//! use apex_spec::{validate_str, ToolRegistry, ValidationMode};
+//!
+//! let registry = ToolRegistry::new();
+//! let (validated, warnings) =
+//!     validate_str("TASK\nDo something", ValidationMode::Lenient, Some(&registry)).unwrap();
+//! assert_eq!(validated.task.line, "Do something");
+//! assert!(warnings.is_empty());
+//! ```
+//!
 //! ### Access execution plan
 //!
 //! ```rust
@@ -112,6 +125,12 @@
 //! assert_eq!(canonicalize("REAL DBS"), "real_dbs");
 //! ```
 //!
+//! Numeric comparisons keep their comparator when canonicalized as a
+//! constraint: `canonicalize_constraint("< 300 LOC")` is `lt_300_loc`.
+//!
+//! A `MUST:`, `SHOULD:` or `MAY:` prefix sets a constraint's severity
+//! (default `MUST`). Only `MUST` constraints are hard requirements.
+//!
 //! ## Tool Registry
 //!
 //! APEX validates all tools against a fixed registry to prevent
@@ -153,6 +172,12 @@
 //! | DIFF | No | Expected file changes |
 //! | CONTEXT | No | Pre-loaded context |
 //! | META | No | Metadata key-value pairs |
+//! | WHEN | No | Condition line followed by conditional steps (repeatable) |
+//! | RETRY | No | Per-step retry policies (`step 3: 5 retries`) |
+//!
+//! Additional blocks (e.g. `BUDGET`) can be registered in a [`BlockRegistry`]
+//! passed through [`ParserConfig::block_registry`]; they parse as
+//! [`BlockKind::Custom`] and are available via [`ApexDocument::custom_blocks`].
 //!
 //! ## Precedence
 //!
@@ -160,7 +185,8 @@
 //! CONSTRAINTS > TASK > GOALS > PLAN > CONTEXT
 //! ```
 //!
-//! Constraints always win in conflict resolution.
+//! Constraints always win in conflict resolution. See
+//! [`Semantics::resolve_conflict`] and [`Semantics::resolve_directive_conflict`].
 //!
 //! ## Validation Modes
 //!
@@ -172,26 +198,48 @@
 //! with any agent runtime.
 
 pub mod ast;
+pub mod batch;
+pub mod binary;
+pub mod compat;
+pub mod dot;
+pub mod equivalence;
 pub mod errors;
 pub mod interpreter;
+pub mod lint;
+pub mod merge;
 pub mod parser;
+pub mod plan_diff;
 pub mod prompts;
+pub mod render;
 pub mod sem;
 pub mod tool_registry;
 pub mod validate;
+pub mod visit;
 
 // Re-exports for convenience
-pub use ast::{ApexDocument, Block, BlockKind, Span};
+pub use ast::{ApexDocument, ApexDocumentBuilder, ApexDocumentRef, Block, BlockKind, BlockRef, BlockRegistry, Span};
+pub use batch::{validate_batch, BatchReport, DocumentReport};
+pub use compat::upgrade_to_v1_1;
 pub use errors::{ApexError, ApexErrorKind, ApexResult};
 pub use interpreter::{
-    ExecutionPlan, ExecutionStep, ExecutionState, StepStatus,
-    ToolInvocation, build_execution_plan
+    ExecutionPlan, ExecutionStep, ExecutionState, StepResult, StepStatus, ProgressSummary, ElapsedSteps,
+    StepCondition, ConditionKind, StepOutcome,
+    ToolInvocation, InterpreterConfig, ToolMatchStrategy,
+    build_execution_plan, build_execution_plan_with_config, execution_plan_json_schema
 };
-pub use parser::{parse_str, parse_str_with_mode, ParseMode, ParseFix};
+pub use lint::{LintConfig, LintKind, StepLint, DEFAULT_LINT_VERBS};
+pub use merge::MergeStrategy;
+pub use parser::{parse_str, parse_str_borrowed, parse_str_with_mode, parse_str_with_config, parse_str_with_limits, parse_reader, parse_multi, quick_check, ParseMode, ParseFix, ParseLimits, ParserConfig};
+pub use plan_diff::{PlanDiff, StepChange};
 pub use prompts::{APEX_GENERATOR_V1_1, APEX_EXECUTOR_V1_1, APEX_SPEC_V1_1};
-pub use sem::{Constraint, Precedence, Semantics, normalize_constraint, canonicalize};
-pub use tool_registry::{ToolRegistry, VALID_TOOLS, extract_tool_name};
-pub use validate::{ValidatedDocument, validate, validate_with_mode, DiffFormat, ValidationMode};
+pub use sem::{Comparator, ComplexityFactor, ComplexityReport, Constraint, ConstraintCategory, FactorScore, NumericConstraint, Precedence, PLAN_REQUIRED_GOALS, SAFE_REFACTOR_MAX_DELETIONS, Resolution, Semantics, Severity, NormalizeConfig, normalize_constraint, normalize_constraint_with_config, canonicalize, canonicalize_constraint};
+pub use tool_registry::{ToolRegistry, ToolCategory, VALID_TOOLS, TOOL_CATEGORIES, extract_tool_name, parse_mcp_tool, split_tool_namespace};
+pub use validate::{
+    ValidatedDocument, validate, validate_with_mode, validate_with_config, validate_with_limits, validate_collecting, DiffFormat,
+    ValidationConfig, ValidationLimits, ValidationMode, ValidationCondition, NearDuplicate, Timestamp, Warning, WarningKind, KNOWN_META_KEYS,
+    UnifiedDiff, FileDiff, DiffHunk, DiffLine,
+};
+pub use visit::Visitor;
 
 /// Parse and validate APEX input in one call
 pub fn parse_and_validate(input: &str) -> ApexResult<ValidatedDocument> {
@@ -199,12 +247,45 @@ pub fn parse_and_validate(input: &str) -> ApexResult<ValidatedDocument> {
     validate(doc)
 }
 
+/// Parse and validate with a mode and optional tool registry
+///
+/// Returns the document together with its validation warnings.
+pub fn validate_str(
+    input: &str,
+    mode: ValidationMode,
+    registry: Option<&ToolRegistry>,
+) -> ApexResult<(ValidatedDocument, Vec<Warning>)> {
+    let validated = validate_with_mode(parse_str(input)?, mode, registry)?;
+    let warnings = validated.warnings.clone();
+    Ok((validated, warnings))
+}
+
 /// Full pipeline: parse → validate → interpret
 pub fn parse_full(input: &str) -> ApexResult<ExecutionPlan> {
     let validated = parse_and_validate(input)?;
     build_execution_plan(&validated)
 }
 
+/// Parse with mode and validate, recording parse fixes in `meta_fixes`
+pub fn parse_and_validate_with_mode(input: &str, mode: ParseMode) -> ApexResult<ValidatedDocument> {
+    parse_and_validate_with_fixes(input, mode).map(|(validated, _)| validated)
+}
+
+/// Full pipeline with parse mode, returning the plan and the fixes applied
+pub fn parse_full_with_mode(input: &str, mode: ParseMode) -> ApexResult<(ExecutionPlan, Vec<ParseFix>)> {
+    let (validated, fixes) = parse_and_validate_with_fixes(input, mode)?;
+    let plan = build_execution_plan(&validated)?;
+    Ok((plan, fixes))
+}
+
+/// [`parse_and_validate_with_mode`], also returning the fixes applied
+fn parse_and_validate_with_fixes(input: &str, mode: ParseMode) -> ApexResult<(ValidatedDocument, Vec<ParseFix>)> {
+    let parsed = parse_str_with_mode(input, mode)?;
+    let mut validated = validate(parsed.document)?;
+    validated.meta_fixes.extend(parsed.fixes.iter().map(|f| f.to_string()));
+    Ok((validated, parsed.fixes))
+}
+
 /// APEX format version supported by this crate
 pub const APEX_VERSION: &str = "1.1";
 
@@ -222,6 +303,21 @@ mod tests {
         assert_eq!(validated.task.line, "Do something important");
     }
 
+    #[test]
+    fn test_validate_str() {
+        let registry = ToolRegistry::new();
+        let (validated, warnings) =
+            validate_str("TASK\nDo it\nTOOLS\nnot_a_tool", ValidationMode::Lenient, Some(&registry)).unwrap();
+        assert_eq!(validated.task.line, "Do it");
+        assert_eq!(warnings, validated.warnings);
+        assert_eq!(warnings[0].kind, WarningKind::ToolDegraded);
+        assert_eq!(warnings[0].line, Some(4));
+        assert!(warnings[0].message.contains("not_a_tool"));
+
+        let err = validate_str("TASK\nDo it\nTOOLS\nnot_a_tool", ValidationMode::Strict, Some(&registry)).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InvalidToolName);
+    }
+
     #[test]
     fn test_parse_full() {
         let input = r#"TASK
@@ -0,0 +1,182 @@
+//! Plan Step Lints
+//!
+//! Quality checks for PLAN step descriptions, intended as a gate before an
+//! LLM-generated plan is accepted: steps that are too vague, too long, or do
+//! not start with an imperative verb.
+
+use crate::interpreter::ExecutionPlan;
+use serde::{Deserialize, Serialize};
+
+/// Default imperative verbs a step may start with
+pub static DEFAULT_LINT_VERBS: &[&str] = &[
+    "add", "analyze", "apply", "build", "check", "clean", "collect", "commit", "compare", "compile",
+    "configure", "confirm", "copy", "create", "debug", "define", "delete", "deploy", "document",
+    "download", "edit", "ensure", "extract", "fetch", "find", "fix", "format", "generate", "identify",
+    "implement", "inspect", "install", "integrate", "investigate", "lint", "list", "load", "locate",
+    "measure", "merge", "migrate", "move", "notify", "open", "optimize", "parse", "prepare", "publish",
+    "read", "rebuild", "record", "refactor", "release", "remove", "rename", "replace", "report",
+    "reproduce", "restart", "review", "revert", "rewrite", "run", "save", "scan", "search", "set",
+    "ship", "split", "start", "stop", "store", "summarize", "tag", "test", "trace", "update",
+    "upgrade", "upload", "validate", "verify", "write",
+];
+
+/// Thresholds and verb list for [`ExecutionPlan::lint_steps_with_config`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintConfig {
+    /// Steps with fewer words are too vague
+    pub min_words: usize,
+    /// Steps with more characters are too long
+    pub max_chars: usize,
+    /// Accepted leading verbs (lowercase)
+    pub verbs: Vec<String>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            min_words: 3,
+            max_chars: 200,
+            verbs: DEFAULT_LINT_VERBS.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+}
+
+impl LintConfig {
+    /// Builder: also accept `verb` as a leading verb
+    pub fn with_verb(mut self, verb: &str) -> Self {
+        self.verbs.push(verb.to_lowercase());
+        self
+    }
+}
+
+/// Kind of step lint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LintKind {
+    /// Fewer than [`LintConfig::min_words`] words
+    TooVague,
+    /// More than [`LintConfig::max_chars`] characters
+    TooLong,
+    /// First word is not a known imperative verb
+    NotImperative,
+}
+
+/// A quality issue in one PLAN step
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepLint {
+    /// Step number (1-indexed)
+    pub step_number: usize,
+    /// What is wrong
+    pub kind: LintKind,
+    /// Human-readable explanation
+    pub message: String,
+}
+
+impl ExecutionPlan {
+    /// Lint step descriptions with [`LintConfig::default`]
+    pub fn lint_steps(&self) -> Vec<StepLint> {
+        self.lint_steps_with_config(&LintConfig::default())
+    }
+
+    /// Lint step descriptions, in step order
+    pub fn lint_steps_with_config(&self, config: &LintConfig) -> Vec<StepLint> {
+        let mut lints = Vec::new();
+        for step in &self.steps {
+            let desc = step.description.trim();
+            let words: Vec<&str> = desc.split_whitespace().collect();
+            let mut lint = |kind, message: String| {
+                lints.push(StepLint {
+                    step_number: step.step_number,
+                    kind,
+                    message,
+                })
+            };
+
+            if words.len() < config.min_words {
+                lint(
+                    LintKind::TooVague,
+                    format!(
+                        "Step {} '{}' is too vague ({} words, at least {} expected)",
+                        step.step_number,
+                        desc,
+                        words.len(),
+                        config.min_words
+                    ),
+                );
+            }
+
+            let chars = desc.chars().count();
+            if chars > config.max_chars {
+                lint(
+                    LintKind::TooLong,
+                    format!(
+                        "Step {} is too long ({} characters, at most {} expected)",
+                        step.step_number, chars, config.max_chars
+                    ),
+                );
+            }
+
+            let first = words
+                .first()
+                .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+                .unwrap_or_default();
+            if !config.verbs.contains(&first) {
+                lint(
+                    LintKind::NotImperative,
+                    format!(
+                        "Step {} '{}' does not start with an imperative verb",
+                        step.step_number, desc
+                    ),
+                );
+            }
+        }
+        lints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_full;
+
+    fn kinds(lints: &[StepLint]) -> Vec<(usize, LintKind)> {
+        lints.iter().map(|l| (l.step_number, l.kind)).collect()
+    }
+
+    #[test]
+    fn test_lint_steps() {
+        let long = format!("Update {}", "the config ".repeat(20));
+        let input = format!(
+            "TASK\nX\nPLAN\nRun the test suite\nDo it\nThe parser should be faster\n{}\nSummarize: open issues",
+            long
+        );
+        let plan = parse_full(&input).unwrap();
+        let lints = plan.lint_steps();
+
+        assert_eq!(
+            kinds(&lints),
+            vec![
+                (2, LintKind::TooVague),
+                (2, LintKind::NotImperative),
+                (3, LintKind::NotImperative),
+                (4, LintKind::TooLong),
+            ]
+        );
+        assert_eq!(lints[0].message, "Step 2 'Do it' is too vague (2 words, at least 3 expected)");
+    }
+
+    #[test]
+    fn test_lint_config() {
+        let plan = parse_full("TASK\nX\nPLAN\nBenchmark the parser\nRun tests").unwrap();
+        assert_eq!(
+            kinds(&plan.lint_steps()),
+            vec![(1, LintKind::NotImperative), (2, LintKind::TooVague)]
+        );
+
+        let config = LintConfig {
+            min_words: 2,
+            ..LintConfig::default()
+        }
+        .with_verb("Benchmark");
+        assert!(plan.lint_steps_with_config(&config).is_empty());
+    }
+}
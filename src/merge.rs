@@ -0,0 +1,256 @@
+//! APEX Document Merging
+//!
+//! Combines a base document with a patch document:
+//!
+//! - TASK is single-valued: identical lines merge, differing lines are a
+//!   conflict unless [`MergeStrategy::Overwrite`] lets the patch win
+//! - GOALS, PLAN, CONSTRAINTS, VALIDATION and TOOLS are concatenated with
+//!   duplicates removed (constraints compared canonically)
+//! - CONTEXT lines from the patch are appended verbatim
+//! - META entries and RETRY policies from the patch override those with the
+//!   same key or step
+//! - DIFF from the patch replaces the base DIFF
+//! - WHEN blocks from both documents are kept
+//!
+//! Constraints have the highest precedence (see [`crate::sem::Precedence`]):
+//! constraints from either document are never dropped, and GOALS or PLAN
+//! lines that conflict with a constraint of the other document are (see
+//! [`Semantics::resolve_directive_conflict`]).
+
+use crate::ast::{ApexDocument, Block, BlockKind};
+use crate::compat::meta_key;
+use crate::errors::{ApexError, ApexErrorKind, ApexResult};
+use crate::sem::{canonicalize_constraint, Precedence, Resolution, Semantics};
+
+/// How to resolve conflicting single-valued blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// Fail when both documents have different TASK lines
+    #[default]
+    RejectConflicts,
+    /// Let the patch document win
+    Overwrite,
+}
+
+impl ApexDocument {
+    /// Merge `other` into this document, rejecting conflicting TASK lines
+    pub fn merge(&self, other: &ApexDocument) -> ApexResult<ApexDocument> {
+        self.merge_with_strategy(other, MergeStrategy::default())
+    }
+
+    /// Merge `other` into this document with an explicit conflict strategy
+    pub fn merge_with_strategy(
+        &self,
+        other: &ApexDocument,
+        strategy: MergeStrategy,
+    ) -> ApexResult<ApexDocument> {
+        let sem = Semantics::default();
+        let mut blocks: Vec<Block> = Vec::new();
+
+        for block in &self.blocks {
+            let repeatable = block.kind == BlockKind::When || block.kind.is_custom();
+            if repeatable || !blocks.iter().any(|b| b.kind == block.kind) {
+                blocks.push(drop_overruled(block, other, &sem));
+            }
+        }
+
+        for incoming in &other.blocks {
+            let incoming = &drop_overruled(incoming, self, &sem);
+            let existing = blocks
+                .iter_mut()
+                .find(|b| b.kind == incoming.kind && b.kind != BlockKind::When);
+            let Some(existing) = existing else {
+                blocks.push(incoming.clone());
+                continue;
+            };
+
+            match incoming.kind {
+                BlockKind::Task => merge_task(existing, incoming, strategy)?,
+                BlockKind::Meta => merge_meta(existing, incoming),
+                BlockKind::Diff => existing.lines = incoming.lines.clone(),
+                BlockKind::Context => existing.lines.extend(incoming.lines.iter().cloned()),
+                BlockKind::Retry => merge_retry(existing, incoming),
+                BlockKind::Constraints => {
+                    append_unique(existing, incoming, canonicalize_constraint)
+                }
+                _ => append_unique(existing, incoming, |l| l.to_string()),
+            }
+        }
+
+        Ok(ApexDocument {
+            blocks,
+            version: other.version.clone().or_else(|| self.version.clone()),
+        })
+    }
+}
+
+fn merge_task(existing: &mut Block, incoming: &Block, strategy: MergeStrategy) -> ApexResult<()> {
+    if existing.content() == incoming.content() {
+        return Ok(());
+    }
+    match strategy {
+        MergeStrategy::Overwrite => {
+            existing.lines = incoming.lines.clone();
+            Ok(())
+        }
+        MergeStrategy::RejectConflicts => Err(ApexError::new(
+            ApexErrorKind::MergeConflict,
+            format!(
+                "Conflicting TASK lines: '{}' vs '{}'",
+                existing.content(),
+                incoming.content()
+            ),
+        )
+        .with_line(incoming.span.start_line)),
+    }
+}
+
+/// Copy of `block` without the lines a constraint of `rules_from` overrules
+///
+/// Only GOALS and PLAN lines are checked: TASK also ranks below
+/// CONSTRAINTS but is single-valued, and the other blocks carry no
+/// directives.
+fn drop_overruled(block: &Block, rules_from: &ApexDocument, sem: &Semantics) -> Block {
+    let mut block = block.clone();
+    let checked = matches!(Precedence::for_block(&block.kind), Precedence::Goals | Precedence::Plan)
+        && block.kind != BlockKind::When;
+    let Some(constraints) = rules_from.constraints().filter(|_| checked) else {
+        return block;
+    };
+    let rules = constraints.content_lines();
+    let overruled = |line: &str| {
+        rules
+            .iter()
+            .any(|rule| sem.resolve_directive_conflict(rule, line) != Resolution::NoConflict)
+    };
+
+    if block.lines.iter().any(|l| overruled(l)) {
+        let line_numbers = (0..block.lines.len()).map(|idx| block.source_line(idx));
+        let (lines, line_numbers) = block
+            .lines
+            .iter()
+            .zip(line_numbers)
+            .filter(|(line, _)| !overruled(line))
+            .map(|(line, number)| (line.clone(), number))
+            .unzip();
+        block.lines = lines;
+        block.line_numbers = line_numbers;
+    }
+    block
+}
+
+/// Append lines from `incoming` whose key is not already present
+fn append_unique(existing: &mut Block, incoming: &Block, key: impl Fn(&str) -> String) {
+    let mut seen: Vec<String> = existing.content_lines().into_iter().map(&key).collect();
+    for line in incoming.content_lines() {
+        let k = key(line);
+        if !seen.contains(&k) {
+            existing.lines.push(line.to_string());
+            seen.push(k);
+        }
+    }
+}
+
+/// Override META entries by key, appending new keys
+fn merge_meta(existing: &mut Block, incoming: &Block) {
+    for line in incoming.content_lines() {
+        let Some(key) = meta_key(line) else {
+            continue;
+        };
+        match existing.lines.iter().position(|l| meta_key(l).as_ref() == Some(&key)) {
+            Some(idx) => existing.lines[idx] = line.to_string(),
+            None => existing.lines.push(line.to_string()),
+        }
+    }
+}
+
+/// Override RETRY policies by target (`all` or `step N`), appending new targets
+fn merge_retry(existing: &mut Block, incoming: &Block) {
+    let target = |line: &str| {
+        let target = line.split_once(':').map_or(line, |(target, _)| target);
+        target.split_whitespace().collect::<String>().to_lowercase()
+    };
+    for line in incoming.content_lines() {
+        let key = target(line);
+        match existing.lines.iter().position(|l| !l.trim().is_empty() && target(l) == key) {
+            Some(idx) => existing.lines[idx] = line.to_string(),
+            None => existing.lines.push(line.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    #[test]
+    fn test_merge_concatenates_and_dedups() {
+        let base = parse_str("TASK\nShip it\nGOALS\nFast\nPLAN\nBuild\nTest\nCONSTRAINTS\nNo Mocks\nMETA\nversion=1.0\nauthor=a").unwrap();
+        let patch = parse_str("TASK\nShip it\nPLAN\nTest\nDeploy\nCONSTRAINTS\nno_mocks\nreal dbs\nMETA\nversion=1.1\nreviewer: b").unwrap();
+
+        let merged = base.merge(&patch).unwrap();
+
+        assert_eq!(merged.count_blocks(BlockKind::Task), 1);
+        assert_eq!(merged.plan().unwrap().content_lines(), vec!["Build", "Test", "Deploy"]);
+        assert_eq!(merged.constraints().unwrap().content_lines(), vec!["No Mocks", "real dbs"]);
+        assert_eq!(merged.goals().unwrap().content_lines(), vec!["Fast"]);
+        assert_eq!(
+            merged.meta().unwrap().content_lines(),
+            vec!["version=1.1", "author=a", "reviewer: b"]
+        );
+    }
+
+    #[test]
+    fn test_merge_constraints_overrule_other_document() {
+        let base = parse_str("TASK\nShip it\nPLAN\nMock the payment gateway\nRun tests\nCONSTRAINTS\nsafe refactor").unwrap();
+        let patch = parse_str("PLAN\nIntroduce breaking change to API\nDeploy\nCONSTRAINTS\nNo Mocks").unwrap();
+
+        let merged = base.merge(&patch).unwrap();
+        assert_eq!(merged.plan().unwrap().content_lines(), vec!["Run tests", "Deploy"]);
+        assert_eq!(merged.constraints().unwrap().content_lines(), vec!["safe refactor", "No Mocks"]);
+
+        // A document's own steps are left alone
+        let own = parse_str("TASK\nShip it\nPLAN\nMock the payment gateway\nCONSTRAINTS\nNo Mocks").unwrap();
+        let merged = own.merge(&parse_str("TASK\nShip it").unwrap()).unwrap();
+        assert_eq!(merged.plan().unwrap().content_lines(), vec!["Mock the payment gateway"]);
+    }
+
+    #[test]
+    fn test_merge_context_verbatim_and_retry_override() {
+        let base = parse_str("TASK\nShip it\nCONTEXT\nNotes:\n  - keep\nRETRY\nall: 1 retry\nstep 2: 3 retries").unwrap();
+        let patch = parse_str("CONTEXT\nNotes:\n\n  - keep\nRETRY\nStep 2: 5 retries, backoff exponential\nstep 3: 1 retry").unwrap();
+
+        let merged = base.merge(&patch).unwrap();
+        let context = merged.get_block(BlockKind::Context).unwrap();
+        assert_eq!(context.lines, vec!["Notes:", "  - keep", "Notes:", "", "  - keep"]);
+        assert_eq!(
+            merged.retry().unwrap().content_lines(),
+            vec!["all: 1 retry", "Step 2: 5 retries, backoff exponential", "step 3: 1 retry"]
+        );
+    }
+
+    #[test]
+    fn test_merge_task_conflict() {
+        let base = parse_str("TASK\nShip it").unwrap();
+        let patch = parse_str("TASK\nRoll back\nVALIDATION\nHealthy").unwrap();
+
+        let err = base.merge(&patch).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::MergeConflict);
+
+        let merged = base.merge_with_strategy(&patch, MergeStrategy::Overwrite).unwrap();
+        assert_eq!(merged.task().unwrap().content(), "Roll back");
+        assert!(merged.validation().is_some());
+    }
+
+    #[test]
+    fn test_merge_keeps_base_task_when_patch_has_none() {
+        let base = parse_str("TASK\nShip it\nDIFF\nraw\nold").unwrap();
+        let patch = parse_str("DIFF\nraw\nnew\nWHEN\nfailure\nRoll back").unwrap();
+
+        let merged = base.merge(&patch).unwrap();
+        assert_eq!(merged.task().unwrap().content(), "Ship it");
+        assert_eq!(merged.diff().unwrap().content_lines(), vec!["raw", "new"]);
+        assert_eq!(merged.when_blocks().len(), 1);
+    }
+}
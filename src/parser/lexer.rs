@@ -2,8 +2,9 @@
 //!
 //! Tokenizes APEX input into block headers and content lines.
 
-use crate::ast::{BlockKind, Span};
-use crate::errors::ApexResult;
+use crate::ast::{is_block_identifier, BlockKind, BlockRegistry, Span};
+use crate::errors::{ApexError, ApexResult};
+use std::ops::Range;
 
 /// Token types produced by lexer
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -27,6 +28,28 @@ impl Token {
     }
 }
 
+/// Token borrowing its line content from the lexer input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenRef<'a> {
+    /// Block header (e.g., "TASK", "PLAN")
+    BlockHeader(BlockKind, Span),
+    /// Content line (non-header text)
+    Line(&'a str, Span),
+    /// End of input
+    Eof,
+}
+
+impl TokenRef<'_> {
+    /// Convert into an owned [`Token`]
+    pub fn to_token(&self) -> Token {
+        match *self {
+            TokenRef::BlockHeader(ref kind, span) => Token::BlockHeader(kind.clone(), span),
+            TokenRef::Line(content, span) => Token::Line(content.to_string(), span),
+            TokenRef::Eof => Token::Eof,
+        }
+    }
+}
+
 /// Parser mode per APEX v1.1 spec
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ParseMode {
@@ -44,14 +67,76 @@ pub struct ParseFix {
     pub description: String,
 }
 
+impl std::fmt::Display for ParseFix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.description)
+    }
+}
+
+fn indent_name(unit: char) -> &'static str {
+    if unit == '\t' {
+        "tabs"
+    } else {
+        "spaces"
+    }
+}
+
+/// Split input into lines, normalizing line endings
+///
+/// Breaks on `\n`, U+2028 (line separator) and U+2029 (paragraph separator),
+/// and strips trailing `\r` so CRLF input behaves like LF. As with
+/// [`str::lines`], a final line break does not start an empty line.
+pub fn split_lines(input: &str) -> Vec<&str> {
+    line_iter(input).collect()
+}
+
+/// Lazy form of [`split_lines`]
+pub fn line_iter(input: &str) -> impl Iterator<Item = &str> {
+    line_ranges(input).map(|(_, line)| line)
+}
+
+/// Lines as in [`split_lines`], each with its byte range in `input`
+///
+/// The range covers the line content only: the break and any stripped `\r`
+/// are excluded, so `&input[range] == line`.
+pub fn line_ranges(input: &str) -> impl Iterator<Item = (Range<usize>, &str)> {
+    let mut pieces = input.split(['\n', '\u{2028}', '\u{2029}']).peekable();
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let piece = pieces.next()?;
+        let line = piece.trim_end_matches('\r');
+        // A trailing break yields one extra empty piece
+        if line.is_empty() && pieces.peek().is_none() {
+            return None;
+        }
+        let start = offset;
+        // Skip the piece and the break after it (one byte for `\n`, three for U+2028/9)
+        offset += piece.len();
+        offset += input[offset..].chars().next().map_or(0, char::len_utf8);
+        Some((start..start + line.len(), line))
+    })
+}
+
 /// Lexer state
 pub struct Lexer<'a> {
     /// Lines split from input
     lines: Vec<&'a str>,
+    /// Byte range of each line in the input
+    line_ranges: Vec<Range<usize>>,
     /// Current line index (0-based)
     line_idx: usize,
     /// Parser mode (strict or tolerant)
     mode: ParseMode,
+    /// Skip `#` comment lines outside DIFF and CONTEXT blocks
+    allow_comments: bool,
+    /// Kind of the most recent block header
+    current_block: Option<BlockKind>,
+    /// Extension blocks recognized as headers
+    registry: BlockRegistry,
+    /// Indentation character (tab or space) of the current PLAN block
+    indent_unit: Option<char>,
+    /// Blank lines after the TASK header, until its first content line (tolerant mode)
+    task_leading_blanks: Option<usize>,
     /// Fixes applied in tolerant mode
     pub fixes: Vec<ParseFix>,
     /// Phantom to preserve lifetime
@@ -66,16 +151,49 @@ impl<'a> Lexer<'a> {
 
     /// Create new lexer with specified mode
     pub fn with_mode(input: &'a str, mode: ParseMode) -> Self {
-        let lines: Vec<&str> = input.lines().collect();
+        let (line_ranges, lines) = line_ranges(input).unzip();
+        Self::from_lines(lines, line_ranges, mode)
+    }
+
+    /// Create lexer over lines already split by [`split_lines`] rules
+    pub(crate) fn from_lines(lines: Vec<&'a str>, line_ranges: Vec<Range<usize>>, mode: ParseMode) -> Self {
         Self {
             lines,
+            line_ranges,
             line_idx: 0,
             mode,
+            allow_comments: false,
+            current_block: None,
+            registry: BlockRegistry::default(),
+            indent_unit: None,
+            task_leading_blanks: None,
             fixes: Vec::new(),
             _phantom: std::marker::PhantomData,
         }
     }
 
+    /// Enable or disable `#` comment lines
+    ///
+    /// Comment lines are skipped entirely except inside DIFF and CONTEXT
+    /// blocks, where `#` is meaningful content. Line numbers are unaffected.
+    pub fn with_comments(mut self, allow: bool) -> Self {
+        self.allow_comments = allow;
+        self
+    }
+
+    /// Recognize the extension blocks in `registry` as headers
+    pub fn with_blocks(mut self, registry: BlockRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Check if line is a comment that should be skipped
+    fn is_skipped_comment(&self, line: &str) -> bool {
+        self.allow_comments
+            && line.trim_start().starts_with('#')
+            && !matches!(self.current_block, Some(BlockKind::Diff) | Some(BlockKind::Context))
+    }
+
     /// Check if at end of input
     pub fn is_eof(&self) -> bool {
         self.line_idx >= self.lines.len()
@@ -86,6 +204,11 @@ impl<'a> Lexer<'a> {
         self.line_idx + 1
     }
 
+    /// Byte range of a line (1-indexed) in the input, break excluded
+    pub fn line_byte_range(&self, line_num: usize) -> Option<Range<usize>> {
+        self.line_ranges.get(line_num.checked_sub(1)?).cloned()
+    }
+
     /// Peek at current line without consuming
     pub fn peek_line(&self) -> Option<&'a str> {
         self.lines.get(self.line_idx).copied()
@@ -121,9 +244,14 @@ impl<'a> Lexer<'a> {
     /// Check if line is a block header based on current mode
     fn check_block_header(&mut self, line: &str, line_num: usize) -> Option<BlockKind> {
         match self.mode {
-            ParseMode::Strict => Self::is_block_header_strict(line),
+            ParseMode::Strict => Self::is_block_header_strict(line).or_else(|| self.registry.get(line.trim())),
             ParseMode::Tolerant => {
-                if let Some((kind, was_fixed)) = Self::is_block_header_tolerant(line) {
+                let resolved = Self::is_block_header_tolerant(line).or_else(|| {
+                    let trimmed = line.trim();
+                    let kind = self.registry.resolve(trimmed)?;
+                    Some((kind, !is_block_identifier(trimmed)))
+                });
+                if let Some((kind, was_fixed)) = resolved {
                     if was_fixed {
                         self.fixes.push(ParseFix {
                             line: line_num,
@@ -142,23 +270,126 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Check that PLAN indentation is consistent
+    ///
+    /// A line may not mix tabs and spaces in its leading whitespace, and a
+    /// PLAN block may not switch between tab and space indentation. Strict
+    /// mode rejects both; tolerant mode records a fix, and the parser
+    /// rewrites each indentation tab as two spaces.
+    fn check_indentation(&mut self, line: &str, line_num: usize, span: Span) -> ApexResult<()> {
+        let leading = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        if leading.is_empty() || line.trim().is_empty() {
+            return Ok(());
+        }
+
+        let has_tab = leading.contains('\t');
+        let problem = if has_tab && leading.contains(' ') {
+            Some("mixes tabs and spaces in its indentation".to_string())
+        } else {
+            let unit = if has_tab { '\t' } else { ' ' };
+            match self.indent_unit {
+                Some(previous) if previous != unit => Some(format!(
+                    "switches PLAN indentation from {} to {}",
+                    indent_name(previous),
+                    indent_name(unit)
+                )),
+                Some(_) => None,
+                None => {
+                    self.indent_unit = Some(unit);
+                    None
+                }
+            }
+        };
+
+        match (problem, self.mode) {
+            (None, _) => Ok(()),
+            (Some(problem), ParseMode::Strict) => {
+                Err(ApexError::parse(format!("Line {} {}", line_num, problem), Some(line_num)).with_span(span))
+            }
+            (Some(problem), ParseMode::Tolerant) => {
+                self.fixes.push(ParseFix {
+                    line: line_num,
+                    description: format!("Normalized indentation to spaces (line {})", problem),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Record a fix for blank lines between `TASK` and its content
+    ///
+    /// Only tracked in tolerant mode, where the parser drops the blank lines;
+    /// strict mode keeps them as ordinary TASK lines.
+    fn record_task_gap(&mut self, blanks: usize, line_num: usize) {
+        if blanks == 0 {
+            return;
+        }
+        let noun = if blanks == 1 { "line" } else { "lines" };
+        self.fixes.push(ParseFix {
+            line: line_num,
+            description: format!("Moved TASK content up past {} blank {}", blanks, noun),
+        });
+    }
+
     /// Get next token
     pub fn next_token(&mut self) -> ApexResult<Token> {
+        self.next_token_ref().map(|t| t.to_token())
+    }
+
+    /// Get next token without copying line content
+    pub fn next_token_ref(&mut self) -> ApexResult<TokenRef<'a>> {
+        // Skip comment lines (line_idx still advances, so spans stay accurate)
+        while !self.is_eof() && self.is_skipped_comment(self.lines[self.line_idx]) {
+            self.line_idx += 1;
+        }
+
         if self.is_eof() {
-            return Ok(Token::Eof);
+            return Ok(TokenRef::Eof);
         }
 
         let line = self.lines[self.line_idx];
         let line_num = self.current_line_number();
         self.line_idx += 1;
 
+        let span = Self::line_span(line, line_num);
+
         // Check if this is a block header
         if let Some(kind) = self.check_block_header(line, line_num) {
-            return Ok(Token::BlockHeader(kind, Span::line(line_num)));
+            self.task_leading_blanks = (kind == BlockKind::Task && self.mode == ParseMode::Tolerant).then_some(0);
+            self.current_block = Some(kind.clone());
+            self.indent_unit = None;
+            return Ok(TokenRef::BlockHeader(kind, span));
+        }
+
+        if let Some(blanks) = self.task_leading_blanks {
+            if line.trim().is_empty() {
+                self.task_leading_blanks = Some(blanks + 1);
+            } else {
+                self.task_leading_blanks = None;
+                self.record_task_gap(blanks, line_num);
+            }
+        }
+
+        if self.current_block == Some(BlockKind::Plan) {
+            self.check_indentation(line, line_num, span)?;
         }
 
         // Otherwise it's a content line
-        Ok(Token::Line(line.to_string(), Span::line(line_num)))
+        Ok(TokenRef::Line(line, span))
+    }
+
+    /// Single-line span from the first to the last non-whitespace character
+    ///
+    /// Columns are 1-indexed character positions. Blank lines span column 1.
+    fn line_span(line: &str, line_num: usize) -> Span {
+        let mut span = Span::line(line_num);
+        let leading = line.chars().take_while(|c| c.is_whitespace()).count();
+        let content_len = line.trim().chars().count();
+        if content_len > 0 {
+            span.start_col = leading + 1;
+            span.end_col = leading + content_len;
+        }
+        span
     }
 
     /// Tokenize entire input into token vector
@@ -175,9 +406,26 @@ impl<'a> Lexer<'a> {
         Ok(tokens)
     }
 
+    /// Tokenize entire input without copying line content
+    pub fn tokenize_all_ref(&mut self) -> ApexResult<Vec<TokenRef<'a>>> {
+        let mut tokens = Vec::new();
+        loop {
+            let token = self.next_token_ref()?;
+            let done = matches!(token, TokenRef::Eof);
+            tokens.push(token);
+            if done {
+                break;
+            }
+        }
+        Ok(tokens)
+    }
+
     /// Reset lexer to beginning
     pub fn reset(&mut self) {
         self.line_idx = 0;
+        self.current_block = None;
+        self.indent_unit = None;
+        self.task_leading_blanks = None;
         self.fixes.clear();
     }
 
@@ -210,6 +458,22 @@ mod tests {
         assert_eq!(Lexer::is_block_header_tolerant("TASK:"), None); // colon still not valid
     }
 
+    #[test]
+    fn test_registered_block_headers() {
+        let registry = BlockRegistry::new().with_block("BUDGET");
+        let input = "TASK\nDo it\nBUDGET\n$100\nOWNER\nalice";
+        let tokens = Lexer::new(input).with_blocks(registry.clone()).tokenize_all().unwrap();
+
+        assert!(matches!(&tokens[2], Token::BlockHeader(BlockKind::Custom(name), _) if name == "BUDGET"));
+        // Unregistered identifiers stay content
+        assert!(matches!(&tokens[4], Token::Line(s, _) if s == "OWNER"));
+
+        let mut lexer = Lexer::with_mode("budget\n$100", ParseMode::Tolerant).with_blocks(registry);
+        let tokens = lexer.tokenize_all().unwrap();
+        assert!(matches!(&tokens[0], Token::BlockHeader(BlockKind::Custom(name), _) if name == "BUDGET"));
+        assert_eq!(lexer.fixes.len(), 1);
+    }
+
     #[test]
     fn test_tolerant_mode_records_fixes() {
         let input = "task\nImplement feature\nplan\nStep 1";
@@ -226,6 +490,22 @@ mod tests {
         assert!(lexer.fixes[1].description.contains("plan"));
     }
 
+    #[test]
+    fn test_line_endings_normalized() {
+        assert_eq!(split_lines(""), Vec::<&str>::new());
+        assert_eq!(split_lines("a\r\nb\r\n"), vec!["a", "b"]);
+        assert_eq!(split_lines("a\u{2028}b\u{2029}c\r"), vec!["a", "b", "c"]);
+        assert_eq!(split_lines("a\n\n"), vec!["a", ""]);
+
+        let input = "TASK\r\nDo it\r\n\r\nPLAN\u{2028}Step 1\r\nStep 2\r";
+        let tokens = Lexer::new(input).tokenize_all().unwrap();
+        assert_eq!(tokens.len(), 7);
+        assert!(matches!(&tokens[0], Token::BlockHeader(BlockKind::Task, s) if s.start_line == 1));
+        assert!(matches!(&tokens[1], Token::Line(l, s) if l == "Do it" && s.start_line == 2 && s.end_col == 5));
+        assert!(matches!(&tokens[3], Token::BlockHeader(BlockKind::Plan, s) if s.start_line == 4));
+        assert!(matches!(&tokens[5], Token::Line(l, s) if l == "Step 2" && s.start_line == 6));
+    }
+
     #[test]
     fn test_simple_tokenize() {
         let input = "TASK\nImplement feature\nPLAN\nStep 1\nStep 2";
@@ -242,6 +522,69 @@ mod tests {
         assert!(matches!(&tokens[5], Token::Eof));
     }
 
+    #[test]
+    fn test_comment_lines_skipped() {
+        let input = "# header note\nTASK\n  # inline note\nDo it\nCONTEXT\n# Heading\nDIFF\n# kept";
+        let mut lexer = Lexer::new(input).with_comments(true);
+        let tokens = lexer.tokenize_all().unwrap();
+
+        assert_eq!(tokens.len(), 7); // TASK, line, CONTEXT, line, DIFF, line, EOF
+        assert!(matches!(&tokens[0], Token::BlockHeader(BlockKind::Task, s) if s.start_line == 2));
+        assert!(matches!(&tokens[1], Token::Line(s, span) if s == "Do it" && span.start_line == 4));
+        assert!(matches!(&tokens[3], Token::Line(s, _) if s == "# Heading"));
+        assert!(matches!(&tokens[5], Token::Line(s, span) if s == "# kept" && span.start_line == 8));
+    }
+
+    #[test]
+    fn test_comment_lines_disabled_by_default() {
+        let mut lexer = Lexer::new("TASK\n# not a comment");
+        let tokens = lexer.tokenize_all().unwrap();
+        assert!(matches!(&tokens[1], Token::Line(s, _) if s == "# not a comment"));
+    }
+
+    #[test]
+    fn test_column_spans() {
+        let input = "  TASK\n    Do it  \n\n\tStep";
+        let mut lexer = Lexer::new(input);
+        let tokens = lexer.tokenize_all().unwrap();
+
+        let header = tokens[0].span().unwrap();
+        assert_eq!((header.start_col, header.end_col), (3, 6));
+        let content = tokens[1].span().unwrap();
+        assert_eq!((content.start_col, content.end_col), (5, 9));
+        let blank = tokens[2].span().unwrap();
+        assert_eq!((blank.start_col, blank.end_col), (1, 1));
+        let tabbed = tokens[3].span().unwrap();
+        assert_eq!((tabbed.start_col, tabbed.end_col), (2, 5));
+    }
+
+    #[test]
+    fn test_task_content_after_blank_lines() {
+        let input = "TASK\n\n\nDo the thing";
+        let mut lexer = Lexer::new(input);
+        assert_eq!(lexer.tokenize_all().unwrap().len(), 5);
+        assert!(lexer.fixes.is_empty());
+
+        let mut lexer = Lexer::with_mode(input, ParseMode::Tolerant);
+        lexer.tokenize_all().unwrap();
+        assert_eq!(lexer.fixes.len(), 1);
+        assert_eq!(lexer.fixes[0].to_string(), "line 4: Moved TASK content up past 2 blank lines");
+
+        // Blank lines before the next header leave TASK empty, not misplaced
+        let mut lexer = Lexer::with_mode("TASK\n\nPLAN\nStep", ParseMode::Tolerant);
+        lexer.tokenize_all().unwrap();
+        assert!(lexer.fixes.is_empty());
+
+        // Reset drops blank lines counted before it
+        let mut lexer = Lexer::with_mode("Intro\nTASK\n\n\nDo it", ParseMode::Tolerant);
+        for _ in 0..4 {
+            lexer.next_token().unwrap();
+        }
+        lexer.reset();
+        lexer.next_token().unwrap();
+        assert!(lexer.fixes.is_empty());
+    }
+
     #[test]
     fn test_empty_lines_preserved() {
         let input = "TASK\n\nLine after empty";
@@ -3,7 +3,8 @@
 //! Tokenization and parsing of APEX documents.
 
 pub mod lexer;
+#[allow(clippy::module_inception)]
 pub mod parser;
 
-pub use lexer::{Lexer, Token, ParseMode, ParseFix};
-pub use parser::{parse_str, parse_str_with_mode, ParserConfig};
+pub use lexer::{Lexer, Token, TokenRef, ParseMode, ParseFix};
+pub use parser::{parse_str, parse_str_borrowed, parse_str_with_mode, parse_str_with_config, parse_str_with_limits, parse_reader, parse_multi, quick_check, ParseLimits, ParseResult, ParserConfig};
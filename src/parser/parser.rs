@@ -2,15 +2,207 @@
 //!
 //! Parses token stream into ApexDocument AST.
 
-use crate::ast::{ApexDocument, Block, Span};
-use crate::errors::ApexResult;
-use crate::parser::lexer::{Lexer, Token, ParseMode, ParseFix};
+use crate::ast::{is_block_identifier, ApexDocument, ApexDocumentRef, Block, BlockKind, BlockRef, BlockRegistry, Span};
+use crate::errors::{ApexError, ApexErrorKind, ApexResult};
+use crate::parser::lexer::{line_iter, line_ranges, Lexer, TokenRef, ParseMode, ParseFix};
+use crate::validate::canonical_meta_key;
+use std::io::{BufRead, BufReader, Read};
+use std::ops::Range;
 
 /// Parse APEX string into document AST (strict mode)
 pub fn parse_str(input: &str) -> ApexResult<ApexDocument> {
+    parse_str_borrowed(input).map(|doc| doc.to_document())
+}
+
+/// Parse APEX string into a document borrowing from `input` (strict mode)
+///
+/// Content lines are `&str` slices of `input`; no per-line allocation.
+pub fn parse_str_borrowed(input: &str) -> ApexResult<ApexDocumentRef<'_>> {
+    let mut lexer = Lexer::new(input);
+    let tokens = lexer.tokenize_all_ref()?;
+    parse_tokens(&tokens, &lexer, &ParserConfig::default())
+}
+
+/// Parse APEX from a reader (strict mode)
+///
+/// Produces the same document as [`parse_str`] on the same content; line
+/// endings are normalized as in [`split_lines`](crate::parser::lexer::split_lines).
+/// The input is read line by line but all lines are buffered before lexing,
+/// so memory use matches [`parse_str`] on the whole text; only the single
+/// contiguous input string is avoided. Fails with [`ApexErrorKind::Io`] on
+/// I/O errors and invalid UTF-8.
+pub fn parse_reader<R: Read>(reader: R) -> ApexResult<ApexDocument> {
+    let mut reader = BufReader::new(reader);
+    let mut lines = Vec::new();
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    let mut chunk = String::new();
+    loop {
+        chunk.clear();
+        let read = reader.read_line(&mut chunk).map_err(|e| {
+            ApexError::new(ApexErrorKind::Io, format!("Failed to read input: {}", e))
+        })?;
+        if read == 0 {
+            break;
+        }
+        // A chunk ends at `\n`, so only its last piece can be a trailing break
+        for (range, line) in line_ranges(&chunk) {
+            lines.push(line.to_string());
+            ranges.push(offset + range.start..offset + range.end);
+        }
+        offset += read;
+    }
+
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let mut lexer = Lexer::from_lines(lines, ranges, ParseMode::Strict);
+    let tokens = lexer.tokenize_all_ref()?;
+    parse_tokens(&tokens, &lexer, &ParserConfig::default()).map(|doc| doc.to_document())
+}
+
+/// Size caps for untrusted input, checked before tokenizing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// Maximum input size in bytes
+    pub max_bytes: usize,
+    /// Maximum number of lines
+    pub max_lines: usize,
+    /// Maximum number of blocks (headers)
+    pub max_blocks: usize,
+    /// Maximum length of a single line in bytes
+    pub max_line_length: usize,
+}
+
+impl Default for ParseLimits {
+    /// Generous caps: 10 MiB, 100k lines, 1k blocks, 64 KiB per line
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024 * 1024,
+            max_lines: 100_000,
+            max_blocks: 1_000,
+            max_line_length: 64 * 1024,
+        }
+    }
+}
+
+impl ParseLimits {
+    /// Check `input` against the byte, line and line-length caps
+    ///
+    /// Lines are scanned lazily, so oversized input is rejected without
+    /// being split or tokenized.
+    pub fn check(&self, input: &str) -> ApexResult<()> {
+        if input.len() > self.max_bytes {
+            return Err(ApexError::parse(
+                format!("Input is {} bytes, exceeding the limit of {}", input.len(), self.max_bytes),
+                None,
+            ));
+        }
+        for (idx, line) in line_iter(input).enumerate() {
+            if idx >= self.max_lines {
+                return Err(ApexError::parse(
+                    format!("Input exceeds the limit of {} lines", self.max_lines),
+                    Some(idx + 1),
+                ));
+            }
+            if line.len() > self.max_line_length {
+                return Err(ApexError::parse(
+                    format!(
+                        "Line is {} bytes, exceeding the limit of {}",
+                        line.len(),
+                        self.max_line_length
+                    ),
+                    Some(idx + 1),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse APEX string (strict mode) after checking it against `limits`
+pub fn parse_str_with_limits(input: &str, limits: ParseLimits) -> ApexResult<ApexDocument> {
+    limits.check(input)?;
     let mut lexer = Lexer::new(input);
-    let tokens = lexer.tokenize_all()?;
-    parse_tokens(&tokens)
+    let tokens = lexer.tokenize_all_ref()?;
+    let headers = tokens.iter().filter(|t| matches!(t, TokenRef::BlockHeader(..))).count();
+    if headers > limits.max_blocks {
+        return Err(ApexError::parse(
+            format!("Input has {} blocks, exceeding the limit of {}", headers, limits.max_blocks),
+            None,
+        ));
+    }
+    parse_tokens(&tokens, &lexer, &ParserConfig::default()).map(|doc| doc.to_document())
+}
+
+/// Cheap pre-filter: exactly one uppercase `TASK` header with content
+///
+/// Only scans lines; nothing is tokenized, no views are built and no
+/// constraints canonicalized. `false` means validation would fail (TASK
+/// missing, repeated or empty); `true` does not guarantee it succeeds.
+pub fn quick_check(input: &str) -> bool {
+    let mut tasks = 0;
+    let mut in_task = false;
+    let mut task_has_content = false;
+    for line in line_iter(input) {
+        let trimmed = line.trim();
+        if is_block_identifier(trimmed) && BlockKind::from_str(trimmed).is_some() {
+            in_task = trimmed == "TASK";
+            tasks += usize::from(in_task);
+        } else if in_task && !trimmed.is_empty() {
+            task_has_content = true;
+        }
+    }
+    tasks == 1 && task_has_content
+}
+
+/// Line that separates documents in a multi-document file
+pub const DOCUMENT_SEPARATOR: &str = "---";
+
+/// Parse a bundle of documents separated by lines that are exactly `---`
+///
+/// Each section is parsed independently (strict mode) and must contain a
+/// TASK block. Spans, byte ranges and error lines refer to the whole input;
+/// error messages name the failing section by its 0-based index.
+pub fn parse_multi(input: &str) -> ApexResult<Vec<ApexDocument>> {
+    // (first line index, byte range) of each section
+    let mut sections: Vec<(usize, Range<usize>)> = vec![(0, 0..input.len())];
+    for (idx, (range, line)) in line_ranges(input).enumerate() {
+        if line == DOCUMENT_SEPARATOR {
+            sections.last_mut().unwrap().1.end = range.start;
+            // The next section starts past the separator's stripped `\r`s and line break
+            let rest = input[range.end..].trim_start_matches('\r');
+            let start = input.len() - rest.len() + rest.chars().next().map_or(0, char::len_utf8);
+            sections.push((idx + 1, start..input.len()));
+        }
+    }
+
+    sections
+        .into_iter()
+        .enumerate()
+        .map(|(index, (offset, bytes))| {
+            let in_section = |mut err: ApexError| {
+                err.message = format!("Section {}: {}", index, err.message);
+                err.line = err.line.map(|l| l + offset);
+                if let Some(span) = err.span.as_mut() {
+                    span.start_line += offset;
+                    span.end_line += offset;
+                }
+                err
+            };
+
+            let mut doc = parse_str(&input[bytes.clone()]).map_err(in_section)?;
+            if doc.task().is_none() {
+                return Err(in_section(ApexError::missing_task()));
+            }
+            for block in doc.blocks.iter_mut() {
+                block.span.start_line += offset;
+                block.span.end_line += offset;
+                block.byte_start += bytes.start;
+                block.byte_end += bytes.start;
+                block.line_numbers.iter_mut().for_each(|n| *n += offset);
+            }
+            Ok(doc)
+        })
+        .collect()
 }
 
 /// Parse result with fixes from tolerant mode
@@ -20,63 +212,205 @@ pub struct ParseResult {
     pub fixes: Vec<ParseFix>,
 }
 
+impl ParseResult {
+    /// Record the applied fixes as a `parse_fixes` META entry
+    ///
+    /// The entry reads `parse_fixes=<n> fixes: line 1: ...; line 4: ...` and
+    /// replaces any existing one; a META block is appended if missing. The
+    /// entry is placed on the line after META, whose span is extended to
+    /// cover it. No-op when no fixes were applied.
+    pub fn record_fixes_in_meta(&mut self) {
+        if self.fixes.is_empty() {
+            return;
+        }
+        let noun = if self.fixes.len() == 1 { "fix" } else { "fixes" };
+        let details: Vec<String> = self.fixes.iter().map(|f| f.to_string()).collect();
+        let entry = format!("parse_fixes={} {}: {}", self.fixes.len(), noun, details.join("; "));
+
+        let meta = match self.document.blocks.iter().position(|b| b.kind == BlockKind::Meta) {
+            Some(idx) => &mut self.document.blocks[idx],
+            None => {
+                let line = self.document.blocks.last().map_or(1, |b| b.span.end_line + 1);
+                self.document.blocks.push(Block::new(BlockKind::Meta, Vec::new(), Span::line(line)));
+                self.document.blocks.last_mut().unwrap()
+            }
+        };
+        let is_fixes_entry = |l: &str| {
+            let key = l.find('=').or_else(|| l.find(':')).map(|idx| &l[..idx]);
+            key.is_some_and(|k| canonical_meta_key(k) == "parse_fixes")
+        };
+        let (mut lines, mut line_numbers): (Vec<String>, Vec<usize>) = meta
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| !is_fixes_entry(l))
+            .map(|(idx, l)| (l.clone(), meta.source_line(idx)))
+            .unzip();
+        meta.span.end_line += 1;
+        lines.push(entry);
+        line_numbers.push(meta.span.end_line);
+
+        let contiguous = line_numbers.iter().enumerate().all(|(i, &n)| n == meta.span.start_line + 1 + i);
+        if contiguous {
+            line_numbers.clear();
+        }
+        meta.lines = lines;
+        meta.line_numbers = line_numbers;
+    }
+}
+
 /// Parse APEX string with specified mode, returning fixes if any
 pub fn parse_str_with_mode(input: &str, mode: ParseMode) -> ApexResult<ParseResult> {
     let mut lexer = Lexer::with_mode(input, mode);
-    let tokens = lexer.tokenize_all()?;
-    let document = parse_tokens(&tokens)?;
+    let tokens = lexer.tokenize_all_ref()?;
+    let mut document = parse_tokens(&tokens, &lexer, &ParserConfig::default())?.to_document();
+    if mode == ParseMode::Tolerant {
+        normalize_tolerant(&mut document);
+    }
     Ok(ParseResult {
         document,
         fixes: lexer.fixes,
     })
 }
 
+/// Apply the document rewrites behind tolerant-mode fixes
+fn normalize_tolerant(document: &mut ApexDocument) {
+    normalize_plan_indentation(document);
+    drop_leading_task_blanks(document);
+}
+
+/// Drop blank lines between `TASK` and its first content line
+fn drop_leading_task_blanks(document: &mut ApexDocument) {
+    for block in document.blocks.iter_mut().filter(|b| b.kind == BlockKind::Task) {
+        if let Some(first) = block.lines.iter().position(|l| !l.trim().is_empty()) {
+            if first > 0 {
+                block.line_numbers = (first..block.lines.len()).map(|i| block.source_line(i)).collect();
+                block.lines.drain(..first);
+            }
+        }
+    }
+}
+
+/// Rewrite tabs in PLAN indentation as two spaces (the PLAN nesting unit)
+fn normalize_plan_indentation(document: &mut ApexDocument) {
+    for block in document.blocks.iter_mut().filter(|b| b.kind == BlockKind::Plan) {
+        for line in block.lines.iter_mut() {
+            let body = line.trim_start_matches([' ', '\t']);
+            let indent = &line[..line.len() - body.len()];
+            if indent.contains('\t') {
+                *line = format!("{}{}", indent.replace('\t', "  "), body);
+            }
+        }
+    }
+}
+
+/// Parse APEX string with full parser configuration, returning fixes if any
+pub fn parse_str_with_config(input: &str, config: &ParserConfig) -> ApexResult<ParseResult> {
+    let mut lexer = Lexer::with_mode(input, config.mode)
+        .with_comments(config.allow_comments)
+        .with_blocks(config.block_registry.clone());
+    let tokens = lexer.tokenize_all_ref()?;
+    let mut document = parse_tokens(&tokens, &lexer, config)?.to_document();
+    if config.mode == ParseMode::Tolerant {
+        normalize_tolerant(&mut document);
+    }
+    let mut result = ParseResult {
+        document,
+        fixes: lexer.fixes,
+    };
+    if config.record_fixes {
+        result.record_fixes_in_meta();
+    }
+    Ok(result)
+}
+
 /// Parse token stream into document AST
-fn parse_tokens(tokens: &[Token]) -> ApexResult<ApexDocument> {
+///
+/// `lexer` produced `tokens` and supplies the byte ranges of their lines.
+fn parse_tokens<'a>(
+    tokens: &[TokenRef<'a>],
+    lexer: &Lexer<'a>,
+    config: &ParserConfig,
+) -> ApexResult<ApexDocumentRef<'a>> {
     let mut blocks = Vec::new();
     let mut idx = 0;
 
     while idx < tokens.len() {
         match &tokens[idx] {
-            Token::Eof => break,
+            TokenRef::Eof => break,
 
-            Token::BlockHeader(kind, header_span) => {
+            TokenRef::BlockHeader(kind, header_span) => {
                 // Start collecting block content
-                let start_line = header_span.start_line;
+                let mut span = *header_span;
                 let mut lines = Vec::new();
-                let mut end_line = start_line;
+                let mut line_numbers = Vec::new();
                 idx += 1;
 
                 // Collect all lines until next header or EOF
                 while idx < tokens.len() {
                     match &tokens[idx] {
-                        Token::Line(content, span) => {
-                            lines.push(content.clone());
-                            end_line = span.end_line;
+                        TokenRef::Line(content, line_span) => {
+                            // Strict configs reject a header-shaped line that is neither
+                            // built-in nor registered where it would start a block: after
+                            // a blank line (DIFF/CONTEXT are verbatim)
+                            let verbatim = matches!(kind, BlockKind::Diff | BlockKind::Context);
+                            let starts_block = lines.last().is_some_and(|l: &&str| l.trim().is_empty());
+                            if config.strict
+                                && !config.allow_unknown_blocks
+                                && !verbatim
+                                && starts_block
+                                && is_block_identifier(content.trim())
+                            {
+                                return Err(ApexError::unknown_block(content.trim(), Some(line_span.start_line))
+                                    .with_span(*line_span));
+                            }
+                            lines.push(*content);
+                            line_numbers.push(line_span.start_line);
+                            // Blank lines extend the block but carry no column info
+                            if content.trim().is_empty() {
+                                span.end_line = line_span.end_line;
+                                span.end_col = 1;
+                            } else {
+                                span = span.merge(line_span);
+                            }
                             idx += 1;
                         }
-                        Token::BlockHeader(_, _) | Token::Eof => break,
+                        TokenRef::BlockHeader(_, _) | TokenRef::Eof => break,
                     }
                 }
 
-                let span = Span::new(start_line, end_line);
-                blocks.push(Block::new(*kind, lines, span));
+                let byte_start = lexer.line_byte_range(span.start_line).map_or(0, |r| r.start);
+                let byte_end = lexer.line_byte_range(span.end_line).map_or(byte_start, |r| r.end);
+                // Explicit numbers are only kept when skipped comments leave gaps
+                let contiguous = line_numbers.iter().enumerate().all(|(i, &n)| n == span.start_line + 1 + i);
+                if contiguous {
+                    line_numbers.clear();
+                }
+                blocks.push(BlockRef {
+                    kind: kind.clone(),
+                    lines,
+                    span,
+                    byte_range: byte_start..byte_end,
+                    line_numbers,
+                });
             }
 
-            Token::Line(content, _span) => {
-                // Lines before first header - skip or error?
-                // Per spec, we'll skip leading non-block content (whitespace, comments)
-                if !content.trim().is_empty() {
-                    // Non-empty line before any block - this is likely an error
-                    // but for tolerant parsing, we skip it
-                    // TODO: Consider strict mode that errors here
+            TokenRef::Line(content, span) => {
+                // Lines before the first header: blank lines are always skipped,
+                // other content only when the config allows it
+                if !content.trim().is_empty() && !config.allow_leading_content {
+                    return Err(ApexError::parse(
+                        format!("Content before first block: '{}'", content.trim()),
+                        Some(span.start_line),
+                    )
+                    .with_span(*span));
                 }
                 idx += 1;
             }
         }
     }
 
-    Ok(ApexDocument::with_blocks(blocks))
+    Ok(ApexDocumentRef { blocks })
 }
 
 /// Parser configuration
@@ -88,6 +422,14 @@ pub struct ParserConfig {
     pub allow_leading_content: bool,
     /// Strict mode - fail on any irregularity
     pub strict: bool,
+    /// Header case handling and indentation repair
+    pub mode: ParseMode,
+    /// Skip `#` comment lines (except inside DIFF and CONTEXT)
+    pub allow_comments: bool,
+    /// Extension blocks parsed as [`BlockKind::Custom`]
+    pub block_registry: BlockRegistry,
+    /// Record tolerant-mode fixes as a `parse_fixes` META entry
+    pub record_fixes: bool,
 }
 
 impl Default for ParserConfig {
@@ -96,6 +438,10 @@ impl Default for ParserConfig {
             allow_unknown_blocks: false,
             allow_leading_content: true,
             strict: false,
+            mode: ParseMode::Strict,
+            allow_comments: false,
+            block_registry: BlockRegistry::default(),
+            record_fixes: false,
         }
     }
 }
@@ -107,6 +453,10 @@ impl ParserConfig {
             allow_unknown_blocks: false,
             allow_leading_content: false,
             strict: true,
+            mode: ParseMode::Strict,
+            allow_comments: false,
+            block_registry: BlockRegistry::default(),
+            record_fixes: false,
         }
     }
 
@@ -116,8 +466,24 @@ impl ParserConfig {
             allow_unknown_blocks: true,
             allow_leading_content: true,
             strict: false,
+            mode: ParseMode::Tolerant,
+            allow_comments: true,
+            block_registry: BlockRegistry::default(),
+            record_fixes: false,
         }
     }
+
+    /// Builder: record applied fixes in META (see [`ParseResult::record_fixes_in_meta`])
+    pub fn with_recorded_fixes(mut self) -> Self {
+        self.record_fixes = true;
+        self
+    }
+
+    /// Builder: recognize the extension blocks in `registry`
+    pub fn with_blocks(mut self, registry: BlockRegistry) -> Self {
+        self.block_registry = registry;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -214,6 +580,332 @@ version=1.0"#;
         assert!(doc.task().is_some());
     }
 
+    #[test]
+    fn test_record_fixes_in_meta() {
+        use crate::validate::validate;
+
+        let input = "task\nDo it\nplan\nStep 1";
+        let config = ParserConfig::tolerant().with_recorded_fixes();
+        let parsed = parse_str_with_config(input, &config).unwrap();
+        assert_eq!(parsed.fixes.len(), 2);
+
+        let meta = parsed.document.meta().unwrap();
+        let meta_lines = meta.content_lines();
+        assert_eq!(meta_lines.len(), 1);
+        assert!(meta_lines[0].starts_with("parse_fixes=2 fixes: line 1: "));
+        // The appended META block and its entry follow the last block
+        assert_eq!((meta.span.start_line, meta.span.end_line), (5, 6));
+        assert_eq!(meta.source_line(0), 6);
+
+        // Round trip: the entry survives re-serialization and a strict re-parse
+        let text: Vec<String> = parsed
+            .document
+            .blocks
+            .iter()
+            .map(|b| format!("{}\n{}", b.kind, b.lines.join("\n")))
+            .collect();
+        let validated = validate(parse_str(&text.join("\n")).unwrap()).unwrap();
+        assert!(validated.meta.as_ref().unwrap().parse_fixes().is_some());
+
+        // Recording again replaces the entry; without fixes nothing is added
+        let mut reparsed = parse_str_with_mode(&text.join("\n"), ParseMode::Tolerant).unwrap();
+        reparsed.fixes = parsed.fixes.clone();
+        let end_line = reparsed.document.meta().unwrap().span.end_line;
+        reparsed.record_fixes_in_meta();
+        let meta = reparsed.document.meta().unwrap();
+        assert_eq!(meta.content_lines().len(), 1);
+        assert_eq!(meta.span.end_line, end_line + 1);
+        assert_eq!(meta.source_line(0), end_line + 1);
+
+        let clean = parse_str_with_config("TASK\nDo it", &config).unwrap();
+        assert!(clean.document.meta().is_none());
+    }
+
+    #[test]
+    fn test_default_config_matches_parse_str() {
+        let input = "TASK\nDo it\n\nPLAN\nStep 1\n  Step 2\n\nGOALS\nDone";
+        let result = parse_str_with_config(input, &ParserConfig::default()).unwrap();
+        assert_eq!(result.document, parse_str(input).unwrap());
+        assert!(result.fixes.is_empty());
+
+        // Lowercase headers are content in the default (strict) mode
+        let lower = "TASK\nDo it\nplan\nStep 1";
+        let result = parse_str_with_config(lower, &ParserConfig::default()).unwrap();
+        assert_eq!(result.document, parse_str(lower).unwrap());
+    }
+
+    #[test]
+    fn test_comments_with_config() {
+        let input = "# Generated plan\nTASK\nDo it\n\nPLAN\n# first things first\nStep 1\nStep 2\n\nGOALS\nDone";
+        let config = ParserConfig {
+            allow_comments: true,
+            ..ParserConfig::default()
+        };
+        let doc = parse_str_with_config(input, &config).unwrap().document;
+
+        let plan = doc.plan().unwrap();
+        assert_eq!(plan.content_lines(), vec!["Step 1", "Step 2"]);
+        assert_eq!(doc.goals().unwrap().span.start_line, 10);
+
+        // Without the flag, comments are ordinary content
+        let doc = parse_str(input).unwrap();
+        assert_eq!(doc.plan().unwrap().content_lines().len(), 3);
+    }
+
+    #[test]
+    fn test_block_column_spans() {
+        let input = "  TASK\n  Do it\nPLAN\n    Step 1\n  Step two";
+        let doc = parse_str(input).unwrap();
+
+        let task = doc.task().unwrap().span;
+        assert_eq!((task.start_line, task.start_col), (1, 3));
+        assert_eq!((task.end_line, task.end_col), (2, 7));
+
+        let plan = doc.plan().unwrap().span;
+        assert_eq!((plan.start_line, plan.start_col), (3, 1));
+        assert_eq!((plan.end_line, plan.end_col), (5, 10));
+    }
+
+    #[test]
+    fn test_borrowed_parse_matches_owned() {
+        let input = "TASK\nDo it\n\nPLAN\n  Step 1\nStep 2";
+        let borrowed = parse_str_borrowed(input).unwrap();
+
+        let plan = borrowed.get_block(crate::ast::BlockKind::Plan).unwrap();
+        assert_eq!(plan.lines[0], "  Step 1");
+        // Lines point into the input rather than fresh allocations
+        assert!(input.as_bytes().as_ptr_range().contains(&plan.lines[0].as_ptr()));
+        assert_eq!(plan.content_lines(), vec!["Step 1", "Step 2"]);
+
+        assert_eq!(borrowed.to_document(), parse_str(input).unwrap());
+    }
+
+    #[test]
+    fn test_leading_content() {
+        let strict = ParserConfig::strict();
+
+        let doc = parse_str_with_config("\n   \nTASK\nDo it", &strict).unwrap().document;
+        assert!(doc.task().is_some());
+
+        let err = parse_str_with_config("\nHere is the plan:\nTASK\nDo it", &strict).unwrap_err();
+        assert_eq!(err.kind, crate::errors::ApexErrorKind::ParseError);
+        assert_eq!(err.line, Some(2));
+        assert!(err.message.contains("Here is the plan:"));
+
+        // Default and tolerant configs keep skipping leading prose
+        let doc = parse_str_with_config("Here is the plan:\nTASK\nDo it", &ParserConfig::tolerant())
+            .unwrap()
+            .document;
+        assert_eq!(doc.blocks.len(), 1);
+        assert!(parse_str("Here is the plan:\nTASK\nDo it").is_ok());
+    }
+
+    #[test]
+    fn test_custom_blocks() {
+        let input = "TASK\nDo it\n\nBUDGET\n$100\n\nOWNER\nalice\n\nPLAN\nStep 1";
+        let registry = BlockRegistry::new().with_block("BUDGET").with_block("OWNER");
+
+        let doc = parse_str_with_config(input, &ParserConfig::default().with_blocks(registry.clone()))
+            .unwrap()
+            .document;
+        let custom = doc.custom_blocks();
+        assert_eq!(custom.len(), 2);
+        assert_eq!(custom[0].kind, BlockKind::Custom("BUDGET".to_string()));
+        assert_eq!(custom[0].content(), "$100");
+        assert_eq!(custom[1].content(), "alice");
+        assert_eq!(doc.plan().unwrap().content(), "Step 1");
+
+        // Strict configs reject unregistered blocks
+        let strict = ParserConfig::strict().with_blocks(BlockRegistry::new().with_block("BUDGET"));
+        let err = parse_str_with_config(input, &strict).unwrap_err();
+        assert_eq!(err.kind, crate::errors::ApexErrorKind::UnknownBlock);
+        assert_eq!(err.line, Some(7));
+        assert!(parse_str_with_config(input, &ParserConfig::strict().with_blocks(registry)).is_ok());
+        let lenient = ParserConfig {
+            allow_unknown_blocks: true,
+            ..ParserConfig::strict()
+        };
+        assert!(parse_str_with_config(input, &lenient).is_ok());
+
+        // Header-shaped content not at a block start is ordinary content
+        let goals = "TASK\nDo it\nGOALS\nCI\nGreen build";
+        let doc = parse_str_with_config(goals, &ParserConfig::strict()).unwrap().document;
+        assert_eq!(doc.goals().unwrap().content_lines(), vec!["CI", "Green build"]);
+
+        // Without a registry, the lines stay content of the previous block
+        let doc = parse_str(input).unwrap();
+        assert!(doc.custom_blocks().is_empty());
+        assert_eq!(doc.task().unwrap().content_lines().len(), 5);
+    }
+
+    #[test]
+    fn test_plan_indentation_consistency() {
+        let mixed = "TASK\nX\nPLAN\nBuild\n \tLink";
+        let err = parse_str(mixed).unwrap_err();
+        assert_eq!(err.line, Some(5));
+        assert!(err.message.contains("mixes tabs and spaces"));
+
+        let switched = "TASK\nX\nPLAN\nPrepare\n  Fetch\nBuild\n\tLink";
+        let err = parse_str(switched).unwrap_err();
+        assert_eq!(err.line, Some(7));
+        assert!(err.message.contains("from spaces to tabs"));
+
+        // Each block picks its own unit; other blocks are not checked
+        assert!(parse_str("TASK\nX\nPLAN\nA\n\tB\nCONTEXT\n  x\n\ty").is_ok());
+
+        let tolerant = parse_str_with_mode(switched, ParseMode::Tolerant).unwrap();
+        assert_eq!(tolerant.fixes.len(), 1);
+        assert_eq!(tolerant.fixes[0].line, 7);
+        assert_eq!(tolerant.document.plan().unwrap().lines[3], "  Link");
+    }
+
+    #[test]
+    fn test_task_content_after_blank_lines() {
+        let input = "TASK\n\n\nDo the thing\nPLAN\nStep 1";
+        // Strict parsing keeps the blank lines as TASK content
+        let strict = parse_str(input).unwrap();
+        assert_eq!(strict.task().unwrap().content(), "Do the thing");
+        assert_eq!(strict.task().unwrap().lines.len(), 3);
+
+        let result = parse_str_with_mode(input, ParseMode::Tolerant).unwrap();
+        let task = result.document.task().unwrap();
+        assert_eq!(task.lines, vec!["Do the thing"]);
+        assert_eq!(task.span.start_line, 1);
+        assert_eq!(result.fixes.len(), 1);
+        assert_eq!(result.fixes[0].line, 4);
+    }
+
+    #[test]
+    fn test_crlf_document() {
+        let lf = "TASK\nDo it\n\nPLAN\n  Step 1\nStep 2\n";
+        let crlf = lf.replace('\n', "\r\n");
+        let doc = parse_str(&crlf).unwrap();
+
+        assert_eq!(doc, parse_str(lf).unwrap());
+        assert_eq!(doc.task().unwrap().content(), "Do it");
+        let plan = doc.plan().unwrap().span;
+        assert_eq!((plan.start_line, plan.end_line, plan.end_col), (4, 6, 6));
+    }
+
+    #[test]
+    fn test_block_byte_ranges() {
+        let input = "  TASK\nDo é\u{2028}now\n\nPLAN\r\n  Step 1\r\nStep 2\r\n\nMETA\nversion=1.1";
+        let doc = parse_str(input).unwrap();
+        let slices: Vec<&str> = doc.blocks.iter().map(|b| &input[b.byte_range()]).collect();
+        assert_eq!(slices, vec!["  TASK\nDo é\u{2028}now\n", "PLAN\r\n  Step 1\r\nStep 2\r\n", "META\nversion=1.1"]);
+
+        let borrowed = parse_str_borrowed(input).unwrap();
+        assert_eq!(borrowed.blocks[1].byte_range, doc.blocks[1].byte_range());
+
+        // Byte ranges refer to the original input in every parse entry point
+        let config = ParserConfig::tolerant();
+        let tolerant = parse_str_with_config("# note\ntask\nDo it\nplan\n\tStep", &config).unwrap().document;
+        assert_eq!(tolerant.blocks[1].byte_range(), 18..28);
+
+        let multi = "TASK\nA\r\n---\r\nTASK\nB\nPLAN\nStep";
+        let docs = parse_multi(multi).unwrap();
+        assert_eq!(&multi[docs[1].blocks[0].byte_range()], "TASK\nB");
+        assert_eq!(&multi[docs[1].blocks[1].byte_range()], "PLAN\nStep");
+
+        // Blocks built in code have no source text
+        assert!(Block::new(BlockKind::Task, vec![], Span::line(1)).byte_range().is_empty());
+    }
+
+    #[test]
+    fn test_parse_reader_matches_parse_str() {
+        let inputs = [
+            "TASK\nDo it\n\nPLAN\n  Step 1\nStep 2\n",
+            "TASK\r\nDo it\r\nPLAN\u{2028}Step 1\r\nStep 2\r",
+            "TASK\nDo it\nCONTEXT\n\n\u{2029}",
+            "TASK\nDo it\nTASK\nAgain",
+            "GOALS\nNo task\n\r",
+            "",
+        ];
+        for input in inputs {
+            match (parse_reader(input.as_bytes()), parse_str(input)) {
+                (Ok(read), Ok(parsed)) => assert_eq!(read, parsed, "{:?}", input),
+                (Err(read), Err(parsed)) => assert_eq!(read.to_string(), parsed.to_string(), "{:?}", input),
+                (read, parsed) => panic!("{:?}: {:?} vs {:?}", input, read, parsed),
+            }
+        }
+
+        let err = parse_reader(&b"TASK\n\xFF\n"[..]).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::Io);
+        assert!(err.message.starts_with("Failed to read input"));
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let input = "TASK\nDo it\nPLAN\nStep 1\nStep 2";
+        let doc = parse_str_with_limits(input, ParseLimits::default()).unwrap();
+        assert_eq!(doc, parse_str(input).unwrap());
+
+        let limits = |max_bytes, max_lines, max_blocks, max_line_length| ParseLimits {
+            max_bytes,
+            max_lines,
+            max_blocks,
+            max_line_length,
+        };
+        let kind = crate::errors::ApexErrorKind::ParseError;
+
+        let err = parse_str_with_limits(input, limits(10, 100, 100, 100)).unwrap_err();
+        assert_eq!(err.kind, kind);
+        assert!(err.message.contains("bytes"));
+
+        let err = parse_str_with_limits(input, limits(100, 4, 100, 100)).unwrap_err();
+        assert_eq!(err.line, Some(5));
+
+        let err = parse_str_with_limits(input, limits(100, 100, 1, 100)).unwrap_err();
+        assert!(err.message.contains("2 blocks"));
+
+        let err = parse_str_with_limits(input, limits(100, 100, 100, 5)).unwrap_err();
+        assert_eq!(err.line, Some(4));
+    }
+
+    #[test]
+    fn test_quick_check() {
+        assert!(quick_check("TASK\nDo it"));
+        assert!(quick_check("Intro prose\nGOALS\nFast\n  TASK  \nDo it\nPLAN\nStep 1"));
+
+        assert!(!quick_check(""));
+        assert!(!quick_check("GOALS\nFast"));
+        assert!(!quick_check("task\nDo it"));
+        assert!(!quick_check("TASK\n\n   \nPLAN\nStep 1"));
+        assert!(!quick_check("TASK\nOne\nTASK\nTwo"));
+        // Header-shaped lines that are not blocks are content
+        assert!(quick_check("TASK\nTODO"));
+    }
+
+    #[test]
+    fn test_parse_multi() {
+        let input = "TASK\nFirst\n---\nTASK\nSecond\nPLAN\nStep 1\n---\nTASK\nThird";
+        let docs = parse_multi(input).unwrap();
+        assert_eq!(docs.len(), 3);
+        assert_eq!(docs[1].task().unwrap().content(), "Second");
+        assert_eq!(docs[1].plan().unwrap().span.start_line, 6);
+        assert_eq!(docs[2].task().unwrap().span.start_line, 9);
+
+        // No separator: a single document
+        assert_eq!(parse_multi("TASK\nOnly").unwrap().len(), 1);
+        // Only exact separator lines split
+        assert_eq!(parse_multi("TASK\nA\n--- \n----").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_multi_errors() {
+        let err = parse_multi("TASK\nFirst\n---\nPLAN\nStep 1").unwrap_err();
+        assert_eq!(err.kind, crate::errors::ApexErrorKind::MissingTask);
+        assert!(err.message.starts_with("Section 1: "));
+
+        let err = parse_multi("TASK\nA\n---\nTASK\nB\n---\nTASK\nC\nPLAN\nBuild\n \tLink").unwrap_err();
+        assert!(err.message.starts_with("Section 2: "));
+        assert!(err.message.contains("mixes tabs and spaces"));
+        assert_eq!(err.line, Some(11));
+
+        let err = parse_multi("TASK\nA\n---").unwrap_err();
+        assert!(err.message.starts_with("Section 1: "));
+    }
+
     #[test]
     fn test_empty_input() {
         let input = "";
@@ -0,0 +1,215 @@
+//! Execution Plan Diffing
+//!
+//! Compares two execution plans, e.g. before and after an agent re-plans.
+//! Steps are paired regardless of position: identical descriptions first,
+//! then the most similar remaining pairs, where similarity is the word-level
+//! longest common subsequence relative to the combined description length.
+
+use crate::interpreter::{ExecutionPlan, ExecutionStep};
+use serde::{Deserialize, Serialize};
+
+/// Minimum description similarity (0.0-1.0) for two steps to be paired
+pub const STEP_SIMILARITY_THRESHOLD: f64 = 0.5;
+
+/// A step present in both plans whose description or tool changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepChange {
+    /// Step in the original plan
+    pub old: ExecutionStep,
+    /// Matching step in the new plan
+    pub new: ExecutionStep,
+    /// Description similarity (1.0 = identical)
+    pub similarity: f64,
+}
+
+/// Structured differences between two execution plans
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlanDiff {
+    /// Steps only in the new plan
+    pub added_steps: Vec<ExecutionStep>,
+    /// Steps only in the original plan
+    pub removed_steps: Vec<ExecutionStep>,
+    /// Paired steps that differ
+    pub modified_steps: Vec<StepChange>,
+    /// Constraints only in the new plan
+    pub added_constraints: Vec<String>,
+    /// Constraints only in the original plan
+    pub removed_constraints: Vec<String>,
+    /// Tool names only in the new plan
+    pub added_tools: Vec<String>,
+    /// Tool names only in the original plan
+    pub removed_tools: Vec<String>,
+}
+
+impl PlanDiff {
+    /// Check if the plans are equivalent
+    pub fn is_empty(&self) -> bool {
+        self.added_steps.is_empty()
+            && self.removed_steps.is_empty()
+            && self.modified_steps.is_empty()
+            && self.added_constraints.is_empty()
+            && self.removed_constraints.is_empty()
+            && self.added_tools.is_empty()
+            && self.removed_tools.is_empty()
+    }
+}
+
+impl ExecutionPlan {
+    /// Differences from this plan to `other`
+    pub fn diff(&self, other: &ExecutionPlan) -> PlanDiff {
+        let mut diff = PlanDiff::default();
+        let mut old_matched = vec![false; self.steps.len()];
+        let mut new_matched = vec![false; other.steps.len()];
+        let mut pairs: Vec<(usize, usize, f64)> = Vec::new();
+
+        // Identical descriptions pair up first, regardless of position
+        for (i, old) in self.steps.iter().enumerate() {
+            let found = other
+                .steps
+                .iter()
+                .enumerate()
+                .position(|(j, new)| !new_matched[j] && new.description == old.description);
+            if let Some(j) = found {
+                old_matched[i] = true;
+                new_matched[j] = true;
+                pairs.push((i, j, 1.0));
+            }
+        }
+
+        // Then the most similar remaining pairs above the threshold
+        let mut candidates: Vec<(usize, usize, f64)> = Vec::new();
+        for (i, old) in self.steps.iter().enumerate().filter(|(i, _)| !old_matched[*i]) {
+            for (j, new) in other.steps.iter().enumerate().filter(|(j, _)| !new_matched[*j]) {
+                let similarity = description_similarity(&old.description, &new.description);
+                if similarity >= STEP_SIMILARITY_THRESHOLD {
+                    candidates.push((i, j, similarity));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.2.total_cmp(&a.2).then((a.0, a.1).cmp(&(b.0, b.1))));
+        for (i, j, similarity) in candidates {
+            if !old_matched[i] && !new_matched[j] {
+                old_matched[i] = true;
+                new_matched[j] = true;
+                pairs.push((i, j, similarity));
+            }
+        }
+
+        pairs.sort_by_key(|&(i, _, _)| i);
+        for (i, j, similarity) in pairs {
+            let (old, new) = (&self.steps[i], &other.steps[j]);
+            if old.description != new.description || tool_name(old) != tool_name(new) {
+                diff.modified_steps.push(StepChange {
+                    old: old.clone(),
+                    new: new.clone(),
+                    similarity,
+                });
+            }
+        }
+
+        diff.removed_steps = unmatched(&self.steps, &old_matched);
+        diff.added_steps = unmatched(&other.steps, &new_matched);
+
+        diff.added_constraints = missing_from(&other.constraints, &self.constraints);
+        diff.removed_constraints = missing_from(&self.constraints, &other.constraints);
+
+        let old_tools: Vec<String> = self.available_tools.iter().map(|t| t.name.clone()).collect();
+        let new_tools: Vec<String> = other.available_tools.iter().map(|t| t.name.clone()).collect();
+        diff.added_tools = missing_from(&new_tools, &old_tools);
+        diff.removed_tools = missing_from(&old_tools, &new_tools);
+
+        diff
+    }
+}
+
+fn tool_name(step: &ExecutionStep) -> Option<&str> {
+    step.tool.as_ref().map(|t| t.name.as_str())
+}
+
+fn unmatched(steps: &[ExecutionStep], matched: &[bool]) -> Vec<ExecutionStep> {
+    steps
+        .iter()
+        .zip(matched)
+        .filter(|(_, m)| !**m)
+        .map(|(s, _)| s.clone())
+        .collect()
+}
+
+/// Items of `items` not present in `other`, in order
+fn missing_from(items: &[String], other: &[String]) -> Vec<String> {
+    items.iter().filter(|i| !other.contains(i)).cloned().collect()
+}
+
+/// Word-level LCS similarity: `2 * lcs / (len_a + len_b)`
+fn description_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<String> = a.split_whitespace().map(|w| w.to_lowercase()).collect();
+    let b: Vec<String> = b.split_whitespace().map(|w| w.to_lowercase()).collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+
+    let mut table = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            table[i][j] = if a[i - 1] == b[j - 1] {
+                table[i - 1][j - 1] + 1
+            } else {
+                table[i - 1][j].max(table[i][j - 1])
+            };
+        }
+    }
+
+    2.0 * table[a.len()][b.len()] as f64 / (a.len() + b.len()) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_full;
+
+    #[test]
+    fn test_identical_plans() {
+        let plan = parse_full("TASK\nX\nPLAN\nSearch code\nEdit files").unwrap();
+        assert!(plan.diff(&plan).is_empty());
+    }
+
+    #[test]
+    fn test_plan_diff() {
+        let old = parse_full(
+            "TASK\nX\nPLAN\nSearch the auth code\nEdit files\nRun tests\nCONSTRAINTS\nno_mocks\nTOOLS\ncode_search(\"auth\")",
+        )
+        .unwrap();
+        let new = parse_full(
+            "TASK\nX\nPLAN\nRun tests\nSearch the session code\nDeploy\nCONSTRAINTS\nno_mocks\nreal_dbs\nTOOLS\nbash(\"cargo test\")",
+        )
+        .unwrap();
+
+        let diff = old.diff(&new);
+
+        assert_eq!(diff.modified_steps.len(), 2);
+        assert_eq!(diff.modified_steps[0].old.description, "Search the auth code");
+        assert_eq!(diff.modified_steps[0].new.description, "Search the session code");
+        assert!((diff.modified_steps[0].similarity - 0.75).abs() < 1e-9);
+        // Same description, but only the new plan binds a tool ("run" -> bash)
+        assert_eq!(diff.modified_steps[1].old.description, "Run tests");
+        assert_eq!(tool_name(&diff.modified_steps[1].old), None);
+        assert_eq!(tool_name(&diff.modified_steps[1].new), Some("bash"));
+
+        let removed: Vec<_> = diff.removed_steps.iter().map(|s| s.description.as_str()).collect();
+        let added: Vec<_> = diff.added_steps.iter().map(|s| s.description.as_str()).collect();
+        assert_eq!(removed, vec!["Edit files"]);
+        assert_eq!(added, vec!["Deploy"]);
+
+        assert_eq!(diff.added_constraints, vec!["real_dbs"]);
+        assert!(diff.removed_constraints.is_empty());
+        assert_eq!(diff.added_tools, vec!["bash"]);
+        assert_eq!(diff.removed_tools, vec!["code_search"]);
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_description_similarity() {
+        assert_eq!(description_similarity("Run tests", "run TESTS"), 1.0);
+        assert_eq!(description_similarity("a b", "c d"), 0.0);
+    }
+}
@@ -0,0 +1,151 @@
+//! APEX Markdown Rendering
+//!
+//! Renders validated documents as GitHub-flavored Markdown for human review:
+//!
+//! - TASK as an H1
+//! - GOALS, CONSTRAINTS and VALIDATION as bulleted lists
+//! - PLAN (and WHEN steps) as numbered lists matching `ExecutionStep.step_number`
+//! - TOOLS and DIFF as fenced code blocks
+//! - META as a table (keys sorted)
+
+use crate::validate::{DiffFormat, ValidatedDocument};
+use std::fmt::Write;
+
+impl ValidatedDocument {
+    /// Render the document as GitHub-flavored Markdown
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# {}", self.task.line);
+
+        if let Some(ref goals) = self.goals {
+            bullet_section(&mut out, "Goals", &goals.goals);
+        }
+
+        // WHEN steps continue the PLAN numbering, as in the execution plan
+        let mut step_number = 0;
+        if let Some(ref plan) = self.plan {
+            let _ = writeln!(out, "\n## Plan\n");
+            for step in plan.steps() {
+                step_number += 1;
+                let _ = writeln!(out, "{}. {}", step_number, step);
+            }
+        }
+        for when in &self.when {
+            let _ = writeln!(out, "\n### When: {}\n", when.condition);
+            for step in &when.steps {
+                step_number += 1;
+                let _ = writeln!(out, "{}. {}", step_number, step);
+            }
+        }
+
+        if let Some(ref constraints) = self.constraints {
+            let display: Vec<String> = constraints.rules.iter().map(|r| r.display.clone()).collect();
+            bullet_section(&mut out, "Constraints", &display);
+        }
+        if let Some(ref validation) = self.validation {
+            bullet_section(&mut out, "Validation", &validation.conditions);
+        }
+
+        if let Some(ref tools) = self.tools {
+            let raw: Vec<&str> = tools.tools.iter().map(|t| t.raw.as_str()).collect();
+            code_section(&mut out, "Tools", "", &raw);
+        }
+        if let Some(ref diff) = self.diff {
+            let lang = if diff.format == DiffFormat::Unified { "diff" } else { "" };
+            let changes: Vec<&str> = diff.changes.iter().map(|l| l.as_str()).collect();
+            code_section(&mut out, "Diff", lang, &changes);
+        }
+        if let Some(ref context) = self.context {
+            let _ = writeln!(out, "\n## Context\n");
+            for line in &context.lines {
+                let _ = writeln!(out, "{}", line);
+            }
+        }
+
+        if let Some(ref meta) = self.meta {
+            let mut keys: Vec<&String> = meta.entries.keys().collect();
+            keys.sort();
+            let _ = writeln!(out, "\n## Meta\n\n| Key | Value |\n| --- | --- |");
+            for key in keys {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} |",
+                    escape_cell(meta.original_key(key)),
+                    escape_cell(&meta.entries[key])
+                );
+            }
+        }
+
+        out
+    }
+}
+
+fn bullet_section(out: &mut String, title: &str, items: &[String]) {
+    let _ = writeln!(out, "\n## {}\n", title);
+    for item in items {
+        let _ = writeln!(out, "- {}", item);
+    }
+}
+
+fn code_section(out: &mut String, title: &str, lang: &str, lines: &[&str]) {
+    let _ = writeln!(out, "\n## {}\n\n```{}", title, lang);
+    for line in lines {
+        let _ = writeln!(out, "{}", line);
+    }
+    let _ = writeln!(out, "```");
+}
+
+/// Escape pipes so table cells stay intact
+fn escape_cell(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::build_execution_plan;
+    use crate::parser::parse_str;
+    use crate::validate::validate;
+
+    #[test]
+    fn test_to_markdown() {
+        let input = r#"TASK
+Refactor auth
+
+GOALS
+Tests pass
+
+PLAN
+Search code
+Edit files [after: 1]
+
+WHEN
+tests fail
+Revert changes
+
+CONSTRAINTS
+No Mocks
+
+TOOLS
+code_search "auth"
+
+META
+version=1.1
+author=a|b
+"#;
+        let validated = validate(parse_str(input).unwrap()).unwrap();
+        let md = validated.to_markdown();
+
+        assert!(md.starts_with("# Refactor auth\n"));
+        assert!(md.contains("## Goals\n\n- Tests pass\n"));
+        assert!(md.contains("## Constraints\n\n- No Mocks\n"));
+        assert!(md.contains("## Tools\n\n```\ncode_search \"auth\"\n```\n"));
+        assert!(md.contains("| author | a\\|b |\n| version | 1.1 |\n"));
+
+        let plan = build_execution_plan(&validated).unwrap();
+        for step in &plan.steps {
+            assert!(md.contains(&format!("{}. ", step.step_number)));
+        }
+        assert!(md.contains("1. Search code\n2. Edit files [after: 1]\n"));
+        assert!(md.contains("### When: tests fail\n\n3. Revert changes\n"));
+    }
+}
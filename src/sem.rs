@@ -10,9 +10,20 @@
 //! 3. Replace any sequence of non-alphanumeric characters with "_"
 //!
 //! Example: "No Mocks Allowed!" -> "no_mocks_allowed"
+//!
+//! Constraints written as `key: value` keep their value verbatim and only
+//! canonicalize the key, so configuration values such as versions and paths
+//! survive: "Node_Version: 18.x" -> "node_version: 18.x"
+//!
+//! A constraint may carry a severity prefix (`MUST:`, `SHOULD:`, `MAY:`).
+//! The prefix is stripped before canonicalization; unprefixed constraints
+//! are `MUST`.
 
-use crate::validate::ValidatedDocument;
+use crate::ast::BlockKind;
+use crate::errors::{ApexError, ApexResult};
+use crate::validate::{DiffFormat, DiffLine, DiffView, ValidatedDocument};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Canonicalize a constraint string per APEX v1.1 spec
 ///
@@ -53,14 +64,44 @@ pub fn canonicalize(s: &str) -> String {
 /// assert_eq!(normalize_constraint("< 300 LOC"), "300_loc");
 /// ```
 pub fn normalize_constraint(s: &str) -> String {
+    normalize_constraint_with_config(s, &NormalizeConfig::default())
+}
+
+/// Options for [`normalize_constraint_with_config`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NormalizeConfig {
+    /// Keep any Unicode letter or digit, not just ASCII ones
+    ///
+    /// Off by default, matching the v1.1 spec: `"résumé parsing"` becomes
+    /// `"r_sum_parsing"` in ASCII mode and `"résumé_parsing"` in Unicode mode.
+    pub unicode: bool,
+}
+
+impl NormalizeConfig {
+    /// Unicode-preserving normalization
+    pub fn unicode() -> Self {
+        Self { unicode: true }
+    }
+}
+
+/// Normalize a constraint string with explicit options
+///
+/// # Examples
+/// ```
+/// use apex_spec::sem::{normalize_constraint_with_config, NormalizeConfig};
+/// assert_eq!(normalize_constraint_with_config("Résumé Parsing", &NormalizeConfig::default()), "r_sum_parsing");
+/// assert_eq!(normalize_constraint_with_config("Résumé Parsing", &NormalizeConfig::unicode()), "résumé_parsing");
+/// ```
+pub fn normalize_constraint_with_config(s: &str, config: &NormalizeConfig) -> String {
     let trimmed = s.trim().to_lowercase();
 
     // Replace any sequence of non-alphanumeric characters with "_"
     let mut result = String::with_capacity(trimmed.len());
-    let mut last_was_separator = true; // Start true to skip leading separators
+    let mut last_was_separator = false;
 
     for c in trimmed.chars() {
-        if c.is_ascii_alphanumeric() {
+        let keep = if config.unicode { c.is_alphanumeric() } else { c.is_ascii_alphanumeric() };
+        if keep {
             result.push(c);
             last_was_separator = false;
         } else if !last_was_separator {
@@ -69,12 +110,200 @@ pub fn normalize_constraint(s: &str) -> String {
         }
     }
 
-    // Trim trailing underscore
-    if result.ends_with('_') {
-        result.pop();
+    // Trim leading/trailing underscores; the output is then a fixed point
+    result.trim_matches('_').to_string()
+}
+
+/// Split a `key: value` constraint into canonical key and verbatim value
+///
+/// The key must be identifier-like (a letter, then letters, digits, `_`,
+/// `-` or `.`) and the value non-empty. A plain word key (`Note`) only
+/// takes a single-token value, so prose such as `Note: no mocks` is not a
+/// key/value pair. Returns `None` for any other form.
+///
+/// # Examples
+/// ```
+/// use apex_spec::sem::split_key_value;
+/// assert_eq!(split_key_value("node_version: 18.x"), Some(("node_version".to_string(), "18.x")));
+/// assert_eq!(split_key_value("No mocks"), None);
+/// assert_eq!(split_key_value("Note: no mocks"), None);
+/// ```
+pub fn split_key_value(s: &str) -> Option<(String, &str)> {
+    let (key, value) = s.split_once(':')?;
+    let key = key.trim();
+    let value = value.trim();
+
+    if value.is_empty() {
+        return None;
+    }
+    if !key.starts_with(|c: char| c.is_ascii_alphabetic())
+        || !key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return None;
+    }
+    let compound_key = key.contains(['_', '-', '.']);
+    if !compound_key && value.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let canonical_key = normalize_constraint(key);
+    if canonical_key.is_empty() {
+        return None;
+    }
+    Some((canonical_key, value))
+}
+
+/// Canonicalize a full constraint line
+///
+/// `key: value` constraints keep their value intact (`"node_version: 18.x"`);
+/// numeric comparisons keep their comparator (`"< 300 LOC"` -> `"lt_300_loc"`,
+/// see [`NumericConstraint`]); everything else goes through [`canonicalize`].
+/// A severity prefix is kept
+/// and the rest of the line canonicalized (`"SHOULD: No Mocks"` ->
+/// `"SHOULD: no_mocks"`).
+pub fn canonicalize_constraint(s: &str) -> String {
+    if let Some((severity, rest)) = Severity::prefix(s) {
+        return format!("{}: {}", severity.as_str(), canonicalize_constraint(rest));
+    }
+    if let Some(numeric) = Constraint::parse_numeric(s) {
+        return numeric.as_str();
+    }
+    match split_key_value(s) {
+        Some((key, value)) => format!("{}: {}", key, value),
+        None => canonicalize(s),
+    }
+}
+
+/// Constraint severity level
+///
+/// Variants are ordered strongest first, so `min` picks the stronger level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default)]
+pub enum Severity {
+    /// Hard requirement (default)
+    #[default]
+    Must,
+    /// Preference
+    Should,
+    /// Optional
+    May,
+}
+
+impl Severity {
+    /// Split a leading `MUST:` / `SHOULD:` / `MAY:` prefix off a constraint
+    ///
+    /// Prefixes are uppercase only, so lowercase `key: value` constraints are
+    /// never mistaken for severities.
+    pub fn split_prefix(s: &str) -> (Self, &str) {
+        Self::prefix(s).unwrap_or((Severity::Must, s))
+    }
+
+    /// Explicit severity prefix, if present
+    fn prefix(s: &str) -> Option<(Self, &str)> {
+        let trimmed = s.trim_start();
+        [
+            ("MUST:", Severity::Must),
+            ("SHOULD:", Severity::Should),
+            ("MAY:", Severity::May),
+        ]
+        .into_iter()
+        .find_map(|(prefix, severity)| trimmed.strip_prefix(prefix).map(|rest| (severity, rest.trim())))
+    }
+
+    /// Get canonical uppercase keyword
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Must => "MUST",
+            Severity::Should => "SHOULD",
+            Severity::May => "MAY",
+        }
+    }
+}
+
+/// Comparison operator of a numeric constraint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Comparator {
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+    /// `=`
+    Eq,
+}
+
+impl Comparator {
+    /// Canonical keyword (`lt`, `le`, `gt`, `ge`, `eq`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Comparator::Lt => "lt",
+            Comparator::Le => "le",
+            Comparator::Gt => "gt",
+            Comparator::Ge => "ge",
+            Comparator::Eq => "eq",
+        }
+    }
+
+    /// Parse canonical keyword
+    pub fn from_keyword(s: &str) -> Option<Self> {
+        match s {
+            "lt" => Some(Comparator::Lt),
+            "le" => Some(Comparator::Le),
+            "gt" => Some(Comparator::Gt),
+            "ge" => Some(Comparator::Ge),
+            "eq" => Some(Comparator::Eq),
+            _ => None,
+        }
     }
 
-    result
+    /// Split a leading `<`, `<=`, `>`, `>=`, `=` or `==` off `s`
+    pub fn strip_symbol(s: &str) -> Option<(Self, &str)> {
+        [
+            ("<=", Comparator::Le),
+            (">=", Comparator::Ge),
+            ("==", Comparator::Eq),
+            ("<", Comparator::Lt),
+            (">", Comparator::Gt),
+            ("=", Comparator::Eq),
+        ]
+        .into_iter()
+        .find_map(|(sym, cmp)| s.strip_prefix(sym).map(|rest| (cmp, rest)))
+    }
+
+    /// Check `actual` against `bound`
+    pub fn holds(&self, actual: u32, bound: u32) -> bool {
+        match self {
+            Comparator::Lt => actual < bound,
+            Comparator::Le => actual <= bound,
+            Comparator::Gt => actual > bound,
+            Comparator::Ge => actual >= bound,
+            Comparator::Eq => actual == bound,
+        }
+    }
+}
+
+/// Numeric constraint such as `< 300 LOC` or `>= 80% coverage`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NumericConstraint {
+    /// Comparison operator
+    pub comparator: Comparator,
+    /// Bound
+    pub value: u32,
+    /// Canonical unit (`loc`, `coverage`; empty if none)
+    pub unit: String,
+}
+
+impl NumericConstraint {
+    /// Canonical form: `<comparator>_<value>[_<unit>]`
+    pub fn as_str(&self) -> String {
+        if self.unit.is_empty() {
+            format!("{}_{}", self.comparator.as_str(), self.value)
+        } else {
+            format!("{}_{}_{}", self.comparator.as_str(), self.value, self.unit)
+        }
+    }
 }
 
 /// Known constraint types
@@ -94,13 +323,33 @@ pub enum Constraint {
     NoStubs,
     /// Require tests
     RequireTests,
+    /// Structured `key: value` constraint (value preserved verbatim)
+    KeyValue { key: String, value: String },
+    /// Numeric comparison other than a `<` LOC limit
+    Numeric(NumericConstraint),
     /// Custom constraint
     Other(String),
 }
 
 impl Constraint {
     /// Parse constraint from string using v1.1 normalization
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
+        if let Some((key, value)) = split_key_value(s) {
+            return Constraint::KeyValue {
+                key,
+                value: value.to_string(),
+            };
+        }
+
+        if let Some(numeric) = Self::parse_numeric(s) {
+            let is_loc = numeric.unit.split('_').next() == Some("loc");
+            if numeric.comparator == Comparator::Lt && is_loc {
+                return Constraint::LtLoc(numeric.value);
+            }
+            return Constraint::Numeric(numeric);
+        }
+
         let canonical = normalize_constraint(s);
 
         // Match known canonical identifiers (v1.1 standard constraints)
@@ -151,6 +400,32 @@ impl Constraint {
         Constraint::Other(canonical)
     }
 
+    /// Parse a numeric comparison
+    ///
+    /// Accepts symbolic (`< 300 LOC`, `>=80% coverage`) and canonical
+    /// (`lt_300_loc`) forms. The unit is canonicalized; `None` if the text
+    /// does not start with a comparator followed by a number.
+    pub fn parse_numeric(s: &str) -> Option<NumericConstraint> {
+        let trimmed = s.trim();
+        let (comparator, rest) = match Comparator::strip_symbol(trimmed) {
+            Some(found) => found,
+            None => {
+                let (keyword, rest) = trimmed.split_once('_')?;
+                (Comparator::from_keyword(keyword)?, rest)
+            }
+        };
+
+        let rest = rest.trim_start();
+        let digits_end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let value = rest[..digits_end].parse().ok()?;
+
+        Some(NumericConstraint {
+            comparator,
+            value,
+            unit: normalize_constraint(&rest[digits_end..]),
+        })
+    }
+
     /// Get canonical string representation
     pub fn as_str(&self) -> String {
         match self {
@@ -161,9 +436,196 @@ impl Constraint {
             Constraint::ApiCompat => "api_compat".to_string(),
             Constraint::NoStubs => "no_stubs".to_string(),
             Constraint::RequireTests => "require_tests".to_string(),
+            Constraint::KeyValue { key, value } => format!("{}: {}", key, value),
+            Constraint::Numeric(n) => n.as_str(),
             Constraint::Other(s) => s.clone(),
         }
     }
+
+    /// Theme of the constraint, for grouping in reports
+    ///
+    /// Known variants map directly; `Other`, `key: value` and numeric
+    /// constraints are classified by the first category whose
+    /// [`ConstraintCategory::keywords`] contain one of their words.
+    pub fn category(&self) -> ConstraintCategory {
+        match self {
+            Constraint::NoMocks | Constraint::RealDbsOnly | Constraint::NoStubs | Constraint::RequireTests => {
+                ConstraintCategory::TestIntegrity
+            }
+            Constraint::SafeRefactor | Constraint::ApiCompat => ConstraintCategory::Compatibility,
+            Constraint::LtLoc(_) => ConstraintCategory::Size,
+            Constraint::KeyValue { .. } | Constraint::Numeric(_) | Constraint::Other(_) => {
+                let words = stem_words(&canonicalize(&self.as_str()));
+                ConstraintCategory::KEYWORD_ORDER
+                    .into_iter()
+                    .find(|cat| cat.keywords().iter().any(|k| words.iter().any(|w| w == k)))
+                    .unwrap_or(ConstraintCategory::General)
+            }
+        }
+    }
+}
+
+/// Theme of a constraint (see [`Constraint::category`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ConstraintCategory {
+    /// Security and secrets handling
+    Security,
+    /// Test realism and coverage
+    TestIntegrity,
+    /// API and behavior compatibility
+    Compatibility,
+    /// Change size limits
+    Size,
+    /// Runtime and resource budgets
+    Performance,
+    /// Code style and hygiene
+    Quality,
+    /// Anything not matching a category
+    General,
+}
+
+impl ConstraintCategory {
+    /// Categories in keyword-matching priority order
+    const KEYWORD_ORDER: [ConstraintCategory; 6] = [
+        ConstraintCategory::Security,
+        ConstraintCategory::TestIntegrity,
+        ConstraintCategory::Compatibility,
+        ConstraintCategory::Size,
+        ConstraintCategory::Performance,
+        ConstraintCategory::Quality,
+    ];
+
+    /// Words (singular, lowercase) that place a custom constraint in this category
+    pub fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            ConstraintCategory::Security => &[
+                "secret", "credential", "password", "token", "auth", "security", "encrypt", "permission", "sudo",
+            ],
+            ConstraintCategory::TestIntegrity => &["test", "mock", "stub", "fixture", "coverage", "db"],
+            ConstraintCategory::Compatibility => &["compat", "breaking", "backward", "api", "migration"],
+            ConstraintCategory::Size => &["loc", "line", "file", "size", "diff"],
+            ConstraintCategory::Performance => &["latency", "performance", "perf", "ms", "memory", "cpu", "throughput"],
+            ConstraintCategory::Quality => &["lint", "clippy", "format", "fmt", "style", "doc", "unwrap", "warning"],
+            ConstraintCategory::General => &[],
+        }
+    }
+}
+
+/// Built-in pairs of mutually exclusive canonical constraints
+///
+/// A constraint matches a rule term when its canonical form equals the term
+/// or extends it with further `_`-separated words (`use_mocks` matches
+/// `use_mocks_for_network`).
+pub static CONFLICTING_CONSTRAINTS: &[(&str, &str)] = &[
+    ("no_mocks", "use_mocks"),
+    ("no_mocks", "mock_dbs"),
+    ("real_dbs_only", "mock_dbs"),
+    ("real_dbs_only", "in_memory_dbs"),
+    ("no_stubs", "use_stubs"),
+    ("require_tests", "no_tests"),
+    ("api_compat", "breaking_changes"),
+    ("safe_refactor", "breaking_changes"),
+];
+
+fn default_conflict_rules() -> Vec<(String, String)> {
+    CONFLICTING_CONSTRAINTS
+        .iter()
+        .map(|(a, b)| (a.to_string(), b.to_string()))
+        .collect()
+}
+
+/// Check whether a canonical constraint matches a conflict rule term
+fn matches_rule_term(canonical: &str, term: &str) -> bool {
+    canonical == term
+        || canonical
+            .strip_prefix(term)
+            .is_some_and(|rest| rest.starts_with('_'))
+}
+
+/// Aspect of a document that adds to its complexity score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComplexityFactor {
+    /// PLAN steps
+    Steps,
+    /// Longest chain of dependent PLAN steps
+    DependencyDepth,
+    /// Declared TOOLS
+    Tools,
+    /// CONSTRAINTS rules
+    Constraints,
+    /// A DIFF block is present
+    Diff,
+}
+
+impl ComplexityFactor {
+    /// Points added per unit of this factor
+    pub fn weight(self) -> f64 {
+        match self {
+            ComplexityFactor::Steps => 1.0,
+            ComplexityFactor::DependencyDepth => 1.0,
+            ComplexityFactor::Tools => 2.0,
+            ComplexityFactor::Constraints => 0.5,
+            ComplexityFactor::Diff => 5.0,
+        }
+    }
+}
+
+/// One factor's share of a [`ComplexityReport`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FactorScore {
+    pub factor: ComplexityFactor,
+    /// Units counted in the document
+    pub count: usize,
+    /// `count` times the factor weight
+    pub points: f64,
+}
+
+/// Weighted complexity estimate of a document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComplexityReport {
+    /// Sum of all factor points
+    pub score: f64,
+    /// Coarse level (1-5) derived from `score`
+    pub level: u8,
+    /// Factors with a non-zero count, in [`ComplexityFactor`] order
+    pub factors: Vec<FactorScore>,
+}
+
+/// Scores above each threshold raise the level by one (1-5)
+///
+/// Chosen so that a plain sequential plan keeps the level the step-count
+/// estimate gave it (3-5 steps is level 2, 6-10 level 3, ...).
+const COMPLEXITY_LEVEL_THRESHOLDS: [f64; 4] = [5.0, 10.0, 20.0, 40.0];
+
+impl ValidatedDocument {
+    /// Weighted complexity from steps, dependency depth, tools, constraints and DIFF
+    ///
+    /// Dependency depth is the longest chain of dependent PLAN steps.
+    pub fn complexity_score(&self) -> ComplexityReport {
+        let steps = self.plan.as_ref().map_or(0, |p| p.entries.len());
+        let depth = self.plan.as_ref().map_or(0, |p| p.dependency_depth());
+        let counts = [
+            (ComplexityFactor::Steps, steps),
+            (ComplexityFactor::DependencyDepth, depth),
+            (ComplexityFactor::Tools, self.tools.as_ref().map_or(0, |t| t.tools.len())),
+            (ComplexityFactor::Constraints, self.constraints.as_ref().map_or(0, |c| c.rules.len())),
+            (ComplexityFactor::Diff, usize::from(self.diff.is_some())),
+        ];
+
+        let factors: Vec<FactorScore> = counts
+            .into_iter()
+            .filter(|&(_, count)| count > 0)
+            .map(|(factor, count)| FactorScore {
+                factor,
+                count,
+                points: count as f64 * factor.weight(),
+            })
+            .collect();
+        let score = factors.iter().map(|f| f.points).sum();
+        let level = 1 + COMPLEXITY_LEVEL_THRESHOLDS.iter().filter(|&&t| score > t).count() as u8;
+
+        ComplexityReport { score, level, factors }
+    }
 }
 
 /// Semantic analysis of validated document
@@ -171,69 +633,201 @@ impl Constraint {
 pub struct Semantics {
     /// Parsed constraints
     pub constraints: Vec<Constraint>,
+    /// Severity by canonical constraint ([`Constraint::as_str`]); constraints
+    /// without an entry are `Must`
+    #[serde(default)]
+    pub severities: BTreeMap<String, Severity>,
     /// Whether execution mode requires PLAN
     pub requires_plan: bool,
-    /// Estimated complexity (1-5)
+    /// Estimated complexity (1-5), the level of [`ValidatedDocument::complexity_score`]
     pub complexity: u8,
+    /// Pairs of mutually exclusive canonical constraints
+    #[serde(skip, default = "default_conflict_rules")]
+    pub conflict_rules: Vec<(String, String)>,
+}
+
+/// Number of GOALS from which a PLAN is required
+pub const PLAN_REQUIRED_GOALS: usize = 2;
+
+/// Lines a single hunk may delete before `safe_refactor` flags it
+pub const SAFE_REFACTOR_MAX_DELETIONS: usize = 50;
+
+/// Item keywords that start a public API signature after `pub`
+const PUBLIC_API_ITEMS: &[&str] = &["fn ", "async fn ", "unsafe fn ", "const fn ", "struct ", "enum ", "trait ", "type "];
+
+/// Check if a source line declares a public API item (`pub fn`, `pub struct`, ...)
+fn is_public_api_signature(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix("pub ")
+        .is_some_and(|rest| PUBLIC_API_ITEMS.iter().any(|item| rest.starts_with(item)))
+}
+
+impl Default for Semantics {
+    /// No constraints, with the default conflict rules
+    fn default() -> Self {
+        Self {
+            constraints: Vec::new(),
+            severities: BTreeMap::new(),
+            requires_plan: false,
+            complexity: 1,
+            conflict_rules: default_conflict_rules(),
+        }
+    }
 }
 
 impl Semantics {
+    /// Check if a task with `goal_count` goals needs an explicit PLAN
+    pub fn plan_required_for(goal_count: usize) -> bool {
+        goal_count >= PLAN_REQUIRED_GOALS
+    }
+
     /// Build semantics from validated document
     pub fn from_validated(doc: &ValidatedDocument) -> Self {
-        let constraints = if let Some(ref cv) = doc.constraints {
-            cv.rules.iter().map(|r| Constraint::from_str(r)).collect()
-        } else {
-            Vec::new()
-        };
-
-        // Estimate complexity based on plan steps
-        let complexity = if let Some(ref plan) = doc.plan {
-            match plan.steps.len() {
-                0..=2 => 1,
-                3..=5 => 2,
-                6..=10 => 3,
-                11..=20 => 4,
-                _ => 5,
+        // Exact duplicates collapse to one entry; each constraint keeps the
+        // strongest severity it was given
+        let mut rules: Vec<&str> = Vec::new();
+        let mut severities: BTreeMap<String, Severity> = BTreeMap::new();
+        for rule in doc.constraints.iter().flat_map(|cv| &cv.rules) {
+            let key = Constraint::from_str(&rule.canonical).as_str();
+            let severity = severities.entry(key).or_insert(rule.severity);
+            *severity = (*severity).min(rule.severity);
+            if !rules.contains(&rule.canonical.as_str()) {
+                rules.push(&rule.canonical);
             }
-        } else {
-            1
-        };
+        }
+        let constraints = rules.iter().map(|r| Constraint::from_str(r)).collect();
+
+        let complexity = doc.complexity_score().level;
 
         // Plan is required if we have complex goals or multiple steps implied
-        let requires_plan = doc.goals.as_ref().is_some_and(|g| g.goals.len() > 1);
+        let requires_plan = Self::plan_required_for(doc.goals.as_ref().map_or(0, |g| g.goals.len()));
 
         Self {
             constraints,
+            severities,
             requires_plan,
             complexity,
+            conflict_rules: default_conflict_rules(),
+        }
+    }
+
+    /// Constraints with semantically-equivalent variants collapsed
+    ///
+    /// `real_dbs` and `real_dbs_only` both parse to [`Constraint::RealDbsOnly`]
+    /// and are kept once, at the position of the first occurrence.
+    pub fn unique_constraints(&self) -> Vec<Constraint> {
+        let mut unique: Vec<Constraint> = Vec::new();
+        for constraint in &self.constraints {
+            if !unique.contains(constraint) {
+                unique.push(constraint.clone());
+            }
+        }
+        unique
+    }
+
+    /// Unique constraints grouped by [`Constraint::category`], in document order
+    pub fn constraints_by_category(&self) -> BTreeMap<ConstraintCategory, Vec<Constraint>> {
+        let mut groups: BTreeMap<ConstraintCategory, Vec<Constraint>> = BTreeMap::new();
+        for constraint in self.unique_constraints() {
+            groups.entry(constraint.category()).or_default().push(constraint);
+        }
+        groups
+    }
+
+    /// Add a conflict rule between two constraints (canonicalized)
+    pub fn with_conflict_rule(mut self, a: &str, b: &str) -> Self {
+        self.conflict_rules.push((normalize_constraint(a), normalize_constraint(b)));
+        self
+    }
+
+    /// Pairs of mutually exclusive constraints present in the document
+    ///
+    /// Each pair is reported once, in document order.
+    pub fn conflicts(&self) -> Vec<(Constraint, Constraint)> {
+        let mut found = Vec::new();
+        let constraints = self.unique_constraints();
+
+        for (i, first) in constraints.iter().enumerate() {
+            for second in &constraints[i + 1..] {
+                if self.is_conflicting(first, second) {
+                    found.push((first.clone(), second.clone()));
+                }
+            }
+        }
+
+        found
+    }
+
+    /// Check if two constraints are mutually exclusive under the conflict rules
+    fn is_conflicting(&self, first: &Constraint, second: &Constraint) -> bool {
+        let (a, b) = (first.as_str(), second.as_str());
+        self.conflict_rules.iter().any(|(x, y)| {
+            (matches_rule_term(&a, x) && matches_rule_term(&b, y))
+                || (matches_rule_term(&a, y) && matches_rule_term(&b, x))
+        })
+    }
+
+    /// Merge an inherited profile of base constraints at `Must` level
+    ///
+    /// A base constraint is skipped when the document already has it (by
+    /// canonical form), conflicts with a document constraint, or sets a value
+    /// the document sets too (LOC limit, `key: value` key). Document-level
+    /// constraints therefore always take precedence.
+    pub fn with_base_constraints(&mut self, base: &[Constraint]) {
+        let own = self.constraints.clone();
+
+        for constraint in base {
+            let overridden = own.iter().any(|c| {
+                c.as_str() == constraint.as_str()
+                    || self.is_conflicting(c, constraint)
+                    || match (c, constraint) {
+                        (Constraint::LtLoc(_), Constraint::LtLoc(_)) => true,
+                        (Constraint::KeyValue { key: a, .. }, Constraint::KeyValue { key: b, .. }) => a == b,
+                        _ => false,
+                    }
+            });
+            let duplicate = self.constraints.iter().any(|c| c.as_str() == constraint.as_str());
+            if !overridden && !duplicate {
+                self.constraints.push(constraint.clone());
+            }
         }
     }
 
     // --- Constraint Queries ---
 
+    /// Severity of `constraint` (`Must` if not recorded)
+    pub fn severity(&self, constraint: &Constraint) -> Severity {
+        self.severities.get(&constraint.as_str()).copied().unwrap_or_default()
+    }
+
+    /// Constraints at `Must` level (hard requirements)
+    pub fn must_constraints(&self) -> impl Iterator<Item = &Constraint> {
+        self.constraints.iter().filter(|c| self.severity(c) == Severity::Must)
+    }
+
     /// Check if mocks are forbidden
     pub fn forbids_mocks(&self) -> bool {
-        self.constraints.iter().any(|c| matches!(c, Constraint::NoMocks))
+        self.must_constraints().any(|c| matches!(c, Constraint::NoMocks))
     }
 
     /// Check if stubs are forbidden
     pub fn forbids_stubs(&self) -> bool {
-        self.constraints.iter().any(|c| matches!(c, Constraint::NoStubs))
+        self.must_constraints().any(|c| matches!(c, Constraint::NoStubs))
     }
 
     /// Check if real databases are required
     pub fn requires_real_dbs(&self) -> bool {
-        self.constraints.iter().any(|c| matches!(c, Constraint::RealDbsOnly))
+        self.must_constraints().any(|c| matches!(c, Constraint::RealDbsOnly))
     }
 
     /// Check if tests are required
     pub fn requires_tests(&self) -> bool {
-        self.constraints.iter().any(|c| matches!(c, Constraint::RequireTests))
+        self.must_constraints().any(|c| matches!(c, Constraint::RequireTests))
     }
 
     /// Get LOC limit if specified
     pub fn loc_limit(&self) -> Option<u32> {
-        for c in &self.constraints {
+        for c in self.must_constraints() {
             if let Constraint::LtLoc(n) = c {
                 return Some(*n);
             }
@@ -241,14 +835,164 @@ impl Semantics {
         None
     }
 
+    /// Check that a DIFF stays within the LOC limit
+    ///
+    /// Unified diffs count added lines (`+` but not `+++`); raw and
+    /// unspecified diffs count every non-empty change line.
+    pub fn check_loc_against_diff(&self, diff: &DiffView) -> ApexResult<()> {
+        let Some(limit) = self.loc_limit() else {
+            return Ok(());
+        };
+
+        let count = match diff.format {
+            DiffFormat::Unified => diff
+                .changes
+                .iter()
+                .filter(|l| l.starts_with('+') && !l.starts_with("+++"))
+                .count(),
+            DiffFormat::Raw | DiffFormat::Unspecified => {
+                diff.changes.iter().filter(|l| !l.trim().is_empty()).count()
+            }
+        };
+
+        if count > limit as usize {
+            return Err(ApexError::constraint_violation(
+                &Constraint::LtLoc(limit).as_str(),
+                &format!("DIFF adds {} lines, limit is {}", count, limit),
+            ));
+        }
+        Ok(())
+    }
+
     /// Check if refactoring must be safe
     pub fn requires_safe_refactor(&self) -> bool {
-        self.constraints.iter().any(|c| matches!(c, Constraint::SafeRefactor))
+        self.must_constraints().any(|c| matches!(c, Constraint::SafeRefactor))
+    }
+
+    /// Flag risky deletions in a unified DIFF under `safe_refactor`
+    ///
+    /// Reports hunks deleting more than [`SAFE_REFACTOR_MAX_DELETIONS`] lines
+    /// and removed public API signatures (`pub fn`, `pub struct`, ...) that
+    /// are not re-added verbatim elsewhere in the same file. Returns nothing
+    /// when `safe_refactor` is not a MUST constraint or the DIFF is not
+    /// unified.
+    pub fn check_safe_refactor(&self, diff: &DiffView) -> Vec<String> {
+        if !self.requires_safe_refactor() || diff.format != DiffFormat::Unified {
+            return Vec::new();
+        }
+        let unified = match diff.parse_unified() {
+            Ok(unified) => unified,
+            Err(err) => return vec![format!("Cannot check safe_refactor: {}", err.message)],
+        };
+
+        let mut warnings = Vec::new();
+        for file in &unified.files {
+            let added: Vec<&str> = file
+                .hunks
+                .iter()
+                .flat_map(|h| &h.lines)
+                .filter_map(|l| match l {
+                    DiffLine::Added(text) => Some(text.trim()),
+                    _ => None,
+                })
+                .collect();
+
+            for hunk in &file.hunks {
+                let removed: Vec<&str> = hunk
+                    .lines
+                    .iter()
+                    .filter_map(|l| match l {
+                        DiffLine::Removed(text) => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+
+                if removed.len() > SAFE_REFACTOR_MAX_DELETIONS {
+                    warnings.push(format!(
+                        "{}: hunk at line {} deletes {} lines (more than {})",
+                        file.old_path,
+                        hunk.old_start,
+                        removed.len(),
+                        SAFE_REFACTOR_MAX_DELETIONS
+                    ));
+                }
+                for line in removed {
+                    if is_public_api_signature(line) && !added.contains(&line.trim()) {
+                        warnings.push(format!("{}: removes public API '{}'", file.old_path, line.trim()));
+                    }
+                }
+            }
+        }
+        warnings
     }
 
     /// Check if API compatibility is required
     pub fn requires_api_compat(&self) -> bool {
-        self.constraints.iter().any(|c| matches!(c, Constraint::ApiCompat))
+        self.must_constraints().any(|c| matches!(c, Constraint::ApiCompat))
+    }
+
+    /// Get the value of a `key: value` constraint
+    pub fn constraint_value(&self, key: &str) -> Option<&str> {
+        let key = normalize_constraint(key);
+        self.constraints.iter().find_map(|c| match c {
+            Constraint::KeyValue { key: k, value } if *k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Block whose directive wins when `a` and `b` disagree
+    ///
+    /// Ties (same precedence) resolve to `a`.
+    pub fn resolve_conflict(&self, a: BlockKind, b: BlockKind) -> BlockKind {
+        if Precedence::for_block(&b) > Precedence::for_block(&a) {
+            b
+        } else {
+            a
+        }
+    }
+
+    /// Check a PLAN step against a constraint
+    ///
+    /// A step violates a constraint when it mentions a term the conflict
+    /// rules pair with the constraint (`no_mocks` vs "use mocks"), or the
+    /// subject of a `no_*` constraint ("Mock the network" vs `no_mocks`).
+    /// Words are compared canonically, ignoring a trailing plural `s`.
+    pub fn resolve_directive_conflict(&self, constraint_text: &str, plan_step: &str) -> Resolution {
+        let (_, text) = Severity::split_prefix(constraint_text);
+        let constraint = Constraint::from_str(text).as_str();
+        let step = stem_words(&canonicalize(plan_step));
+
+        let mut forbidden: Vec<String> = self
+            .conflict_rules
+            .iter()
+            .filter_map(|(x, y)| {
+                if matches_rule_term(&constraint, x) {
+                    Some(y.clone())
+                } else if matches_rule_term(&constraint, y) {
+                    Some(x.clone())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        if let Some(subject) = constraint.strip_prefix("no_") {
+            forbidden.push(subject.to_string());
+        }
+
+        let violated = forbidden
+            .iter()
+            .find(|term| contains_words(&step, &stem_words(term)));
+        match violated {
+            Some(term) => Resolution::ConstraintWins {
+                constraint: constraint.clone(),
+                step: plan_step.to_string(),
+                explanation: format!(
+                    "PLAN step '{}' conflicts with constraint '{}' (mentions '{}'); CONSTRAINTS take precedence over PLAN",
+                    plan_step, constraint, term
+                ),
+            },
+            None => Resolution::NoConflict,
+        }
     }
 
     /// Get all custom constraints
@@ -266,6 +1010,39 @@ impl Semantics {
     }
 }
 
+/// Split a canonical identifier into words with a trailing plural `s` removed
+pub(crate) fn stem_words(canonical: &str) -> Vec<String> {
+    canonical
+        .split('_')
+        .filter(|w| !w.is_empty())
+        .map(|w| match w.strip_suffix('s') {
+            Some(stem) if stem.len() >= 2 => stem.to_string(),
+            _ => w.to_string(),
+        })
+        .collect()
+}
+
+/// Check whether `needle` occurs as a contiguous run of words in `haystack`
+fn contains_words(haystack: &[String], needle: &[String]) -> bool {
+    !needle.is_empty() && haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+/// Outcome of checking a PLAN step against a constraint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Resolution {
+    /// The step does not conflict with the constraint
+    NoConflict,
+    /// The step conflicts with the constraint, which wins
+    ConstraintWins {
+        /// Canonical constraint
+        constraint: String,
+        /// Offending PLAN step
+        step: String,
+        /// Human-readable reason
+        explanation: String,
+    },
+}
+
 /// Precedence level for conflict resolution
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Precedence {
@@ -283,12 +1060,12 @@ pub enum Precedence {
 
 impl Precedence {
     /// Get precedence for block kind
-    pub fn for_block(kind: crate::ast::BlockKind) -> Self {
+    pub fn for_block(kind: &BlockKind) -> Self {
         match kind {
-            crate::ast::BlockKind::Constraints => Precedence::Constraints,
-            crate::ast::BlockKind::Task => Precedence::Task,
-            crate::ast::BlockKind::Goals => Precedence::Goals,
-            crate::ast::BlockKind::Plan => Precedence::Plan,
+            BlockKind::Constraints => Precedence::Constraints,
+            BlockKind::Task => Precedence::Task,
+            BlockKind::Goals => Precedence::Goals,
+            BlockKind::Plan | BlockKind::When => Precedence::Plan,
             _ => Precedence::Context,
         }
     }
@@ -298,6 +1075,25 @@ impl Precedence {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_normalize_unicode() {
+        let unicode = NormalizeConfig::unicode();
+        assert_eq!(normalize_constraint_with_config("résumé_parsing", &unicode), "résumé_parsing");
+        assert_eq!(normalize_constraint_with_config("Ölçek  Testi!", &unicode), "ölçek_testi");
+        assert_eq!(normalize_constraint_with_config("ÉCOLE Normale", &unicode), "école_normale");
+        assert_eq!(normalize_constraint_with_config("不要 模拟", &unicode), "不要_模拟");
+        assert_eq!(normalize_constraint_with_config("テスト：必須", &unicode), "テスト_必須");
+        assert_eq!(normalize_constraint_with_config("No Mocks", &unicode), "no_mocks");
+
+        // ASCII mode (the default) is unchanged
+        assert_eq!(normalize_constraint("résumé_parsing"), "r_sum_parsing");
+        assert_eq!(normalize_constraint("不要 模拟"), "");
+        assert_eq!(
+            normalize_constraint_with_config("résumé_parsing", &NormalizeConfig::default()),
+            normalize_constraint("résumé_parsing")
+        );
+    }
+
     #[test]
     fn test_normalize_constraint() {
         // Basic normalization
@@ -317,6 +1113,43 @@ mod tests {
         // Already canonical
         assert_eq!(normalize_constraint("no_mocks"), "no_mocks");
         assert_eq!(normalize_constraint("lt300loc"), "lt300loc");
+
+        // Leading separators and digits
+        assert_eq!(normalize_constraint("__no__mocks__"), "no_mocks");
+        assert_eq!(normalize_constraint("300 LOC"), "300_loc");
+        assert_eq!(normalize_constraint("-_- 1st pass"), "1st_pass");
+    }
+
+    #[test]
+    fn test_normalization_is_idempotent() {
+        let corpus = [
+            "No Mocks",
+            "< 300 LOC",
+            "<300LOC",
+            "__leading",
+            "trailing__",
+            "a__b___c",
+            "_9_lives_",
+            "  1.2.3 release  ",
+            "API compatibility!",
+            "Ünïcode dbs",
+            "\u{212A}elvin",
+            "tabs\tand\nnewlines",
+            "---",
+            "",
+            "SHOULD: No Mocks",
+            "MAY: < 300 LOC",
+            "node_version: 18.x",
+            "lt_300_loc",
+        ];
+        for s in corpus {
+            let once = normalize_constraint(s);
+            assert_eq!(normalize_constraint(&once), once, "normalize({:?})", s);
+            assert!(!once.starts_with('_') && !once.ends_with('_') && !once.contains("__"));
+
+            let once = canonicalize_constraint(s);
+            assert_eq!(canonicalize_constraint(&once), once, "canonicalize_constraint({:?})", s);
+        }
     }
 
     #[test]
@@ -343,6 +1176,238 @@ mod tests {
         assert!(matches!(custom, Constraint::Other(s) if s == "custom_rule_here"));
     }
 
+    #[test]
+    fn test_key_value_constraint() {
+        assert_eq!(
+            Constraint::from_str("node_version: 18.x"),
+            Constraint::KeyValue { key: "node_version".to_string(), value: "18.x".to_string() }
+        );
+        assert_eq!(
+            Constraint::from_str("Output-Dir: src/app/"),
+            Constraint::KeyValue { key: "output_dir".to_string(), value: "src/app/".to_string() }
+        );
+        assert_eq!(canonicalize_constraint("node_version: 18.x"), "node_version: 18.x");
+
+        // Multi-word keys are not key/value constraints
+        assert!(matches!(Constraint::from_str("No mocks: ever"), Constraint::NoMocks));
+        // Prose after a one-word label is not a value
+        assert!(matches!(Constraint::from_str("Note: no mocks"), Constraint::NoMocks));
+        assert_eq!(canonicalize_constraint("Use path src/app"), "use_path_src_app");
+    }
+
+    fn semantics_for(constraints: &[&str]) -> Semantics {
+        Semantics {
+            constraints: constraints.iter().map(|c| Constraint::from_str(c)).collect(),
+            severities: BTreeMap::new(),
+            requires_plan: false,
+            complexity: 1,
+            conflict_rules: default_conflict_rules(),
+        }
+    }
+
+    #[test]
+    fn test_severity_prefix() {
+        assert_eq!(Severity::split_prefix("MUST: no_mocks"), (Severity::Must, "no_mocks"));
+        assert_eq!(Severity::split_prefix("SHOULD: prefer async"), (Severity::Should, "prefer async"));
+        assert_eq!(Severity::split_prefix("MAY:use_cache"), (Severity::May, "use_cache"));
+        assert_eq!(Severity::split_prefix("no mocks"), (Severity::Must, "no mocks"));
+        // Lowercase is a key/value constraint, not a severity
+        assert_eq!(Severity::split_prefix("may: later"), (Severity::Must, "may: later"));
+
+        assert_eq!(canonicalize_constraint("SHOULD: No Mocks"), "SHOULD: no_mocks");
+        assert_eq!(canonicalize_constraint("MAY: node_version: 18.x"), "MAY: node_version: 18.x");
+    }
+
+    #[test]
+    fn test_numeric_constraints() {
+        let coverage = Constraint::parse_numeric("> 80 coverage").unwrap();
+        assert_eq!(coverage.comparator, Comparator::Gt);
+        assert_eq!(coverage.value, 80);
+        assert_eq!(coverage.unit, "coverage");
+        assert_eq!(Constraint::from_str("> 80 coverage"), Constraint::Numeric(coverage.clone()));
+        assert_eq!(canonicalize_constraint("> 80 coverage"), "gt_80_coverage");
+        assert_eq!(Constraint::parse_numeric("gt_80_coverage"), Some(coverage));
+
+        let pct = Constraint::parse_numeric(">=90% line coverage").unwrap();
+        assert_eq!((pct.comparator, pct.value, pct.unit.as_str()), (Comparator::Ge, 90, "line_coverage"));
+
+        assert_eq!(Constraint::from_str("<= 300 LOC").as_str(), "le_300_loc");
+        assert_eq!(Constraint::from_str("= 2 reviewers").as_str(), "eq_2_reviewers");
+        assert_eq!(canonicalize_constraint("< 300 LOC"), "lt_300_loc");
+        assert!(Constraint::parse_numeric("no mocks").is_none());
+        assert!(Constraint::parse_numeric("< many LOC").is_none());
+        assert!(Constraint::parse_numeric("le_monde").is_none());
+        assert!(Comparator::Ge.holds(90, 90));
+    }
+
+    #[test]
+    fn test_resolve_conflict() {
+        let sem = semantics_for(&[]);
+        assert_eq!(sem.resolve_conflict(BlockKind::Plan, BlockKind::Constraints), BlockKind::Constraints);
+        assert_eq!(sem.resolve_conflict(BlockKind::Task, BlockKind::Goals), BlockKind::Task);
+        assert_eq!(sem.resolve_conflict(BlockKind::Plan, BlockKind::When), BlockKind::Plan);
+    }
+
+    #[test]
+    fn test_resolve_directive_conflict() {
+        let sem = semantics_for(&[]);
+
+        let resolution = sem.resolve_directive_conflict("No Mocks", "Mock the payment gateway");
+        match resolution {
+            Resolution::ConstraintWins { constraint, explanation, .. } => {
+                assert_eq!(constraint, "no_mocks");
+                assert!(explanation.contains("CONSTRAINTS take precedence"));
+            }
+            Resolution::NoConflict => panic!("expected conflict"),
+        }
+
+        assert!(matches!(
+            sem.resolve_directive_conflict("real dbs only", "Set up in-memory DB for tests"),
+            Resolution::ConstraintWins { .. }
+        ));
+        assert!(matches!(
+            sem.resolve_directive_conflict("MUST: safe refactor", "Introduce breaking change to API"),
+            Resolution::ConstraintWins { .. }
+        ));
+        assert_eq!(
+            sem.resolve_directive_conflict("No Mocks", "Run integration tests"),
+            Resolution::NoConflict
+        );
+    }
+
+    #[test]
+    fn test_conflicting_constraints() {
+        let sem = semantics_for(&["no_mocks", "use mocks for network", "real_dbs", "mock dbs"]);
+        let conflicts = sem.conflicts();
+
+        assert_eq!(conflicts.len(), 3);
+        assert_eq!(conflicts[0].0, Constraint::NoMocks);
+        assert_eq!(conflicts[0].1, Constraint::Other("use_mocks_for_network".to_string()));
+        assert_eq!(conflicts[1].1, Constraint::Other("mock_dbs".to_string()));
+        assert_eq!(conflicts[2].0, Constraint::RealDbsOnly);
+
+        // Prefix matching only on whole words
+        let sem = semantics_for(&["no_mocks", "use_mockserver"]);
+        assert!(sem.conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_constraint_categories() {
+        let sem = semantics_for(&[
+            "no_mocks",
+            "api_compat",
+            "lt_100_loc",
+            "never commit secrets",
+            "p99 latency < 50ms",
+            "no unwrap in library code",
+            "max_files: 3",
+            "be nice",
+            "real_dbs",
+        ]);
+        let groups = sem.constraints_by_category();
+
+        assert_eq!(
+            groups[&ConstraintCategory::TestIntegrity],
+            vec![Constraint::NoMocks, Constraint::RealDbsOnly]
+        );
+        assert_eq!(groups[&ConstraintCategory::Compatibility], vec![Constraint::ApiCompat]);
+        assert_eq!(groups[&ConstraintCategory::Size].len(), 2);
+        assert_eq!(groups[&ConstraintCategory::Security].len(), 1);
+        assert_eq!(groups[&ConstraintCategory::Performance].len(), 1);
+        assert_eq!(groups[&ConstraintCategory::Quality].len(), 1);
+        assert_eq!(groups[&ConstraintCategory::General], vec![Constraint::Other("be_nice".to_string())]);
+
+        // Whole words only: "latest" is not a test constraint
+        assert_eq!(Constraint::from_str("use latest toolchain").category(), ConstraintCategory::General);
+    }
+
+    #[test]
+    fn test_base_constraints() {
+        let mut sem = semantics_for(&["use mocks", "lt_50_loc", "require_tests", "owner: infra"]);
+        sem.severities.insert(Constraint::RequireTests.as_str(), Severity::Should);
+        assert!(!sem.forbids_mocks());
+
+        sem.with_base_constraints(&[
+            Constraint::NoMocks,
+            Constraint::RequireTests,
+            Constraint::NoStubs,
+            Constraint::NoStubs,
+            Constraint::LtLoc(200),
+            Constraint::KeyValue {
+                key: "owner".to_string(),
+                value: "platform".to_string(),
+            },
+        ]);
+
+        // Document wins on conflicts, duplicates and overridden values
+        assert!(!sem.forbids_mocks());
+        assert!(!sem.requires_tests());
+        assert_eq!(sem.loc_limit(), Some(50));
+        assert_eq!(sem.constraint_value("owner"), Some("infra"));
+        assert!(sem.forbids_stubs());
+        assert_eq!(sem.constraints.len(), 5);
+        assert_eq!(sem.severity(&Constraint::NoStubs), Severity::Must);
+
+        let mut plain = semantics_for(&["real_dbs"]);
+        plain.with_base_constraints(&[Constraint::NoMocks, Constraint::RequireTests]);
+        assert!(plain.forbids_mocks() && plain.requires_tests() && plain.requires_real_dbs());
+    }
+
+    #[test]
+    fn test_custom_conflict_rule() {
+        let sem = semantics_for(&["Offline only", "web access"]);
+        assert!(sem.conflicts().is_empty());
+
+        let sem = sem.with_conflict_rule("offline only", "Web Access");
+        let conflicts = sem.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].0.as_str(), "offline_only");
+    }
+
+    #[test]
+    fn test_complexity_score() {
+        let validated = |input: &str| crate::validate::validate(crate::parser::parse_str(input).unwrap()).unwrap();
+
+        // Sequential plans keep their step-count level
+        for (steps, level) in [(2, 1), (4, 2), (10, 3), (11, 4), (21, 5)] {
+            let plan: Vec<String> = (1..=steps).map(|i| format!("Step {}", i)).collect();
+            let doc = validated(&format!("TASK\nX\nPLAN\n{}", plan.join("\n")));
+            assert_eq!(doc.complexity_score().level, level, "{} steps", steps);
+            assert_eq!(Semantics::from_validated(&doc).complexity, level);
+        }
+
+        let doc = validated(
+            "TASK\nX\nPLAN\nBuild\nTest a [after: 1]\nTest b [after: 1]\nTest c [after: 1]\n\
+             TOOLS\ncode_search\ncode_edit\nCONSTRAINTS\nno mocks\nSHOULD: fast\nDIFF\nraw\nsrc/lib.rs: +1",
+        );
+        let report = doc.complexity_score();
+        let counts: Vec<(ComplexityFactor, usize)> = report.factors.iter().map(|f| (f.factor, f.count)).collect();
+        assert_eq!(
+            counts,
+            vec![
+                (ComplexityFactor::Steps, 4),
+                (ComplexityFactor::DependencyDepth, 2),
+                (ComplexityFactor::Tools, 2),
+                (ComplexityFactor::Constraints, 2),
+                (ComplexityFactor::Diff, 1),
+            ]
+        );
+        assert_eq!(report.score, 4.0 + 2.0 + 4.0 + 1.0 + 5.0);
+        assert_eq!(report.level, 3);
+
+        // Sub-steps run after their parent, the next top-level step after both
+        let nested = validated(
+            "TASK\nX\nPLAN\nPrepare\n  Fetch\n  Configure {timeout: 5s}\nBuild\nShip [after: 1] {if: step4.success}",
+        );
+        let report = nested.complexity_score();
+        let depth = report.factors.iter().find(|f| f.factor == ComplexityFactor::DependencyDepth).map(|f| f.count);
+        assert_eq!(depth, Some(3));
+
+        let empty = validated("TASK\nX").complexity_score();
+        assert_eq!((empty.score, empty.level), (0.0, 1));
+        assert!(empty.factors.is_empty());
+    }
+
     #[test]
     fn test_precedence_ordering() {
         assert!(Precedence::Constraints > Precedence::Task);
@@ -3,49 +3,171 @@
 //! Provides validation of tool names against a known registry.
 //! Per APEX v1.1, tools must be validated against a runtime registry.
 
-use std::collections::HashSet;
+use crate::errors::{ApexError, ApexErrorKind, ApexResult};
+use std::collections::{HashMap, HashSet};
 
-/// Default valid tools in the APEX ecosystem
-pub static VALID_TOOLS: &[&str] = &[
+/// Tool capability category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ToolCategory {
+    /// Code intelligence (search, read, edit)
+    Code,
+    /// Vector/embedding operations
+    Vector,
+    /// Graph operations
+    Graph,
+    /// Memory operations
+    Memory,
+    /// Unix/system operations
+    System,
+    /// File operations
+    File,
+    /// Web operations
+    Web,
+    /// MCP server tools (`mcp__*`)
+    Mcp,
+}
+
+impl ToolCategory {
+    /// Every category, in declaration order
+    pub const ALL: [ToolCategory; 8] = [
+        ToolCategory::Code,
+        ToolCategory::Vector,
+        ToolCategory::Graph,
+        ToolCategory::Memory,
+        ToolCategory::System,
+        ToolCategory::File,
+        ToolCategory::Web,
+        ToolCategory::Mcp,
+    ];
+
+    /// Parse a category name (case-insensitive)
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str().eq_ignore_ascii_case(s))
+    }
+
+    /// Lowercase category name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToolCategory::Code => "code",
+            ToolCategory::Vector => "vector",
+            ToolCategory::Graph => "graph",
+            ToolCategory::Memory => "memory",
+            ToolCategory::System => "system",
+            ToolCategory::File => "file",
+            ToolCategory::Web => "web",
+            ToolCategory::Mcp => "mcp",
+        }
+    }
+
+    /// Words in a step description that suggest a tool of this category
+    pub fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            ToolCategory::Code => &["code", "function", "symbol", "source"],
+            ToolCategory::Vector => &["vector", "embedding", "similar"],
+            ToolCategory::Graph => &["graph", "relationship", "node"],
+            ToolCategory::Memory => &["memory", "remember", "recall"],
+            ToolCategory::System => &["shell", "command", "run", "execute"],
+            ToolCategory::File => &["file", "directory", "path"],
+            ToolCategory::Web => &["web", "url", "http", "website"],
+            ToolCategory::Mcp => &[],
+        }
+    }
+}
+
+/// Default valid tools in the APEX ecosystem with their category
+pub static TOOL_CATEGORIES: &[(&str, ToolCategory)] = &DEFAULT_TOOLS;
+
+/// Default valid tools in the APEX ecosystem (the names in [`TOOL_CATEGORIES`])
+pub static VALID_TOOLS: &[&str] = &tool_names(&DEFAULT_TOOLS);
+
+const DEFAULT_TOOLS: [(&str, ToolCategory); 25] = [
     // Code intelligence
-    "code_search",
-    "code_edit",
-    "code_read",
-    "code_write",
+    ("code_search", ToolCategory::Code),
+    ("code_edit", ToolCategory::Code),
+    ("code_read", ToolCategory::Code),
+    ("code_write", ToolCategory::Code),
     // Vector/embedding operations
-    "vector_search",
-    "vector_store",
-    "vector_delete",
+    ("vector_search", ToolCategory::Vector),
+    ("vector_store", ToolCategory::Vector),
+    ("vector_delete", ToolCategory::Vector),
     // Graph operations
-    "graph_query",
-    "graph_store",
-    "graph_delete",
+    ("graph_query", ToolCategory::Graph),
+    ("graph_store", ToolCategory::Graph),
+    ("graph_delete", ToolCategory::Graph),
     // Memory operations (LTMC)
-    "memory.query",
-    "memory.store",
-    "memory.delete",
-    "memory.consolidate",
+    ("memory.query", ToolCategory::Memory),
+    ("memory.store", ToolCategory::Memory),
+    ("memory.delete", ToolCategory::Memory),
+    ("memory.consolidate", ToolCategory::Memory),
     // Unix/system operations
-    "unix_action",
-    "bash",
-    "shell",
+    ("unix_action", ToolCategory::System),
+    ("bash", ToolCategory::System),
+    ("shell", ToolCategory::System),
     // File operations
-    "read_file",
-    "write_file",
-    "edit_file",
-    "glob",
-    "grep",
+    ("read_file", ToolCategory::File),
+    ("write_file", ToolCategory::File),
+    ("edit_file", ToolCategory::File),
+    ("glob", ToolCategory::File),
+    ("grep", ToolCategory::File),
     // Web operations
-    "web_fetch",
-    "web_search",
+    ("web_fetch", ToolCategory::Web),
+    ("web_search", ToolCategory::Web),
     // Generic tool patterns
-    "mcp_tool",
+    ("mcp_tool", ToolCategory::Mcp),
 ];
 
+const fn tool_names<const N: usize>(tools: &[(&'static str, ToolCategory); N]) -> [&'static str; N] {
+    let mut names = [""; N];
+    let mut i = 0;
+    while i < N {
+        names[i] = tools[i].0;
+        i += 1;
+    }
+    names
+}
+
+/// Check a tool or alias name in a manifest: a letter followed by letters,
+/// digits, `_`, `.` or `-`
+fn check_manifest_name(name: &str) -> Result<(), String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid tool name '{}'", name))
+    }
+}
+
+/// Maximum edit distance for [`ToolRegistry::suggest`]
+pub(crate) const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Levenshtein edit distance between two strings (by character)
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b_chars.len()).collect();
+    let mut curr = vec![0; b_chars.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b_chars.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b_chars.len()]
+}
+
 /// Tool registry for validating tool names
 #[derive(Debug, Clone)]
 pub struct ToolRegistry {
     tools: HashSet<String>,
+    /// Alias name -> canonical tool name
+    aliases: HashMap<String, String>,
+    /// Tool name -> capability category
+    categories: HashMap<String, ToolCategory>,
     allow_unknown: bool,
 }
 
@@ -53,8 +175,14 @@ impl ToolRegistry {
     /// Create a new registry with default tools
     pub fn new() -> Self {
         let tools = VALID_TOOLS.iter().map(|s| s.to_string()).collect();
+        let categories = TOOL_CATEGORIES
+            .iter()
+            .map(|(name, cat)| (name.to_string(), *cat))
+            .collect();
         Self {
             tools,
+            aliases: HashMap::new(),
+            categories,
             allow_unknown: false,
         }
     }
@@ -63,14 +191,83 @@ impl ToolRegistry {
     pub fn empty() -> Self {
         Self {
             tools: HashSet::new(),
+            aliases: HashMap::new(),
+            categories: HashMap::new(),
             allow_unknown: false,
         }
     }
 
+    /// Build a registry from a plain-text manifest, one entry per line
+    ///
+    /// ```text
+    /// # comments and blank lines are skipped
+    /// code_search            # tool
+    /// run_tests -> system    # tool with a category
+    /// search = code_search   # alias for a declared tool
+    /// ```
+    ///
+    /// Only the listed tools are registered (no defaults). Malformed entries,
+    /// unknown categories, duplicate names and aliases to undeclared tools are
+    /// reported as parse errors carrying the manifest line number.
+    pub fn from_manifest(text: &str) -> ApexResult<ToolRegistry> {
+        let mut registry = Self::empty();
+        let mut aliases: Vec<(usize, &str, &str)> = Vec::new();
+
+        for (idx, raw) in text.lines().enumerate() {
+            let line_num = idx + 1;
+            let entry = raw.split('#').next().unwrap_or("").trim();
+            if entry.is_empty() {
+                continue;
+            }
+            let malformed = |reason: String| {
+                ApexError::parse(format!("Malformed tool manifest entry '{}': {}", entry, reason), Some(line_num))
+            };
+
+            if let Some((alias, canonical)) = entry.split_once('=') {
+                let (alias, canonical) = (alias.trim(), canonical.trim());
+                check_manifest_name(alias).map_err(malformed)?;
+                check_manifest_name(canonical).map_err(malformed)?;
+                aliases.push((line_num, alias, canonical));
+                continue;
+            }
+
+            let (name, category) = if let Some((name, category)) = entry.split_once("->") {
+                let category = category.trim();
+                let category = ToolCategory::from_str(category).ok_or_else(|| {
+                    let known: Vec<&str> = ToolCategory::ALL.iter().map(|c| c.as_str()).collect();
+                    malformed(format!("unknown category '{}' (expected one of: {})", category, known.join(", ")))
+                })?;
+                (name.trim(), Some(category))
+            } else {
+                (entry, None)
+            };
+
+            check_manifest_name(name).map_err(malformed)?;
+            if registry.tools.contains(name) {
+                return Err(malformed(format!("tool '{}' is already declared", name)));
+            }
+            match category {
+                Some(category) => registry.add_tool_with_category(name, category),
+                None => registry.add_tool(name),
+            }
+        }
+
+        // Aliases may precede the tool they point to
+        for (line_num, alias, canonical) in aliases {
+            registry
+                .add_alias(alias, canonical)
+                .map_err(|err| ApexError::parse(err.message, Some(line_num)))?;
+        }
+
+        Ok(registry)
+    }
+
     /// Create a permissive registry that allows any tool
     pub fn permissive() -> Self {
         Self {
             tools: HashSet::new(),
+            aliases: HashMap::new(),
+            categories: HashMap::new(),
             allow_unknown: true,
         }
     }
@@ -87,20 +284,92 @@ impl ToolRegistry {
         }
     }
 
+    /// Add a tool with a capability category
+    pub fn add_tool_with_category(&mut self, name: &str, category: ToolCategory) {
+        self.tools.insert(name.to_string());
+        self.categories.insert(name.to_string(), category);
+    }
+
+    /// Category of a tool (aliases resolved, `mcp__*` tools are `Mcp`)
+    pub fn category(&self, name: &str) -> Option<ToolCategory> {
+        let canonical = self.resolve(name)?;
+        if let Some(cat) = self.categories.get(canonical) {
+            return Some(*cat);
+        }
+        parse_mcp_tool(canonical).map(|_| ToolCategory::Mcp)
+    }
+
+    /// Registered tools in a dotted namespace (`memory` -> `memory.query`, ...), sorted by name
+    pub fn namespace_tools(&self, namespace: &str) -> Vec<&str> {
+        let mut tools: Vec<&str> = self
+            .tools
+            .iter()
+            .filter(|t| split_tool_namespace(t).is_some_and(|(ns, _)| ns == namespace))
+            .map(|t| t.as_str())
+            .collect();
+        tools.sort_unstable();
+        tools
+    }
+
+    /// Registered tools in a category, sorted by name
+    pub fn tools_in_category(&self, category: ToolCategory) -> Vec<&str> {
+        let mut tools: Vec<&str> = self
+            .tools
+            .iter()
+            .filter(|t| self.categories.get(t.as_str()) == Some(&category))
+            .map(|t| t.as_str())
+            .collect();
+        tools.sort_unstable();
+        tools
+    }
+
+    /// Register `alias` as another name for `canonical`
+    ///
+    /// Fails when `canonical` is neither a registered tool nor an `mcp__*`
+    /// tool, or when `alias` is already a tool or alias.
+    pub fn add_alias(&mut self, alias: &str, canonical: &str) -> ApexResult<()> {
+        if !self.tools.contains(canonical) && parse_mcp_tool(canonical).is_none() {
+            return Err(ApexError::new(
+                ApexErrorKind::InvalidToolName,
+                format!("Alias '{}' refers to undeclared tool '{}'", alias, canonical),
+            ));
+        }
+        if self.tools.contains(alias) || self.aliases.contains_key(alias) {
+            return Err(ApexError::new(
+                ApexErrorKind::InvalidToolName,
+                format!("Alias '{}' is already declared", alias),
+            ));
+        }
+        self.aliases.insert(alias.to_string(), canonical.to_string());
+        Ok(())
+    }
+
+    /// Resolve a tool name to its canonical form
+    ///
+    /// Aliases map to their canonical name; registered tools, `mcp__*` tools
+    /// and (in permissive registries) any name resolve to themselves.
+    /// Returns `None` for unknown tools.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        if let Some(canonical) = self.aliases.get(name) {
+            return Some(canonical.as_str());
+        }
+        if self.is_valid(name) {
+            return Some(name);
+        }
+        None
+    }
+
     /// Check if a tool is valid
     pub fn is_valid(&self, name: &str) -> bool {
         if self.allow_unknown {
             return true;
         }
         // Exact match
-        if self.tools.contains(name) {
+        if self.tools.contains(name) || self.aliases.contains_key(name) {
             return true;
         }
-        // Check for prefix patterns (e.g., "mcp__server__tool")
-        if name.starts_with("mcp__") {
-            return true;
-        }
-        false
+        // MCP namespace: "mcp__server__tool"
+        parse_mcp_tool(name).is_some()
     }
 
     /// Validate a tool name, returning an error message if invalid
@@ -108,7 +377,35 @@ impl ToolRegistry {
         if self.is_valid(name) {
             Ok(())
         } else {
-            Err(format!("Unknown tool '{}' not in registry", name))
+            Err(self.unknown_tool_message(name))
+        }
+    }
+
+    /// Closest registered tool name within 2 edits (Levenshtein distance)
+    ///
+    /// Ties are broken alphabetically so suggestions are deterministic.
+    pub fn suggest(&self, name: &str) -> Option<String> {
+        self.tools
+            .iter()
+            .map(|tool| (levenshtein(name, tool), tool))
+            .filter(|(dist, _)| *dist <= MAX_SUGGESTION_DISTANCE)
+            .min()
+            .map(|(_, tool)| tool.clone())
+    }
+
+    /// Error message for an unknown tool, with a suggestion when one is close
+    ///
+    /// Without a close match, an unknown method of a known namespace
+    /// (`memory.unknown`) lists the namespace's tools instead.
+    pub fn unknown_tool_message(&self, name: &str) -> String {
+        if let Some(suggestion) = self.suggest(name) {
+            return format!("Unknown tool '{}', did you mean '{}'?", name, suggestion);
+        }
+        let siblings = split_tool_namespace(name).map_or_else(Vec::new, |(ns, _)| self.namespace_tools(ns));
+        if siblings.is_empty() {
+            format!("Unknown tool '{}' not in registry", name)
+        } else {
+            format!("Unknown tool '{}', namespace provides: {}", name, siblings.join(", "))
         }
     }
 
@@ -129,12 +426,37 @@ impl Default for ToolRegistry {
     }
 }
 
+/// Split an MCP tool name `mcp__<server>__<tool>` into `(server, tool)`
+///
+/// Both segments must be non-empty; the tool segment may itself contain
+/// `__`. Returns `None` for bare `mcp__`, `mcp__server` and other malformed names.
+pub fn parse_mcp_tool(name: &str) -> Option<(&str, &str)> {
+    let rest = name.strip_prefix("mcp__")?;
+    let (server, tool) = rest.split_once("__")?;
+    (!server.is_empty() && !tool.is_empty()).then_some((server, tool))
+}
+
+/// Split a dotted tool name `<namespace>.<tool>` at its last dot
+///
+/// The namespace may itself be dotted (`a.b.tool` -> `("a.b", "tool")`).
+/// Returns `None` for undotted names and for names with an empty segment
+/// (`memory.`, `.query`, `memory..query`).
+pub fn split_tool_namespace(name: &str) -> Option<(&str, &str)> {
+    if name.split('.').any(str::is_empty) {
+        return None;
+    }
+    name.rsplit_once('.')
+}
+
 /// Extract tool name from a TOOLS block line
 ///
 /// Handles formats like:
 /// - `tool_name`
 /// - `tool_name(args)`
 /// - `tool_name "query"`
+///
+/// Dotted names (`memory.query`, `memory.query("x")`) are kept whole; see
+/// [`split_tool_namespace`].
 pub fn extract_tool_name(line: &str) -> &str {
     let trimmed = line.trim();
 
@@ -176,6 +498,19 @@ mod tests {
         assert!(registry.is_valid("mcp__jenkins__build_job"));
     }
 
+    #[test]
+    fn test_mcp_name_validation() {
+        assert_eq!(parse_mcp_tool("mcp__jenkins__build_job"), Some(("jenkins", "build_job")));
+        assert_eq!(parse_mcp_tool("mcp__fs__read__file"), Some(("fs", "read__file")));
+
+        let registry = ToolRegistry::new();
+        for malformed in ["mcp__", "mcp__x", "mcp____tool", "mcp__server__", "mcp_x__y"] {
+            assert_eq!(parse_mcp_tool(malformed), None, "{}", malformed);
+            assert!(!registry.is_valid(malformed), "{}", malformed);
+        }
+        assert_eq!(registry.category("mcp__x"), None);
+    }
+
     #[test]
     fn test_permissive_registry() {
         let registry = ToolRegistry::permissive();
@@ -191,6 +526,92 @@ mod tests {
         assert!(!registry.is_valid("code_search")); // Default not included
     }
 
+    #[test]
+    fn test_tools_in_category() {
+        let mut registry = ToolRegistry::new();
+        assert_eq!(
+            registry.tools_in_category(ToolCategory::File),
+            vec!["edit_file", "glob", "grep", "read_file", "write_file"]
+        );
+        assert_eq!(registry.tools_in_category(ToolCategory::Memory).len(), 4);
+
+        registry.add_tool_with_category("s3_upload", ToolCategory::Web);
+        registry.add_alias("rg", "grep").unwrap();
+        assert!(registry.tools_in_category(ToolCategory::Web).contains(&"s3_upload"));
+        assert_eq!(registry.category("rg"), Some(ToolCategory::File));
+        assert_eq!(registry.category("mcp__github__create_pr"), Some(ToolCategory::Mcp));
+        assert_eq!(registry.category("unknown"), None);
+
+        // Uncategorized custom tools are in no category
+        registry.add_tool("plain_tool");
+        assert_eq!(registry.category("plain_tool"), None);
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut registry = ToolRegistry::new();
+        registry.add_alias("ripgrep_search", "grep").unwrap();
+        registry.add_alias("mcp__rg__search", "grep").unwrap();
+
+        assert!(registry.is_valid("ripgrep_search"));
+        assert_eq!(registry.resolve("ripgrep_search"), Some("grep"));
+        assert_eq!(registry.resolve("grep"), Some("grep"));
+        assert_eq!(registry.resolve("unknown_tool"), None);
+
+        // mcp__ prefix rule still applies; aliases take priority
+        assert_eq!(registry.resolve("mcp__jenkins__build_job"), Some("mcp__jenkins__build_job"));
+        assert_eq!(registry.resolve("mcp__rg__search"), Some("grep"));
+
+        // Targets must exist and aliases must be new
+        let err = registry.add_alias("deploy_now", "deploy").unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InvalidToolName);
+        assert!(registry.add_alias("rg", "mcp__rg__search_v2").is_ok());
+        assert!(registry.add_alias("rg", "grep").is_err());
+        assert!(registry.add_alias("bash", "grep").is_err());
+        assert!(!registry.is_valid("deploy_now"));
+    }
+
+    #[test]
+    fn test_from_manifest() {
+        let manifest = "# Runtime tools\n\nsearch = code_search\ncode_search\nrun_tests -> System  # CI runner\nmcp_alias = mcp__jira__comment\n";
+        let registry = ToolRegistry::from_manifest(manifest).unwrap();
+
+        assert!(registry.is_valid("code_search"));
+        assert!(registry.is_valid("run_tests"));
+        assert!(!registry.is_valid("bash"));
+        assert_eq!(registry.resolve("search"), Some("code_search"));
+        assert_eq!(registry.resolve("mcp_alias"), Some("mcp__jira__comment"));
+        assert_eq!(registry.category("run_tests"), Some(ToolCategory::System));
+        assert_eq!(registry.tools().len(), 2);
+    }
+
+    #[test]
+    fn test_from_manifest_errors() {
+        let err = |text: &str| ToolRegistry::from_manifest(text).unwrap_err();
+
+        let bad_category = err("code_search\nrun_tests -> cloud");
+        assert_eq!(bad_category.line, Some(2));
+        assert!(bad_category.message.contains("unknown category 'cloud'"));
+
+        let bad_name = err("# header\nrun tests");
+        assert_eq!(bad_name.line, Some(2));
+        assert!(bad_name.message.contains("invalid tool name 'run tests'"));
+
+        assert_eq!(err("grep\n\ngrep").line, Some(3));
+        assert_eq!(err("search = code_search\ngrep").line, Some(1));
+        let dup_alias = err("grep\nrg = grep\nrg = grep");
+        assert_eq!((dup_alias.kind, dup_alias.line), (ApexErrorKind::ParseError, Some(3)));
+        assert!(err("grep -> ").message.contains("unknown category ''"));
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("code_search", "code_search"), 0);
+        assert_eq!(levenshtein("code_serch", "code_search"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
     #[test]
     fn test_extract_tool_name() {
         assert_eq!(extract_tool_name("code_search"), "code_search");
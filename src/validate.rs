@@ -8,16 +8,18 @@
 //! - Constraint canonicalization
 //! - Tool registry validation
 //! - DIFF format marker detection
+//! - Unknown META key detection (strict mode)
 
-use crate::ast::{ApexDocument, Block, BlockKind};
-use crate::errors::{ApexError, ApexResult};
-use crate::sem::canonicalize;
-use crate::tool_registry::{ToolRegistry, extract_tool_name};
+use crate::ast::{ApexDocument, Block, BlockKind, Span};
+use crate::errors::{ApexError, ApexErrorKind, ApexResult};
+use crate::sem::{canonicalize, canonicalize_constraint, stem_words, Comparator, Constraint, Resolution, Semantics, Severity};
+use crate::tool_registry::{levenshtein, split_tool_namespace, ToolRegistry, extract_tool_name, MAX_SUGGESTION_DISTANCE};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Component, Path};
 
 /// Validation mode for v1.1 documents
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum ValidationMode {
     /// Strict: requires version=1.1, validates tools against registry
     #[default]
@@ -28,6 +30,63 @@ pub enum ValidationMode {
     Legacy,
 }
 
+/// Validation options beyond mode and tool registry
+#[derive(Debug, Clone, Default)]
+pub struct ValidationConfig {
+    /// Concatenate repeated optional blocks instead of rejecting them
+    ///
+    /// Lines are joined in document order; later META entries override
+    /// earlier ones. TASK and WHEN are never merged.
+    pub merge_duplicate_blocks: bool,
+    /// Keep only the first TASK line; extra lines are reported as warnings
+    ///
+    /// Without this flag, multi-line TASKs are joined with `\n` (and a
+    /// warning is still emitted).
+    pub strict_single_line_task: bool,
+    /// META keys accepted in strict mode in addition to [`KNOWN_META_KEYS`]
+    pub extra_meta_keys: Vec<String>,
+    /// Fail on unknown META keys in strict mode instead of warning
+    pub reject_unknown_meta_keys: bool,
+}
+
+/// Item caps for [`validate_with_limits`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationLimits {
+    /// Maximum number of PLAN steps (nested steps included)
+    pub max_steps: usize,
+    /// Maximum number of GOALS
+    pub max_goals: usize,
+    /// Maximum number of CONSTRAINTS rules
+    pub max_constraints: usize,
+    /// Cut oversized blocks down to the limit instead of rejecting them
+    ///
+    /// Truncation is recorded in `meta_fixes` and warned about in every mode.
+    pub truncate: bool,
+}
+
+impl Default for ValidationLimits {
+    /// Generous caps: 200 steps, 50 goals, 100 constraints, no truncation
+    fn default() -> Self {
+        Self {
+            max_steps: 200,
+            max_goals: 50,
+            max_constraints: 100,
+            truncate: false,
+        }
+    }
+}
+
+impl ValidationLimits {
+    /// Builder: truncate oversized blocks instead of rejecting them
+    pub fn with_truncation(mut self) -> Self {
+        self.truncate = true;
+        self
+    }
+}
+
+/// META keys recognized in strict mode
+pub const KNOWN_META_KEYS: &[&str] = &["version", "author", "format", "created_at", "parse_fixes", "priority", "deadline"];
+
 // --- Validated View Types ---
 
 /// Validated TASK view
@@ -47,15 +106,149 @@ pub struct GoalsView {
 /// Validated PLAN view
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlanView {
-    /// Ordered execution steps
-    pub steps: Vec<String>,
+    /// Steps in document order with their source line and nesting depth
+    pub entries: Vec<PlanStep>,
+}
+
+/// PLAN step with its enumeration marker stripped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanStep {
+    /// Step text without a leading `1.`, `1)`, `Step 1:` or `-` marker
+    pub description: String,
+    /// Original line (trimmed)
+    pub raw: String,
+    /// Nesting depth by indentation (two spaces or one tab per level)
+    #[serde(default)]
+    pub depth: usize,
+    /// Source line (1-indexed)
+    #[serde(default)]
+    pub line: usize,
+}
+
+/// PLAN step with its indented sub-steps
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanItem {
+    /// Step text (trimmed, enumeration marker stripped)
+    pub text: String,
+    /// Nesting depth (0 = top level)
+    pub depth: usize,
+    /// Sub-steps
+    pub children: Vec<PlanItem>,
+}
+
+impl PlanView {
+    /// Ordered step descriptions (flattened in document order)
+    pub fn steps(&self) -> Vec<&str> {
+        self.entries.iter().map(|e| e.description.as_str()).collect()
+    }
+
+    /// Steps as a tree by nesting depth
+    pub fn items(&self) -> Vec<PlanItem> {
+        let mut items: Vec<PlanItem> = Vec::new();
+        let mut depths: Vec<usize> = Vec::with_capacity(self.entries.len());
+        for (entry, parent) in self.entries.iter().zip(self.parent_indices()) {
+            let depth = parent.map_or(0, |p| depths[p] + 1);
+            depths.push(depth);
+            let mut siblings = &mut items;
+            for _ in 0..depth {
+                siblings = &mut siblings.last_mut().expect("parent exists at shallower depth").children;
+            }
+            siblings.push(PlanItem {
+                text: entry.description.clone(),
+                depth,
+                children: Vec::new(),
+            });
+        }
+        items
+    }
+
+    /// Parent of each entry (0-based index), `None` at top level
+    pub fn parent_indices(&self) -> Vec<Option<usize>> {
+        let mut parents = Vec::with_capacity(self.entries.len());
+        // Indices of the open ancestors, shallowest first
+        let mut open: Vec<usize> = Vec::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            open.truncate(entry.depth);
+            parents.push(open.last().copied());
+            open.push(i);
+        }
+        parents
+    }
+
+    /// Longest chain of dependent steps (1 for independent steps)
+    ///
+    /// Steps depend on their `[after: ...]` steps, else sub-steps on their
+    /// parent and top-level steps on the previous top-level step and its
+    /// sub-steps. Annotations that do not name an earlier step are ignored.
+    pub(crate) fn dependency_depth(&self) -> usize {
+        let parents = self.parent_indices();
+        let mut levels: Vec<usize> = Vec::with_capacity(self.entries.len());
+        for (i, entry) in self.entries.iter().enumerate() {
+            let deps: Vec<usize> = match explicit_after(&entry.description) {
+                Some(after) => after.into_iter().filter(|&d| d >= 1 && d <= i).map(|d| d - 1).collect(),
+                None => match parents[i] {
+                    Some(parent) => vec![parent],
+                    None => parents[..i].iter().rposition(Option::is_none).map_or(Vec::new(), |p| (p..i).collect()),
+                },
+            };
+            levels.push(deps.iter().map(|&d| levels[d] + 1).max().unwrap_or(1));
+        }
+        levels.into_iter().max().unwrap_or(0)
+    }
+}
+
+/// `[after: ...]` step numbers of a PLAN line, skipping trailing `{...}` annotations
+fn explicit_after(line: &str) -> Option<Vec<usize>> {
+    let mut rest = line.trim_end();
+    loop {
+        if let Ok((_, Some(deps))) = split_after(rest) {
+            return Some(deps);
+        }
+        let open_idx = rest.strip_suffix('}').and_then(|t| t.rfind('{'))?;
+        rest = rest[..open_idx].trim_end();
+    }
+}
+
+/// One CONSTRAINTS rule in canonical and original form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstraintRule {
+    /// Canonical form used for matching and conflict detection (`no_mocks`)
+    pub canonical: String,
+    /// Original phrasing for display (`No Mocks`)
+    pub display: String,
+    /// Level from a `MUST:`/`SHOULD:`/`MAY:` prefix (`Must` when unprefixed)
+    #[serde(default)]
+    pub severity: Severity,
 }
 
 /// Validated CONSTRAINTS view
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstraintsView {
-    /// Constraint rules
-    pub rules: Vec<String>,
+    /// Constraint rules (severity prefix stripped)
+    pub rules: Vec<ConstraintRule>,
+}
+
+impl ConstraintsView {
+    /// Canonical forms of the rules, in order
+    pub fn canonical_rules(&self) -> Vec<&str> {
+        self.rules.iter().map(|r| r.canonical.as_str()).collect()
+    }
+
+    /// Canonical rules paired with their severity
+    pub fn rules_with_severity(&self) -> Vec<(Severity, String)> {
+        self.rules.iter().map(|r| (r.severity, r.canonical.clone())).collect()
+    }
+
+    /// Canonical rules with exact duplicates removed, in first-seen order
+    pub fn deduplicated(&self) -> Vec<String> {
+        let mut unique: Vec<String> = Vec::new();
+        for rule in &self.rules {
+            if !unique.contains(&rule.canonical) {
+                unique.push(rule.canonical.clone());
+            }
+        }
+        unique
+    }
 }
 
 /// Validated VALIDATION view
@@ -65,6 +258,72 @@ pub struct ValidationView {
     pub conditions: Vec<String>,
 }
 
+/// Machine-checkable form of a VALIDATION line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ValidationCondition {
+    /// `command: cargo test` - passes when the command exits with status 0
+    Command(String),
+    /// `metric: latency < 100ms` - compares a measured value against a bound
+    Metric {
+        /// Measured quantity (`latency`)
+        name: String,
+        /// How the measurement is compared against `value`
+        comparator: Comparator,
+        /// Bound to compare against (`100`)
+        value: f64,
+        /// Unit of `value`, empty if none (`ms`)
+        unit: String,
+    },
+    /// Anything else, kept verbatim
+    FreeText(String),
+}
+
+impl ValidationView {
+    /// Parse conditions into checkable forms
+    ///
+    /// Lines with a `command:` or `metric:` prefix (case-insensitive) are
+    /// structured; a malformed metric falls back to `FreeText`.
+    pub fn parse_conditions(&self) -> Vec<ValidationCondition> {
+        self.conditions.iter().map(|c| parse_condition(c)).collect()
+    }
+}
+
+fn parse_condition(line: &str) -> ValidationCondition {
+    let free_text = || ValidationCondition::FreeText(line.to_string());
+    let Some((prefix, body)) = line.split_once(':') else {
+        return free_text();
+    };
+    let body = body.trim();
+
+    match prefix.trim().to_lowercase().as_str() {
+        "command" if !body.is_empty() => ValidationCondition::Command(body.to_string()),
+        "metric" => parse_metric(body).unwrap_or_else(free_text),
+        _ => free_text(),
+    }
+}
+
+/// Parse `name <op> value[unit]`
+fn parse_metric(body: &str) -> Option<ValidationCondition> {
+    let op_idx = body.find(['<', '>', '='])?;
+    let name = body[..op_idx].trim();
+    let (comparator, rest) = Comparator::strip_symbol(&body[op_idx..])?;
+    let rest = rest.trim_start();
+    let number_end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(rest.len());
+    let value = rest[..number_end].parse().ok()?;
+    if name.is_empty() {
+        return None;
+    }
+
+    Some(ValidationCondition::Metric {
+        name: name.to_string(),
+        comparator,
+        value,
+        unit: rest[number_end..].trim().to_string(),
+    })
+}
+
 /// Validated TOOLS view
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolsView {
@@ -73,14 +332,19 @@ pub struct ToolsView {
 }
 
 /// Tool declaration parsed from TOOLS block
+///
+/// A declaration may continue onto following lines by ending a line with `\`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolDeclaration {
     /// Tool name
     pub name: String,
     /// Raw argument string (unparsed)
     pub arguments: Option<String>,
-    /// Original line
+    /// Original line (continuations joined with a single space)
     pub raw: String,
+    /// Source extent of the whole declaration
+    #[serde(default)]
+    pub span: Span,
 }
 
 /// DIFF format marker per APEX v1.1
@@ -101,9 +365,164 @@ pub struct DiffView {
     /// Format marker if present
     pub format: DiffFormat,
     /// Expected file changes (excluding format marker line)
+    ///
+    /// Unified diffs keep leading whitespace and blank lines, since both
+    /// are significant in hunk bodies.
     pub changes: Vec<String>,
 }
 
+/// Single line operation inside a unified diff hunk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiffLine {
+    /// Unchanged line (` ` prefix)
+    Context(String),
+    /// Added line (`+` prefix)
+    Added(String),
+    /// Removed line (`-` prefix)
+    Removed(String),
+}
+
+/// Hunk of a unified diff (`@@ -a,b +c,d @@`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffHunk {
+    /// Start line in the old file
+    pub old_start: usize,
+    /// Line count in the old file
+    pub old_count: usize,
+    /// Start line in the new file
+    pub new_start: usize,
+    /// Line count in the new file
+    pub new_count: usize,
+    /// Line operations in order
+    pub lines: Vec<DiffLine>,
+}
+
+/// Changes to a single file in a unified diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDiff {
+    /// Path from the `---` header (as written, e.g. `a/src/lib.rs`)
+    pub old_path: String,
+    /// Path from the `+++` header (as written, e.g. `b/src/lib.rs`)
+    pub new_path: String,
+    /// Hunks in order
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// Structured unified diff parsed from a DIFF block
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct UnifiedDiff {
+    /// Per-file changes in order
+    pub files: Vec<FileDiff>,
+}
+
+impl DiffView {
+    /// Parse `changes` as a unified diff
+    ///
+    /// Fails with `ParseError` if the block is not marked `unified`, a hunk
+    /// appears before its file headers, or a hunk header is malformed.
+    pub fn parse_unified(&self) -> ApexResult<UnifiedDiff> {
+        if self.format != DiffFormat::Unified {
+            return Err(ApexError::parse("DIFF block is not marked 'unified'", None));
+        }
+
+        let mut diff = UnifiedDiff::default();
+        let mut old_path: Option<String> = None;
+        // Remaining (old, new) line counts of the hunk being read
+        let mut remaining = (0usize, 0usize);
+
+        for line in &self.changes {
+            if (remaining.0 > 0 || remaining.1 > 0) && !line.starts_with("@@") {
+                let hunk = diff
+                    .files
+                    .last_mut()
+                    .and_then(|f| f.hunks.last_mut())
+                    .ok_or_else(|| ApexError::new(ApexErrorKind::InternalError, "Hunk state lost"))?;
+                let op = match line.chars().next() {
+                    Some('+') => DiffLine::Added(line[1..].to_string()),
+                    Some('-') => DiffLine::Removed(line[1..].to_string()),
+                    Some('\\') => continue, // "\ No newline at end of file"
+                    Some(' ') => DiffLine::Context(line[1..].to_string()),
+                    _ => DiffLine::Context(line.to_string()),
+                };
+                match op {
+                    DiffLine::Added(_) => remaining.1 = remaining.1.saturating_sub(1),
+                    DiffLine::Removed(_) => remaining.0 = remaining.0.saturating_sub(1),
+                    DiffLine::Context(_) => {
+                        remaining.0 = remaining.0.saturating_sub(1);
+                        remaining.1 = remaining.1.saturating_sub(1);
+                    }
+                }
+                hunk.lines.push(op);
+                continue;
+            }
+
+            if let Some(path) = line.strip_prefix("--- ") {
+                old_path = Some(header_path(path));
+            } else if let Some(path) = line.strip_prefix("+++ ") {
+                let old = old_path.take().ok_or_else(|| {
+                    ApexError::parse(format!("'+++' header without '---' header: {}", line), None)
+                })?;
+                diff.files.push(FileDiff {
+                    old_path: old,
+                    new_path: header_path(path),
+                    hunks: Vec::new(),
+                });
+            } else if line.starts_with("@@") {
+                let file = diff.files.last_mut().ok_or_else(|| {
+                    ApexError::parse(format!("Hunk without file headers: {}", line), None)
+                })?;
+                let hunk = parse_hunk_header(line)?;
+                remaining = (hunk.old_count, hunk.new_count);
+                file.hunks.push(hunk);
+            }
+            // Other lines outside hunks (`diff --git`, `index ...`) are ignored
+        }
+
+        Ok(diff)
+    }
+}
+
+/// Strip a trailing timestamp from a `---`/`+++` header path
+fn header_path(path: &str) -> String {
+    path.split('\t').next().unwrap_or(path).trim().to_string()
+}
+
+/// Parse `@@ -a,b +c,d @@` (counts default to 1 when omitted)
+fn parse_hunk_header(line: &str) -> ApexResult<DiffHunk> {
+    let malformed = || ApexError::parse(format!("Malformed hunk header: {}", line), None);
+
+    let inner = line
+        .strip_prefix("@@")
+        .and_then(|rest| rest.split("@@").next())
+        .ok_or_else(malformed)?;
+    let mut parts = inner.split_whitespace();
+    let old = parts.next().and_then(|p| p.strip_prefix('-')).ok_or_else(malformed)?;
+    let new = parts.next().and_then(|p| p.strip_prefix('+')).ok_or_else(malformed)?;
+    if parts.next().is_some() || !line[2..].contains("@@") {
+        return Err(malformed());
+    }
+
+    let parse_range = |range: &str| -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_count) = parse_range(old).ok_or_else(malformed)?;
+    let (new_start, new_count) = parse_range(new).ok_or_else(malformed)?;
+
+    Ok(DiffHunk {
+        old_start,
+        old_count,
+        new_start,
+        new_count,
+        lines: Vec::new(),
+    })
+}
+
+/// Approximate characters per model token for [`ContextView::approx_tokens`]
+pub const CHARS_PER_TOKEN: usize = 4;
+
 /// Validated CONTEXT view
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextView {
@@ -111,41 +530,391 @@ pub struct ContextView {
     pub lines: Vec<String>,
 }
 
-/// Validated META view
+impl ContextView {
+    /// Size of the context in bytes, with lines joined by `\n`
+    pub fn byte_len(&self) -> usize {
+        let content: usize = self.lines.iter().map(|l| l.len()).sum();
+        content + self.lines.len().saturating_sub(1)
+    }
+
+    /// Rough token estimate: characters / [`CHARS_PER_TOKEN`], rounded up
+    pub fn approx_tokens(&self) -> usize {
+        let content: usize = self.lines.iter().map(|l| l.chars().count()).sum();
+        let chars = content + self.lines.len().saturating_sub(1);
+        chars.div_ceil(CHARS_PER_TOKEN)
+    }
+
+    /// Paths referenced by `@path` lines, in order
+    pub fn file_references(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|l| l.trim_start().strip_prefix('@'))
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+
+    /// Read every referenced file relative to `root`
+    ///
+    /// Returns `(path, content)` pairs in reference order. References must
+    /// stay inside `root`: absolute paths and `..` components are rejected
+    /// with [`ApexErrorKind::ValidationFailure`]. Fails with
+    /// [`ApexErrorKind::Io`] on the first file that cannot be read.
+    pub fn resolve_context(&self, root: &Path) -> ApexResult<Vec<(String, String)>> {
+        self.file_references()
+            .into_iter()
+            .map(|path| {
+                let escapes = Path::new(path)
+                    .components()
+                    .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+                if escapes {
+                    return Err(ApexError::new(
+                        ApexErrorKind::ValidationFailure,
+                        format!("CONTEXT file reference '{}' must be a relative path inside the root", path),
+                    ));
+                }
+                let content = std::fs::read_to_string(root.join(path)).map_err(|e| {
+                    ApexError::new(ApexErrorKind::Io, format!("Failed to read CONTEXT file {}: {}", path, e))
+                })?;
+                Ok((path.to_string(), content))
+            })
+            .collect()
+    }
+}
+
+/// Validated WHEN view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WhenView {
+    /// Free-text condition (first line of the block)
+    pub condition: String,
+    /// Steps to run when the condition holds
+    pub steps: Vec<String>,
+}
+
+/// Steps a retry policy applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RetryTarget {
+    /// Every step
+    All,
+    /// A single step (1-indexed)
+    Step(usize),
+}
+
+/// Retry policy parsed from a RETRY line such as `step 3: 5 retries, backoff exponential`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Steps the policy applies to
+    pub target: RetryTarget,
+    /// Maximum number of retries after the first attempt
+    pub max_retries: u32,
+    /// Optional backoff hint (free text, e.g. `exponential`, `2s`)
+    pub backoff: Option<String>,
+}
+
+/// Validated RETRY view
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryView {
+    /// Retry policies in document order
+    pub policies: Vec<RetryPolicy>,
+}
+
+impl RetryView {
+    /// Retry count for a step (step-specific policy wins over `all`)
+    pub fn max_retries_for(&self, step_number: usize) -> u32 {
+        let specific = self
+            .policies
+            .iter()
+            .rev()
+            .find(|p| p.target == RetryTarget::Step(step_number));
+        let fallback = self.policies.iter().rev().find(|p| p.target == RetryTarget::All);
+        specific.or(fallback).map(|p| p.max_retries).unwrap_or(0)
+    }
+}
+
+/// Validated META view
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MetaView {
-    /// Key-value metadata pairs
+    /// Key-value metadata pairs, keyed by canonical (trimmed, lowercase) key
     pub entries: HashMap<String, String>,
+    /// Canonical key -> key as written in the document
+    #[serde(default)]
+    pub original_keys: HashMap<String, String>,
+}
+
+/// Canonical form of a META key: trimmed and lowercased
+pub(crate) fn canonical_meta_key(key: &str) -> String {
+    key.trim().to_lowercase()
 }
 
 impl MetaView {
+    /// Get an entry by key (case-insensitive, surrounding whitespace ignored)
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(&canonical_meta_key(key)).map(|s| s.as_str())
+    }
+
+    /// Key as originally written, for round-tripping (falls back to `key`)
+    pub fn original_key<'a>(&'a self, key: &'a str) -> &'a str {
+        self.original_keys
+            .get(&canonical_meta_key(key))
+            .map_or(key, |k| k.as_str())
+    }
+
     /// Get APEX version from META if present
     pub fn version(&self) -> Option<&str> {
-        self.entries.get("version").map(|s| s.as_str())
+        self.get("version")
     }
 
     /// Check if version is compatible with this implementation
+    ///
+    /// A missing version is treated as v1.0. Otherwise the version must lie
+    /// within `APEX_MIN_VERSION..=APEX_VERSION`.
     pub fn is_version_compatible(&self) -> bool {
-        match self.version() {
-            None => true, // No version = v1.0 assumed, compatible
-            Some(v) => {
-                // Parse major.minor
-                let parts: Vec<&str> = v.split('.').collect();
-                if parts.is_empty() {
-                    return false;
-                }
-                match parts[0].parse::<u32>() {
-                    Ok(1) => true, // v1.x is compatible
-                    Ok(major) if major >= 2 => false, // v2+ not supported
-                    _ => false,
+        self.version().is_none()
+            || self.version_satisfies(crate::APEX_MIN_VERSION, crate::APEX_VERSION)
+    }
+
+    /// Check that the META version lies within `min..=max`
+    ///
+    /// Comparison is on major.minor; a patch component (`1.1.0`) is accepted
+    /// but ignored. Returns false if any version is missing or malformed.
+    pub fn version_satisfies(&self, min: &str, max: &str) -> bool {
+        let (Some(v), Some(lo), Some(hi)) = (
+            self.version().and_then(parse_version),
+            parse_version(min),
+            parse_version(max),
+        ) else {
+            return false;
+        };
+        lo <= v && v <= hi
+    }
+
+    /// Get parse_fixes if recorded (from tolerant mode)
+    pub fn parse_fixes(&self) -> Option<&str> {
+        self.get("parse_fixes")
+    }
+
+    /// Get author if present
+    pub fn author(&self) -> Option<&str> {
+        self.get("author")
+    }
+
+    /// Get creation timestamp if present (unparsed)
+    pub fn created_at(&self) -> Option<&str> {
+        self.get("created_at")
+    }
+
+    /// Parse an entry into `T`, returning `None` if missing or unparsable
+    pub fn get_parsed<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key).and_then(|v| v.parse().ok())
+    }
+
+    /// Scheduling priority (0-255), `None` if missing or not a number in range
+    pub fn priority(&self) -> Option<u8> {
+        self.get_parsed("priority")
+    }
+
+    /// Deadline as a comparable [`Timestamp`], `None` if missing or malformed
+    pub fn deadline(&self) -> Option<Timestamp> {
+        self.get("deadline").and_then(Timestamp::parse)
+    }
+}
+
+/// Point in time as seconds since the Unix epoch (UTC), for ordering deadlines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Timestamp {
+    pub unix_seconds: i64,
+}
+
+impl Timestamp {
+    /// Parse an RFC 3339-style date or timestamp
+    ///
+    /// Accepts `2024-05-01T10:00:00Z`, `2024-05-01T10:00+02:00` (`T` or a
+    /// space between date and time, seconds optional, fractions ignored, no
+    /// offset meaning UTC) and a bare date `2024-05-01`, which stands for the
+    /// end of that day (UTC).
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (date, time) = match s.find(['T', 't', ' ']) {
+            Some(idx) => (&s[..idx], Some(&s[idx + 1..])),
+            None => (s, None),
+        };
+
+        let mut parts = date.split('-');
+        let (year, month, day) = (
+            fixed_digits(parts.next()?, 4)?,
+            fixed_digits(parts.next()?, 2)?,
+            fixed_digits(parts.next()?, 2)?,
+        );
+        if parts.next().is_some() || !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        let (seconds, offset) = match time {
+            None => (23 * 3600 + 59 * 60 + 59, 0),
+            Some(time) => {
+                let (clock, offset) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+                    (clock, 0)
+                } else if let Some(idx) = time.rfind(['+', '-']) {
+                    let sign = if time[idx..].starts_with('-') { -1 } else { 1 };
+                    let (hours, minutes) = time[idx + 1..].split_once(':')?;
+                    let (hours, minutes) = (fixed_digits(hours, 2)?, fixed_digits(minutes, 2)?);
+                    if hours > 23 || minutes > 59 {
+                        return None;
+                    }
+                    (&time[..idx], sign * (hours * 3600 + minutes * 60))
+                } else {
+                    (time, 0)
+                };
+
+                let mut fields = clock.split(':');
+                let hour = fixed_digits(fields.next()?, 2)?;
+                let minute = fixed_digits(fields.next()?, 2)?;
+                let second = match fields.next() {
+                    Some(sec) => fixed_digits(sec.split_once('.').map_or(sec, |(whole, _)| whole), 2)?,
+                    None => 0,
+                };
+                if fields.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+                    return None;
                 }
+                (hour * 3600 + minute * 60 + second, offset)
             }
+        };
+
+        Some(Self {
+            unix_seconds: days_from_civil(year, month, day) * 86_400 + seconds - offset,
+        })
+    }
+}
+
+/// Exactly `len` ASCII digits as a number
+fn fixed_digits(s: &str, len: usize) -> Option<i64> {
+    (s.len() == len && s.bytes().all(|b| b.is_ascii_digit())).then(|| s.parse().ok())?
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Parse `major[.minor[.patch]]` into `(major, minor)`
+fn parse_version(s: &str) -> Option<(u32, u32)> {
+    let mut parts = s.trim().split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map(str::parse).transpose().ok()?.unwrap_or(0);
+    if let Some(patch) = parts.next() {
+        patch.parse::<u32>().ok()?;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor))
+}
+
+/// Two GOALS or CONSTRAINTS entries with the same canonical form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NearDuplicate {
+    /// Block containing both entries
+    pub kind: BlockKind,
+    /// Line of the first occurrence
+    pub first_line: usize,
+    /// First occurrence as written
+    pub first: String,
+    /// Line of the repeated entry
+    pub second_line: usize,
+    /// Repeated entry as written
+    pub second: String,
+}
+
+impl std::fmt::Display for NearDuplicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} line {} '{}' duplicates line {} '{}'",
+            self.kind, self.second_line, self.second, self.first_line, self.first
+        )
+    }
+}
+
+/// Category of a validation warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WarningKind {
+    /// META has no `version` entry (strict mode)
+    MissingVersion,
+    /// No META block at all (strict mode)
+    MissingMeta,
+    /// PLAN step calls a tool that is neither declared nor registered
+    UnknownTool,
+    /// Declared tool unknown to the registry, kept in lenient mode
+    ToolDegraded,
+    /// Block (or WHEN condition) without content
+    EmptyBlock,
+    /// PLAN, GOALS or CONSTRAINTS longer than its [`ValidationLimits`] cap
+    LimitExceeded,
+    /// TASK spans several lines
+    MultiLineTask,
+    /// Multi-goal task without a PLAN (lenient modes)
+    MissingPlan,
+    /// META key outside the known set
+    UnknownMetaKey,
+    /// `priority` or `deadline` META value that cannot be parsed, ignored
+    InvalidMetaValue,
+    /// Constraint line with an empty canonical form, dropped
+    EmptyConstraint,
+    /// Tool declaration with unbalanced parentheses, repaired
+    UnbalancedParens,
+    /// Dotted tool name with an empty segment (`memory.`), kept in lenient modes
+    MalformedToolName,
+    /// GOALS or CONSTRAINTS entry repeating an earlier one
+    NearDuplicate,
+    /// PLAN step contradicting a constraint
+    ConstraintConflict,
+}
+
+/// Non-fatal validation issue
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Warning {
+    /// Category, for branching without matching on `message`
+    pub kind: WarningKind,
+    /// Human-readable description
+    pub message: String,
+    /// Source line the warning refers to (1-indexed), if any
+    pub line: Option<usize>,
+}
+
+impl Warning {
+    /// Create a warning without line context
+    pub fn new(kind: WarningKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            line: None,
         }
     }
 
-    /// Get parse_fixes if recorded (from tolerant mode)
-    pub fn parse_fixes(&self) -> Option<&str> {
-        self.entries.get("parse_fixes").map(|s| s.as_str())
+    /// Attach a source line
+    pub fn with_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
     }
 }
 
@@ -165,12 +934,234 @@ pub struct ValidatedDocument {
     pub diff: Option<DiffView>,
     pub context: Option<ContextView>,
     pub meta: Option<MetaView>,
+    /// Conditional step groups, in document order
+    #[serde(default)]
+    pub when: Vec<WhenView>,
+    /// Retry policies
+    #[serde(default)]
+    pub retry: Option<RetryView>,
     /// Parse/validation fixes applied (v1.1 tolerant mode)
     #[serde(default)]
     pub meta_fixes: Vec<String>,
     /// Validation warnings (non-fatal issues)
     #[serde(default)]
-    pub warnings: Vec<String>,
+    pub warnings: Vec<Warning>,
+    /// Mode the document was validated under (`Legacy` for [`validate`])
+    #[serde(default)]
+    pub validated_mode: ValidationMode,
+    /// Whether a [`ToolRegistry`] was supplied, i.e. tool names were checked
+    #[serde(default)]
+    pub registry_checked: bool,
+    /// META `priority`, if valid
+    #[serde(default)]
+    pub priority: Option<u8>,
+    /// META `deadline`, if valid
+    #[serde(default)]
+    pub deadline: Option<Timestamp>,
+}
+
+/// Words ignored when matching GOALS to VALIDATION conditions
+const COVERAGE_STOPWORDS: &[&str] = &[
+    "the", "and", "for", "with", "all", "are", "that", "this", "from", "into", "should", "must",
+    "have", "has", "not", "any", "each", "when", "after", "before", "still", "work",
+];
+
+/// Significant (stemmed, lowercase) words of a goal or condition
+fn coverage_keywords(text: &str) -> Vec<String> {
+    stem_words(&canonicalize(text))
+        .into_iter()
+        .filter(|w| w.len() >= 3 && !COVERAGE_STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+impl ValidatedDocument {
+    /// Whether each GOAL appears to be checked by some VALIDATION condition
+    ///
+    /// A goal is covered when it shares a keyword with a condition, ignoring
+    /// case, plural `s`, short words and common filler words. Returns
+    /// `(goal, covered)` pairs in GOALS order.
+    pub fn goal_coverage(&self) -> Vec<(String, bool)> {
+        let Some(ref goals) = self.goals else {
+            return Vec::new();
+        };
+        let condition_words: Vec<String> = self
+            .validation
+            .as_ref()
+            .map(|v| v.conditions.iter().flat_map(|c| coverage_keywords(c)).collect())
+            .unwrap_or_default();
+
+        goals
+            .goals
+            .iter()
+            .map(|goal| {
+                let covered = coverage_keywords(goal).iter().any(|w| condition_words.contains(w));
+                (goal.clone(), covered)
+            })
+            .collect()
+    }
+
+    /// GOALS and CONSTRAINTS entries repeating an earlier entry of the block
+    ///
+    /// Entries match when their canonical forms are equal, so `Improve recall`
+    /// and `improve recall.` (or `No Mocks` and `no_mocks`) pair up. Each
+    /// repeat is paired with the first occurrence.
+    pub fn near_duplicate_warnings(&self) -> Vec<NearDuplicate> {
+        let mut found = Vec::new();
+        let blocks = [
+            (self.doc.goals(), canonicalize as fn(&str) -> String),
+            (self.doc.constraints(), |s: &str| canonicalize_constraint(Severity::split_prefix(s).1)),
+        ];
+
+        for (block, canonical) in blocks {
+            let Some(block) = block else { continue };
+            let mut seen: Vec<(String, usize, &str)> = Vec::new();
+            for (idx, raw) in block.lines.iter().enumerate() {
+                let text = raw.trim();
+                let key = canonical(text);
+                if key.is_empty() {
+                    continue;
+                }
+                let line = block.source_line(idx);
+                match seen.iter().find(|(k, _, _)| *k == key) {
+                    Some((_, first_line, first)) => found.push(NearDuplicate {
+                        kind: block.kind.clone(),
+                        first_line: *first_line,
+                        first: first.to_string(),
+                        second_line: line,
+                        second: text.to_string(),
+                    }),
+                    None => seen.push((key, line, text)),
+                }
+            }
+        }
+        found
+    }
+
+    /// PLAN steps (0-based) that textually contradict a constraint
+    ///
+    /// Only the mock, stub and real-database constraints are checked, using
+    /// the keyword heuristics of [`Semantics::resolve_directive_conflict`]
+    /// ("Add mock for the DB" violates `no_mocks`).
+    pub fn constraint_plan_conflicts(&self) -> Vec<(usize, Constraint)> {
+        let (Some(plan), Some(_)) = (&self.plan, &self.constraints) else {
+            return Vec::new();
+        };
+        let sem = Semantics::from_validated(self);
+        let checked: Vec<Constraint> = sem
+            .unique_constraints()
+            .into_iter()
+            .filter(|c| matches!(c, Constraint::NoMocks | Constraint::NoStubs | Constraint::RealDbsOnly))
+            .collect();
+
+        let mut conflicts = Vec::new();
+        for (idx, step) in plan.steps().into_iter().enumerate() {
+            for constraint in &checked {
+                if sem.resolve_directive_conflict(&constraint.as_str(), step) != Resolution::NoConflict {
+                    conflicts.push((idx, constraint.clone()));
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Warning messages as plain strings, for display
+    pub fn warning_messages(&self) -> Vec<String> {
+        self.warnings.iter().map(|w| w.message.clone()).collect()
+    }
+
+    /// Warnings of the given kind
+    pub fn warnings_of_kind(&self, kind: WarningKind) -> impl Iterator<Item = &Warning> {
+        self.warnings.iter().filter(move |w| w.kind == kind)
+    }
+
+    /// Check if the META deadline lies before `now` (parsed like [`Timestamp::parse`])
+    ///
+    /// False without a valid deadline or when `now` cannot be parsed.
+    pub fn is_overdue(&self, now: &str) -> bool {
+        match (self.deadline, Timestamp::parse(now)) {
+            (Some(deadline), Some(now)) => now > deadline,
+            _ => false,
+        }
+    }
+
+    /// Check if the CONTEXT block fits in `max_tokens` (true when absent)
+    pub fn context_fits(&self, max_tokens: usize) -> bool {
+        self.context
+            .as_ref()
+            .is_none_or(|c| c.approx_tokens() <= max_tokens)
+    }
+
+    /// Warnings for DIFF target paths outside the allowed scope
+    ///
+    /// A path is in scope when it is listed in CONTEXT (plain or `@path`)
+    /// or lies under one of `allowed_roots`. Paths containing `..` are always
+    /// out of scope. Only unified diffs carry machine-readable paths
+    /// (`--- a/...` / `+++ b/...`); other formats yield no warnings.
+    pub fn check_diff_scope(&self, allowed_roots: &[&Path]) -> Vec<String> {
+        let Some(ref diff) = self.diff else {
+            return Vec::new();
+        };
+        if diff.format != DiffFormat::Unified {
+            return Vec::new();
+        }
+
+        let context_paths: Vec<&str> = self
+            .context
+            .as_ref()
+            .map(|c| {
+                c.lines
+                    .iter()
+                    .map(|l| l.trim().strip_prefix('@').unwrap_or(l).trim())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Prefer the count-driven parser (hunk bodies may contain `--- ` lines);
+        // fall back to scanning headers if the diff is malformed
+        let headers: Vec<String> = match diff.parse_unified() {
+            Ok(unified) => unified
+                .files
+                .iter()
+                .flat_map(|f| [f.old_path.clone(), f.new_path.clone()])
+                .collect(),
+            Err(_) => diff
+                .changes
+                .iter()
+                .filter_map(|l| l.strip_prefix("--- ").or_else(|| l.strip_prefix("+++ ")))
+                .map(header_path)
+                .collect(),
+        };
+
+        let mut warnings = Vec::new();
+        let mut seen: Vec<String> = Vec::new();
+        for header in headers {
+            if header == "/dev/null" {
+                continue;
+            }
+            let path = header
+                .strip_prefix("a/")
+                .or_else(|| header.strip_prefix("b/"))
+                .unwrap_or(&header)
+                .to_string();
+            if seen.contains(&path) {
+                continue;
+            }
+
+            let target = Path::new(&path);
+            let escapes = target
+                .components()
+                .any(|c| matches!(c, std::path::Component::ParentDir));
+            let in_scope = !escapes
+                && (context_paths.contains(&path.as_str())
+                    || allowed_roots.iter().any(|root| target.starts_with(root)));
+            if !in_scope {
+                warnings.push(format!("DIFF touches '{}' outside the allowed scope", path));
+            }
+            seen.push(path);
+        }
+
+        warnings
+    }
 }
 
 /// Validate parsed document (legacy mode - no version enforcement)
@@ -184,105 +1175,604 @@ pub fn validate_with_mode(
     mode: ValidationMode,
     registry: Option<&ToolRegistry>,
 ) -> ApexResult<ValidatedDocument> {
+    validate_with_config(doc, mode, registry, &ValidationConfig::default())
+}
+
+/// Validate parsed document with mode, optional tool registry and config
+pub fn validate_with_config(
+    doc: ApexDocument,
+    mode: ValidationMode,
+    registry: Option<&ToolRegistry>,
+    config: &ValidationConfig,
+) -> ApexResult<ValidatedDocument> {
+    let validated = validate_inner(doc, mode, registry, config, &mut ErrorSink::fail_fast())?;
+    Ok(validated.expect("fail-fast validation returns the first error"))
+}
+
+/// Validate, then check item counts against `limits`
+///
+/// An oversized PLAN, GOALS or CONSTRAINTS block is truncated to its limit
+/// when [`ValidationLimits::truncate`] is set; otherwise it is an error in
+/// strict mode and a warning in the lenient modes.
+pub fn validate_with_limits(
+    doc: ApexDocument,
+    mode: ValidationMode,
+    registry: Option<&ToolRegistry>,
+    limits: &ValidationLimits,
+) -> ApexResult<ValidatedDocument> {
+    let mut validated = validate_with_mode(doc, mode, registry)?;
+
+    let counts = [
+        (BlockKind::Plan, "steps", validated.plan.as_ref().map_or(0, |p| p.entries.len()), limits.max_steps),
+        (BlockKind::Goals, "goals", validated.goals.as_ref().map_or(0, |g| g.goals.len()), limits.max_goals),
+        (
+            BlockKind::Constraints,
+            "rules",
+            validated.constraints.as_ref().map_or(0, |c| c.rules.len()),
+            limits.max_constraints,
+        ),
+    ];
+    for (kind, noun, count, max) in counts {
+        if count <= max {
+            continue;
+        }
+        let line = validated.doc.get_block(kind.clone()).map_or(1, |b| b.span.start_line);
+        let mut message = format!("{} has {} {}, exceeding the limit of {}", kind.as_str(), count, noun, max);
+
+        if limits.truncate {
+            match kind {
+                BlockKind::Plan => {
+                    let plan = validated.plan.as_mut().expect("counted PLAN is present");
+                    plan.entries.truncate(max);
+                }
+                BlockKind::Goals => {
+                    validated.goals.as_mut().expect("counted GOALS is present").goals.truncate(max);
+                }
+                _ => {
+                    let constraints = validated.constraints.as_mut().expect("counted CONSTRAINTS is present");
+                    constraints.rules.truncate(max);
+                }
+            }
+            validated
+                .meta_fixes
+                .push(format!("Truncated {} from {} to {} {}", kind.as_str(), count, max, noun));
+            message.push_str(&format!("; truncated to {}", max));
+        } else if mode == ValidationMode::Strict {
+            return Err(ApexError::new(ApexErrorKind::ValidationFailure, message).with_line(line));
+        }
+        validated.warnings.push(Warning::new(WarningKind::LimitExceeded, message).with_line(line));
+    }
+
+    Ok(validated)
+}
+
+/// Validate, gathering every error instead of stopping at the first
+///
+/// Recoverable errors (extra TASK or duplicate blocks, unknown tools, a bad
+/// META version, ...) are recorded and validation continues as if the
+/// offending item were absent or accepted, so the document is still
+/// returned. A missing or empty TASK cannot be recovered from: the document
+/// is `None`, but the rest of the document is still checked.
+pub fn validate_collecting(
+    doc: ApexDocument,
+    mode: ValidationMode,
+    registry: Option<&ToolRegistry>,
+) -> (Option<ValidatedDocument>, Vec<ApexError>) {
+    let mut sink = ErrorSink::collecting();
+    let validated = validate_inner(doc, mode, registry, &ValidationConfig::default(), &mut sink)
+        .expect("collecting validation never fails fast");
+    (validated, sink.errors)
+}
+
+/// Destination for validation errors
+struct ErrorSink {
+    /// Keep going after an error instead of returning it
+    collect: bool,
+    errors: Vec<ApexError>,
+}
+
+impl ErrorSink {
+    fn fail_fast() -> Self {
+        Self { collect: false, errors: Vec::new() }
+    }
+
+    fn collecting() -> Self {
+        Self { collect: true, errors: Vec::new() }
+    }
+
+    /// Return `err` when failing fast, otherwise record it and carry on
+    fn report(&mut self, err: ApexError) -> ApexResult<()> {
+        if !self.collect {
+            return Err(err);
+        }
+        self.errors.push(err);
+        Ok(())
+    }
+
+    /// Report a failed view parse, recovering as if the block were absent
+    fn recover<T>(&mut self, result: ApexResult<Option<T>>) -> ApexResult<Option<T>> {
+        match result {
+            Ok(view) => Ok(view),
+            Err(err) => self.report(err).map(|_| None),
+        }
+    }
+}
+
+/// Shared validation pass; `None` only when collecting and no TASK is usable
+fn validate_inner(
+    mut doc: ApexDocument,
+    mode: ValidationMode,
+    registry: Option<&ToolRegistry>,
+    config: &ValidationConfig,
+    errors: &mut ErrorSink,
+) -> ApexResult<Option<ValidatedDocument>> {
     let mut warnings = Vec::new();
 
-    // Rule 1: Exactly one TASK block
-    let task_count = doc.count_blocks(BlockKind::Task);
-    if task_count == 0 {
-        return Err(ApexError::missing_task());
+    // Rule 1: Exactly one TASK block
+    if doc.count_blocks(BlockKind::Task) == 0 {
+        errors.report(ApexError::missing_task())?;
+    }
+    for extra_task in doc.get_blocks(BlockKind::Task).iter().skip(1) {
+        errors.report(ApexError::multiple_tasks(extra_task.span.start_line))?;
+    }
+
+    if config.merge_duplicate_blocks {
+        merge_duplicate_blocks(&mut doc);
+    }
+
+    // Rule 1b: Optional blocks appear at most once (WHEN is repeatable)
+    for (idx, block) in doc.blocks.iter().enumerate() {
+        let repeatable = matches!(block.kind, BlockKind::Task | BlockKind::When | BlockKind::Custom(_));
+        if !repeatable && doc.blocks[..idx].iter().any(|b| b.kind == block.kind) {
+            errors.report(ApexError::duplicate_block(block.kind.as_str(), block.span.start_line))?;
+        }
+    }
+
+    // Rule 2: Required blocks cannot be empty
+    let task_block = doc.task().filter(|b| !b.is_empty());
+    if let Some(block) = doc.task().filter(|b| b.is_empty()) {
+        errors.report(ApexError::empty_block("TASK", Some(block.span.start_line)))?;
+    }
+
+    // Rule 3: Non-empty check for blocks that don't allow empty
+    for block in &doc.blocks {
+        if !block.kind.allows_empty() && block.is_empty() && block.kind != BlockKind::Task {
+            warnings.push(
+                Warning::new(WarningKind::EmptyBlock, format!("Empty {} block", block.kind))
+                    .with_line(block.span.start_line),
+            );
+        }
+    }
+
+    // Build validated views
+    let task = errors.recover(task_block.map(|b| parse_task_view(b, config, &mut warnings)).transpose())?;
+    let goals = errors.recover(doc.goals().map(parse_goals_view).transpose())?;
+    let plan = errors.recover(doc.plan().map(parse_plan_view).transpose())?;
+    let constraints = errors.recover(
+        doc.constraints()
+            .map(|b| parse_constraints_view_canonical(b, &mut warnings))
+            .transpose(),
+    )?;
+    let validation = errors.recover(doc.validation().map(parse_validation_view).transpose())?;
+    let tools = doc
+        .tools()
+        .map(|b| parse_tools_view_with_registry(b, mode, registry, &mut warnings, errors))
+        .transpose();
+    let tools = errors.recover(tools)?;
+    let diff = errors.recover(doc.diff().map(parse_diff_view).transpose())?;
+    let context = errors.recover(doc.context().map(parse_context_view).transpose())?;
+    let meta = errors.recover(doc.meta().map(parse_meta_view).transpose())?;
+    let mut when = Vec::new();
+    for block in doc.when_blocks().into_iter().filter(|b| !b.is_empty()) {
+        when.extend(errors.recover(parse_when_view(block, &mut warnings).map(Some))?);
+    }
+    let retry = errors.recover(doc.retry().map(parse_retry_view).transpose())?;
+
+    // Unknown META keys (often typos such as `verison`)
+    if let (ValidationMode::Strict, Some(block)) = (mode, doc.meta()) {
+        if let Err(err) = check_meta_keys(block, config, &mut warnings) {
+            errors.report(err)?;
+        }
+    }
+
+    // Scheduling hints: bad values are ignored with a warning in every mode
+    if let (Some(m), Some(block)) = (&meta, doc.meta()) {
+        check_schedule_hints(block, m, &mut warnings);
+    }
+
+    // v1.1 version enforcement
+    if mode == ValidationMode::Strict {
+        if let Some(ref m) = meta {
+            if let Some(version) = m.version() {
+                if !m.is_version_compatible() {
+                    let span = doc.meta().map(|b| b.span).unwrap_or_default();
+                    errors.report(ApexError::unsupported_version(version, span))?;
+                }
+            } else {
+                let mut warning = Warning::new(
+                    WarningKind::MissingVersion,
+                    "Missing version in META (v1.1 requires version=1.1)",
+                );
+                if let Some(block) = doc.meta() {
+                    warning = warning.with_line(block.span.start_line);
+                }
+                warnings.push(warning);
+            }
+        } else {
+            warnings.push(Warning::new(
+                WarningKind::MissingMeta,
+                "Missing META block (v1.1 requires version=1.1)",
+            ));
+        }
+    }
+
+    // Multi-goal tasks need an explicit PLAN (error in strict mode)
+    let goal_count = goals.as_ref().map_or(0, |g| g.goals.len());
+    if plan.is_none() && Semantics::plan_required_for(goal_count) {
+        let message = format!(
+            "TASK has {} goals but no PLAN; multi-goal tasks need an explicit plan to order the work",
+            goal_count
+        );
+        let line = doc.goals().map(|b| b.span.start_line).unwrap_or(1);
+        if mode == ValidationMode::Strict {
+            errors.report(ApexError::new(ApexErrorKind::ValidationFailure, message).with_line(line))?;
+        } else {
+            warnings.push(Warning::new(WarningKind::MissingPlan, message).with_line(line));
+        }
+    }
+
+    let Some(task) = task else {
+        return Ok(None);
+    };
+    let priority = meta.as_ref().and_then(MetaView::priority);
+    let deadline = meta.as_ref().and_then(MetaView::deadline);
+    let mut validated = ValidatedDocument {
+        doc,
+        task,
+        goals,
+        plan,
+        constraints,
+        validation,
+        tools,
+        diff,
+        context,
+        meta,
+        when,
+        retry,
+        meta_fixes: Vec::new(),
+        warnings,
+        validated_mode: mode,
+        registry_checked: registry.is_some(),
+        priority,
+        deadline,
+    };
+
+    for duplicate in validated.near_duplicate_warnings() {
+        let warning = Warning::new(WarningKind::NearDuplicate, duplicate.to_string());
+        validated.warnings.push(warning.with_line(duplicate.second_line));
+    }
+
+    // Tools invented inline in a PLAN step (`foo_bar(...)`)
+    let default_registry;
+    let inline_registry = match registry {
+        Some(registry) => registry,
+        None => {
+            default_registry = ToolRegistry::new();
+            &default_registry
+        }
+    };
+    let inline_calls = validated.unknown_inline_tool_calls(inline_registry);
+    validated
+        .warnings
+        .extend(inline_calls.into_iter().map(|m| Warning::new(WarningKind::UnknownTool, m)));
+
+    // PLAN steps contradicting a constraint (constraints always win)
+    for (idx, constraint) in validated.constraint_plan_conflicts() {
+        validated.warnings.push(Warning::new(
+            WarningKind::ConstraintConflict,
+            format!(
+                "PLAN step {} conflicts with constraint '{}'; CONSTRAINTS take precedence",
+                idx + 1,
+                constraint.as_str()
+            ),
+        ));
+    }
+
+    Ok(Some(validated))
+}
+
+/// Warn on `priority` and `deadline` values their accessors cannot parse
+fn check_schedule_hints(block: &Block, meta: &MetaView, warnings: &mut Vec<Warning>) {
+    let invalid = [
+        ("priority", meta.priority().is_none(), "a number from 0 to 255"),
+        ("deadline", meta.deadline().is_none(), "an RFC 3339 date or timestamp"),
+    ];
+    for (key, unparsed, expected) in invalid {
+        let Some(value) = meta.get(key).filter(|_| unparsed) else {
+            continue;
+        };
+        let message = format!("META {} '{}' is not {}; ignored", key, value, expected);
+        let mut warning = Warning::new(WarningKind::InvalidMetaValue, message);
+        // The last entry for a key is the one in effect
+        let line = meta_key_lines(block).filter(|(_, k)| canonical_meta_key(k) == key).last();
+        if let Some((line, _)) = line {
+            warning = warning.with_line(line);
+        }
+        warnings.push(warning);
+    }
+}
+
+/// Source line and raw key of each META entry, in document order
+fn meta_key_lines(block: &Block) -> impl Iterator<Item = (usize, &str)> {
+    block.lines.iter().enumerate().filter_map(|(idx, line)| {
+        let (key, _) = split_meta_line(line.trim())?;
+        Some((block.source_line(idx), key))
+    })
+}
+
+/// Warn (or fail, per config) on META keys outside the known set
+fn check_meta_keys(
+    block: &Block,
+    config: &ValidationConfig,
+    warnings: &mut Vec<Warning>,
+) -> ApexResult<()> {
+    let allowed: Vec<String> = KNOWN_META_KEYS
+        .iter()
+        .map(|k| k.to_string())
+        .chain(config.extra_meta_keys.iter().map(|k| canonical_meta_key(k)))
+        .collect();
+
+    // Report in document order
+    for (line, key) in meta_key_lines(block) {
+        let key = canonical_meta_key(key);
+        if allowed.contains(&key) {
+            continue;
+        }
+
+        let suggestion = allowed
+            .iter()
+            .map(|k| (levenshtein(&key, k), k))
+            .filter(|(dist, _)| *dist <= MAX_SUGGESTION_DISTANCE)
+            .min();
+        let message = match suggestion {
+            Some((_, k)) => format!("Unknown META key '{}', did you mean '{}'?", key, k),
+            None => format!("Unknown META key '{}'", key),
+        };
+        if config.reject_unknown_meta_keys {
+            return Err(ApexError::new(ApexErrorKind::ValidationFailure, message).with_line(line));
+        }
+        warnings.push(Warning::new(WarningKind::UnknownMetaKey, message).with_line(line));
+    }
+    Ok(())
+}
+
+/// Fold repeated optional blocks into their first occurrence
+fn merge_duplicate_blocks(doc: &mut ApexDocument) {
+    let mut merged: Vec<Block> = Vec::with_capacity(doc.blocks.len());
+
+    for mut block in doc.blocks.drain(..) {
+        let mergeable = !matches!(block.kind, BlockKind::Task | BlockKind::When | BlockKind::Custom(_));
+        match merged.iter_mut().find(|b| mergeable && b.kind == block.kind) {
+            Some(first) => {
+                // Only the first DIFF's format marker applies to the merged block
+                if block.kind == BlockKind::Diff {
+                    remove_diff_marker(&mut block);
+                }
+                let mut line_numbers = source_lines(first);
+                line_numbers.extend(source_lines(&block));
+                first.line_numbers = line_numbers;
+                first.lines.extend(block.lines);
+                first.span = first.span.merge(&block.span);
+            }
+            None => merged.push(block),
+        }
+    }
+
+    doc.blocks = merged;
+}
+
+/// Source line of every entry in `block.lines`
+fn source_lines(block: &Block) -> Vec<usize> {
+    (0..block.lines.len()).map(|idx| block.source_line(idx)).collect()
+}
+
+/// Drop a leading `unified`/`raw` marker line from a DIFF block
+fn remove_diff_marker(block: &mut Block) {
+    let Some(idx) = block.lines.iter().position(|l| !l.trim().is_empty()) else {
+        return;
+    };
+    if diff_format_marker(&block.lines[idx]).is_some() {
+        block.line_numbers = source_lines(block);
+        block.line_numbers.remove(idx);
+        block.lines.remove(idx);
+    }
+}
+
+/// Format named by a DIFF marker line (`unified` or `raw`, any case)
+fn diff_format_marker(line: &str) -> Option<DiffFormat> {
+    match line.trim().to_lowercase().as_str() {
+        "unified" => Some(DiffFormat::Unified),
+        "raw" => Some(DiffFormat::Raw),
+        _ => None,
+    }
+}
+
+// --- View Parsers ---
+
+fn parse_task_view(
+    block: &Block,
+    config: &ValidationConfig,
+    warnings: &mut Vec<Warning>,
+) -> ApexResult<TaskView> {
+    // TASK should be a single line; extra lines are joined unless configured otherwise
+    let lines = block.content_lines();
+    if lines.len() <= 1 {
+        return Ok(TaskView { line: block.content() });
+    }
+
+    let message = format!(
+        "TASK at line {} spans {} lines (expected a single line)",
+        block.span.start_line,
+        lines.len()
+    );
+    warnings.push(Warning::new(WarningKind::MultiLineTask, message).with_line(block.span.start_line));
+    if !config.strict_single_line_task {
+        return Ok(TaskView { line: block.content() });
     }
-    if task_count > 1 {
-        let second_task = doc.get_blocks(BlockKind::Task)[1];
-        return Err(ApexError::multiple_tasks(second_task.span.start_line));
+
+    for extra in &lines[1..] {
+        let message = format!("Dropped extra TASK line: {}", extra);
+        warnings.push(Warning::new(WarningKind::MultiLineTask, message).with_line(block.span.start_line));
     }
+    Ok(TaskView { line: lines[0].to_string() })
+}
 
-    // Rule 2: Required blocks cannot be empty
-    let task_block = doc.task().unwrap();
-    if task_block.is_empty() {
-        return Err(ApexError::empty_block("TASK", Some(task_block.span.start_line)));
+fn parse_goals_view(block: &Block) -> ApexResult<GoalsView> {
+    let goals = block.content_lines().iter().map(|s| s.to_string()).collect();
+    Ok(GoalsView { goals })
+}
+
+fn parse_plan_view(block: &Block) -> ApexResult<PlanView> {
+    let mut entries: Vec<PlanStep> = Vec::new();
+    for (idx, line) in block.lines.iter().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let indent: usize = line
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .map(|c| if c == '\t' { 2 } else { 1 })
+            .sum();
+        // A sub-step can sit at most one level below the previous step
+        let depth = (indent / 2).min(entries.last().map_or(0, |e| e.depth + 1));
+        let raw = line.trim();
+        entries.push(PlanStep {
+            description: strip_step_marker(raw).to_string(),
+            raw: raw.to_string(),
+            depth,
+            line: block.source_line(idx),
+        });
     }
 
-    // Rule 3: Non-empty check for blocks that don't allow empty
-    for block in &doc.blocks {
-        if !block.kind.allows_empty() && block.is_empty() && block.kind != BlockKind::Task {
-            warnings.push(format!("Empty {} block", block.kind));
-        }
+    Ok(PlanView { entries })
+}
+
+/// Split a trailing `[after: 1, 2]` dependency annotation from a step line
+///
+/// Bracketed suffixes that are not `after:` annotations are left in the description.
+pub(crate) fn split_after(line: &str) -> ApexResult<(String, Option<Vec<usize>>)> {
+    let trimmed = line.trim_end();
+    if !trimmed.ends_with(']') {
+        return Ok((line.to_string(), None));
     }
+    let Some(open_idx) = trimmed.rfind('[') else {
+        return Ok((line.to_string(), None));
+    };
 
-    // Build validated views
-    let task = parse_task_view(task_block)?;
-    let goals = doc.goals().map(parse_goals_view).transpose()?;
-    let plan = doc.plan().map(parse_plan_view).transpose()?;
-    let constraints = doc.constraints().map(|b| parse_constraints_view_canonical(b)).transpose()?;
-    let validation = doc.validation().map(parse_validation_view).transpose()?;
-    let tools = doc.tools().map(|b| parse_tools_view_with_registry(b, mode, registry, &mut warnings)).transpose()?;
-    let diff = doc.diff().map(parse_diff_view).transpose()?;
-    let context = doc.context().map(parse_context_view).transpose()?;
-    let meta = doc.meta().map(parse_meta_view).transpose()?;
+    let body = &trimmed[open_idx + 1..trimmed.len() - 1];
+    let Some((keyword, list)) = body.split_once(':') else {
+        return Ok((line.to_string(), None));
+    };
+    if !keyword.trim().eq_ignore_ascii_case("after") {
+        return Ok((line.to_string(), None));
+    }
 
-    // v1.1 version enforcement
-    if mode == ValidationMode::Strict {
-        if let Some(ref m) = meta {
-            if let Some(version) = m.version() {
-                if !m.is_version_compatible() {
-                    return Err(ApexError::new(
-                        crate::errors::ApexErrorKind::ValidationFailure,
-                        format!("Unsupported APEX version: {}", version),
-                    ));
-                }
-            } else {
-                warnings.push("Missing version in META (v1.1 requires version=1.1)".to_string());
-            }
-        } else {
-            warnings.push("Missing META block (v1.1 requires version=1.1)".to_string());
+    let mut deps = Vec::new();
+    for item in list.split(',') {
+        let item = item.trim().to_lowercase();
+        let num = item.strip_prefix("step").unwrap_or(&item).trim();
+        let dep = num.parse::<usize>().map_err(|_| {
+            ApexError::parse(format!("Invalid step dependency: [{}]", body.trim()), None)
+        })?;
+        if !deps.contains(&dep) {
+            deps.push(dep);
         }
     }
 
-    Ok(ValidatedDocument {
-        doc,
-        task,
-        goals,
-        plan,
-        constraints,
-        validation,
-        tools,
-        diff,
-        context,
-        meta,
-        meta_fixes: Vec::new(),
-        warnings,
-    })
+    Ok((trimmed[..open_idx].trim_end().to_string(), Some(deps)))
 }
 
-// --- View Parsers ---
+/// Strip leading enumeration markers (`1. `, `1) `, `Step 1: `, `- `, `* `)
+///
+/// Markers must be followed by whitespace (or, for `Step N`, a `:`), so text
+/// like `1.5x faster` is kept. A line that is only a marker is returned as is.
+pub(crate) fn strip_step_marker(line: &str) -> &str {
+    let mut rest = line.trim();
+    loop {
+        let stripped = strip_bullet(rest)
+            .or_else(|| strip_numbered(rest))
+            .or_else(|| strip_step_label(rest));
+        match stripped.map(str::trim_start) {
+            Some(next) if !next.is_empty() => rest = next,
+            _ => return rest,
+        }
+    }
+}
 
-fn parse_task_view(block: &Block) -> ApexResult<TaskView> {
-    // TASK should be a single line or joined as one
-    let content = block.content();
-    Ok(TaskView { line: content })
+fn strip_bullet(s: &str) -> Option<&str> {
+    let rest = s.strip_prefix(['-', '*', '+'])?;
+    rest.starts_with(char::is_whitespace).then_some(rest)
 }
 
-fn parse_goals_view(block: &Block) -> ApexResult<GoalsView> {
-    let goals = block.content_lines().iter().map(|s| s.to_string()).collect();
-    Ok(GoalsView { goals })
+/// `1. `, `1) ` or `1: `
+fn strip_numbered(s: &str) -> Option<&str> {
+    let digits = s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let rest = s[digits..].strip_prefix(['.', ')', ':'])?;
+    rest.starts_with(char::is_whitespace).then_some(rest)
 }
 
-fn parse_plan_view(block: &Block) -> ApexResult<PlanView> {
-    let steps = block.content_lines().iter().map(|s| s.to_string()).collect();
-    Ok(PlanView { steps })
+/// `Step 1:`, `Step 1.`, `Step 1)` or `Step 1 -` (case-insensitive)
+fn strip_step_label(s: &str) -> Option<&str> {
+    let head = s.get(..4)?;
+    if !head.eq_ignore_ascii_case("step") {
+        return None;
+    }
+    let rest = s[4..].trim_start();
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let rest = rest[digits..].trim_start();
+    rest.strip_prefix([':', '.', ')', '-'])
 }
 
+/// Parse constraints verbatim, without v1.1 canonicalization (v1.0)
+#[allow(dead_code)]
 fn parse_constraints_view(block: &Block) -> ApexResult<ConstraintsView> {
-    let rules = block.content_lines().iter().map(|s| s.to_string()).collect();
+    let rules = block
+        .content_lines()
+        .iter()
+        .map(|s| ConstraintRule {
+            canonical: s.to_string(),
+            display: s.to_string(),
+            severity: Severity::default(),
+        })
+        .collect();
     Ok(ConstraintsView { rules })
 }
 
 /// Parse constraints with v1.1 canonicalization
-fn parse_constraints_view_canonical(block: &Block) -> ApexResult<ConstraintsView> {
+///
+/// Lines with an empty canonical form (e.g. `!!!`) are dropped with a warning.
+fn parse_constraints_view_canonical(block: &Block, warnings: &mut Vec<Warning>) -> ApexResult<ConstraintsView> {
     let rules = block
         .content_lines()
         .iter()
-        .map(|s| canonicalize(s))
+        .filter_map(|s| {
+            let (severity, rule) = Severity::split_prefix(s);
+            let canonical = canonicalize_constraint(rule);
+            if canonical.is_empty() {
+                let message = format!("Constraint line '{}' produced empty canonical form", s);
+                warnings.push(Warning::new(WarningKind::EmptyConstraint, message));
+                return None;
+            }
+            let display = rule.trim().to_string();
+            Some(ConstraintRule {
+                canonical,
+                display,
+                severity,
+            })
+        })
         .collect();
     Ok(ConstraintsView { rules })
 }
@@ -292,6 +1782,8 @@ fn parse_validation_view(block: &Block) -> ApexResult<ValidationView> {
     Ok(ValidationView { conditions })
 }
 
+/// Parse tools without registry validation (v1.0)
+#[allow(dead_code)]
 fn parse_tools_view(block: &Block) -> ApexResult<ToolsView> {
     let mut tools = Vec::new();
 
@@ -308,11 +1800,13 @@ fn parse_tools_view_with_registry(
     block: &Block,
     mode: ValidationMode,
     registry: Option<&ToolRegistry>,
-    warnings: &mut Vec<String>,
+    warnings: &mut Vec<Warning>,
+    errors: &mut ErrorSink,
 ) -> ApexResult<ToolsView> {
     let mut tools = Vec::new();
 
-    for line in block.content_lines() {
+    for (line, span) in logical_lines(block) {
+        let line = line.as_str();
         let tool_name = extract_tool_name(line);
 
         // Validate against registry if provided
@@ -320,13 +1814,11 @@ fn parse_tools_view_with_registry(
             if !reg.is_valid(tool_name) {
                 match mode {
                     ValidationMode::Strict => {
-                        return Err(ApexError::new(
-                            crate::errors::ApexErrorKind::InvalidToolName,
-                            format!("Unknown tool '{}' not in registry", tool_name),
-                        ));
+                        errors.report(ApexError::unknown_tool(reg.unknown_tool_message(tool_name), span))?;
                     }
                     ValidationMode::Lenient => {
-                        warnings.push(format!("Unknown tool '{}' (tool_degraded)", tool_name));
+                        let message = format!("Unknown tool '{}' (tool_degraded)", tool_name);
+                        warnings.push(Warning::new(WarningKind::ToolDegraded, message).with_line(span.start_line));
                     }
                     ValidationMode::Legacy => {
                         // No validation in legacy mode
@@ -335,15 +1827,111 @@ fn parse_tools_view_with_registry(
             }
         }
 
-        let tool = parse_tool_declaration(line)?;
+        if tool_name.contains('.') && split_tool_namespace(tool_name).is_none() {
+            let message = format!("Malformed dotted tool name '{}': empty namespace or tool segment", tool_name);
+            if mode == ValidationMode::Strict {
+                errors.report(ApexError::new(ApexErrorKind::InvalidToolName, message).with_span(span))?;
+                continue;
+            }
+            warnings.push(Warning::new(WarningKind::MalformedToolName, message).with_line(span.start_line));
+        }
+
+        let balanced = balance_parens(line);
+        if balanced != line {
+            if mode == ValidationMode::Strict {
+                errors.report(
+                    ApexError::new(
+                        ApexErrorKind::InvalidToolName,
+                        format!("Unbalanced parentheses in tool declaration '{}'", line),
+                    )
+                    .with_span(span),
+                )?;
+            } else {
+                let message = format!("Balanced parentheses in tool declaration '{}' -> '{}'", line, balanced);
+                warnings.push(Warning::new(WarningKind::UnbalancedParens, message).with_line(span.start_line));
+            }
+        }
+
+        let mut tool = match parse_tool_declaration(&balanced) {
+            Ok(tool) => tool,
+            Err(err) => {
+                errors.report(err.with_span(span))?;
+                continue;
+            }
+        };
+        tool.raw = line.to_string();
+        tool.span = span;
         tools.push(tool);
     }
 
     Ok(ToolsView { tools })
 }
 
+/// Join physical lines continued with a trailing `\` into logical lines
+///
+/// Each logical line carries a span from its first to its last physical line.
+fn logical_lines(block: &Block) -> Vec<(String, Span)> {
+    let mut items = Vec::new();
+    let mut pending: Option<(String, Span)> = None;
+
+    for (idx, raw) in block.lines.iter().enumerate() {
+        let line_num = block.source_line(idx);
+        let trimmed = raw.trim();
+        if trimmed.is_empty() && pending.is_none() {
+            continue;
+        }
+
+        let (text, continues) = match trimmed.strip_suffix('\\') {
+            Some(head) => (head.trim_end(), true),
+            None => (trimmed, false),
+        };
+
+        let (mut joined, span) = match pending.take() {
+            Some((prev, prev_span)) => (prev, prev_span.merge(&Span::line(line_num))),
+            None => (String::new(), Span::line(line_num)),
+        };
+        if !joined.is_empty() && !text.is_empty() {
+            joined.push(' ');
+        }
+        joined.push_str(text);
+
+        if continues {
+            pending = Some((joined, span));
+        } else {
+            items.push((joined, span));
+        }
+    }
+
+    if let Some(item) = pending {
+        items.push(item);
+    }
+    items
+}
+
+/// Drop unmatched `)` and close unclosed `(` (double-quoted text is left alone)
+fn balance_parens(line: &str) -> String {
+    let mut balanced = String::with_capacity(line.len() + 1);
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+
+    for ch in line.chars() {
+        match (quote, ch) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') => quote = Some(ch),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => continue,
+            (None, ')') => depth -= 1,
+            _ => {}
+        }
+        balanced.push(ch);
+    }
+    balanced.extend(std::iter::repeat_n(')', depth));
+    balanced
+}
+
 fn parse_tool_declaration(line: &str) -> ApexResult<ToolDeclaration> {
-    // Format: tool_name or tool_name(args)
+    // Format: tool_name or tool_name(args); the name may be dotted
     let trimmed = line.trim();
 
     if let Some(paren_idx) = trimmed.find('(') {
@@ -362,6 +1950,7 @@ fn parse_tool_declaration(line: &str) -> ApexResult<ToolDeclaration> {
             name,
             arguments: args,
             raw: line.to_string(),
+            span: Span::default(),
         })
     } else {
         // No arguments
@@ -369,60 +1958,120 @@ fn parse_tool_declaration(line: &str) -> ApexResult<ToolDeclaration> {
             name: trimmed.to_string(),
             arguments: None,
             raw: line.to_string(),
+            span: Span::default(),
         })
     }
 }
 
 fn parse_diff_view(block: &Block) -> ApexResult<DiffView> {
-    let lines: Vec<&str> = block.content_lines();
-
-    if lines.is_empty() {
+    // Keep change lines verbatim: whitespace and inner blank lines matter
+    let lines = block.raw_lines();
+    let Some(first) = lines.first() else {
         return Ok(DiffView {
             format: DiffFormat::Unspecified,
             changes: Vec::new(),
         });
-    }
-
-    // Check first line for format marker (v1.1)
-    let first_line = lines[0].to_lowercase();
-    let (format, skip_first) = match first_line.as_str() {
-        "unified" => (DiffFormat::Unified, true),
-        "raw" => (DiffFormat::Raw, true),
-        _ => (DiffFormat::Unspecified, false),
-    };
-
-    let changes = if skip_first {
-        lines[1..].iter().map(|s| s.to_string()).collect()
-    } else {
-        lines.iter().map(|s| s.to_string()).collect()
     };
 
+    // Check first line for format marker (v1.1)
+    let marker = diff_format_marker(first);
+    let changes = lines[usize::from(marker.is_some())..].to_vec();
+    let format = marker.unwrap_or(DiffFormat::Unspecified);
     Ok(DiffView { format, changes })
 }
 
 fn parse_context_view(block: &Block) -> ApexResult<ContextView> {
-    let lines = block.content_lines().iter().map(|s| s.to_string()).collect();
-    Ok(ContextView { lines })
+    Ok(ContextView {
+        lines: block.raw_lines().to_vec(),
+    })
+}
+
+fn parse_when_view(block: &Block, warnings: &mut Vec<Warning>) -> ApexResult<WhenView> {
+    let lines = block.content_lines();
+    let condition = lines[0].to_string();
+    let steps: Vec<String> = lines[1..].iter().map(|s| s.to_string()).collect();
+
+    if steps.is_empty() {
+        let message = format!(
+            "WHEN block at line {} has no steps for condition '{}'",
+            block.span.start_line, condition
+        );
+        warnings.push(Warning::new(WarningKind::EmptyBlock, message).with_line(block.span.start_line));
+    }
+
+    Ok(WhenView { condition, steps })
+}
+
+fn parse_retry_view(block: &Block) -> ApexResult<RetryView> {
+    let mut policies = Vec::new();
+
+    for (idx, raw) in block.lines.iter().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let line_num = block.source_line(idx);
+        let malformed = || {
+            ApexError::parse(
+                format!("Invalid RETRY policy '{}' (expected 'step N: M retries' or 'all: M retries')", line),
+                Some(line_num),
+            )
+        };
+
+        let (target_str, policy_str) = line.split_once(':').ok_or_else(malformed)?;
+        let target_str = target_str.trim().to_lowercase();
+        let target = if target_str == "all" {
+            RetryTarget::All
+        } else {
+            let num = target_str.strip_prefix("step").ok_or_else(malformed)?;
+            RetryTarget::Step(num.trim().parse().map_err(|_| malformed())?)
+        };
+
+        let (count_str, backoff_str) = match policy_str.split_once(',') {
+            Some((count, backoff)) => (count, Some(backoff)),
+            None => (policy_str, None),
+        };
+        let max_retries = count_str
+            .split_whitespace()
+            .next()
+            .and_then(|n| n.parse::<u32>().ok())
+            .ok_or_else(malformed)?;
+        let backoff = backoff_str
+            .map(|b| b.trim())
+            .map(|b| b.strip_prefix("backoff").unwrap_or(b))
+            .map(|b| b.trim_start_matches([' ', '=', ':']).trim().to_string())
+            .filter(|b| !b.is_empty());
+
+        policies.push(RetryPolicy {
+            target,
+            max_retries,
+            backoff,
+        });
+    }
+
+    Ok(RetryView { policies })
 }
 
 fn parse_meta_view(block: &Block) -> ApexResult<MetaView> {
-    let mut entries = HashMap::new();
+    let mut meta = MetaView::default();
 
     for line in block.content_lines() {
-        // Format: key=value or key: value
-        if let Some(eq_idx) = line.find('=') {
-            let key = line[..eq_idx].trim().to_string();
-            let value = line[eq_idx + 1..].trim().to_string();
-            entries.insert(key, value);
-        } else if let Some(colon_idx) = line.find(':') {
-            let key = line[..colon_idx].trim().to_string();
-            let value = line[colon_idx + 1..].trim().to_string();
-            entries.insert(key, value);
-        }
         // Skip lines that don't match key=value or key: value format
+        let Some((key, value)) = split_meta_line(line) else {
+            continue;
+        };
+        let canonical = canonical_meta_key(key);
+        meta.entries.insert(canonical.clone(), value.to_string());
+        meta.original_keys.insert(canonical, key.to_string());
     }
 
-    Ok(MetaView { entries })
+    Ok(meta)
+}
+
+/// Split a `key=value` or `key: value` META line (`=` takes priority)
+pub(crate) fn split_meta_line(line: &str) -> Option<(&str, &str)> {
+    let idx = line.find('=').or_else(|| line.find(':'))?;
+    Some((line[..idx].trim(), line[idx + 1..].trim()))
 }
 
 #[cfg(test)]
@@ -456,6 +2105,319 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("MultipleTasks"));
     }
 
+    #[test]
+    fn test_duplicate_optional_block() {
+        let doc = parse_str("TASK\nDo it\nPLAN\nStep 1\nGOALS\nDone\nPLAN\nStep 2").unwrap();
+        let err = validate(doc).unwrap_err();
+
+        assert_eq!(err.kind, ApexErrorKind::DuplicateBlock);
+        assert_eq!(err.line, Some(7));
+        assert!(err.message.contains("PLAN"));
+
+        // WHEN may repeat
+        let doc = parse_str("TASK\nDo it\nWHEN\na\nStep\nWHEN\nb\nStep").unwrap();
+        assert!(validate(doc).is_ok());
+    }
+
+    #[test]
+    fn test_merge_duplicate_blocks() {
+        let input = "TASK\nDo it\nPLAN\nStep 1\nMETA\nauthor=a\nversion=1.1\nPLAN\nStep 2\nMETA\nauthor=b";
+        let config = ValidationConfig {
+            merge_duplicate_blocks: true,
+            ..ValidationConfig::default()
+        };
+        let validated =
+            validate_with_config(parse_str(input).unwrap(), ValidationMode::Strict, None, &config)
+                .unwrap();
+
+        assert_eq!(validated.plan.unwrap().steps(), vec!["Step 1", "Step 2"]);
+        let meta = validated.meta.unwrap();
+        assert_eq!(meta.entries.get("author").map(String::as_str), Some("b"));
+        assert_eq!(meta.version(), Some("1.1"));
+        assert_eq!(validated.doc.count_blocks(BlockKind::Plan), 1);
+
+        // Merged lines keep their source lines; later DIFF markers are dropped
+        let input = "TASK\nDo it\nTOOLS\nbash\nDIFF\nunified\n+a\nMETA\nversion=1.1\nDIFF\nunified\n+b\nTOOLS\ntool(a,b";
+        let err = validate_with_config(parse_str(input).unwrap(), ValidationMode::Strict, None, &config).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InvalidToolName);
+        assert_eq!(err.line, Some(14));
+
+        let input = input.replace("tool(a,b", "read_file");
+        let validated = validate_with_config(parse_str(&input).unwrap(), ValidationMode::Strict, None, &config).unwrap();
+        let diff = validated.diff.unwrap();
+        assert_eq!(diff.format, DiffFormat::Unified);
+        assert_eq!(diff.changes, vec!["+a", "+b"]);
+        assert_eq!(validated.doc.diff().unwrap().source_line(2), 12);
+
+        // TASK is never merged
+        let err = validate_with_config(
+            parse_str("TASK\nOne\nTASK\nTwo").unwrap(),
+            ValidationMode::Legacy,
+            None,
+            &config,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::MultipleTasks);
+    }
+
+    #[test]
+    fn test_multi_line_task() {
+        let input = "TASK\nRefactor auth\nand also rewrite the docs\nPLAN\nStep 1";
+        let validated = validate(parse_str(input).unwrap()).unwrap();
+        assert_eq!(validated.task.line, "Refactor auth\nand also rewrite the docs");
+        assert_eq!(validated.warning_messages(), vec!["TASK at line 1 spans 2 lines (expected a single line)"]);
+        assert_eq!(validated.warnings[0].kind, WarningKind::MultiLineTask);
+
+        let config = ValidationConfig {
+            strict_single_line_task: true,
+            ..ValidationConfig::default()
+        };
+        let validated =
+            validate_with_config(parse_str(input).unwrap(), ValidationMode::Legacy, None, &config)
+                .unwrap();
+        assert_eq!(validated.task.line, "Refactor auth");
+        assert!(validated
+            .warning_messages()
+            .contains(&"Dropped extra TASK line: and also rewrite the docs".to_string()));
+    }
+
+    #[test]
+    fn test_context_file_references() {
+        let root = std::env::temp_dir().join(format!("apex_context_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "pub fn f() {}\n").unwrap();
+
+        let doc = parse_str("TASK\nDo it\nCONTEXT\nSee below\n@src/lib.rs\n@ missing.rs").unwrap();
+        let context = validate(doc).unwrap().context.unwrap();
+        assert_eq!(context.file_references(), vec!["src/lib.rs", "missing.rs"]);
+
+        let err = context.resolve_context(&root).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::Io);
+        assert!(err.message.contains("missing.rs"));
+
+        for escaping in ["@../secret.txt", "@/etc/passwd", "@src/../../secret.txt"] {
+            let doc = parse_str(&format!("TASK\nDo it\nCONTEXT\n{}", escaping)).unwrap();
+            let err = validate(doc).unwrap().context.unwrap().resolve_context(&root).unwrap_err();
+            assert_eq!(err.kind, ApexErrorKind::ValidationFailure, "{}", escaping);
+        }
+
+        std::fs::write(root.join("missing.rs"), "").unwrap();
+        let resolved = context.resolve_context(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(resolved[0], ("src/lib.rs".to_string(), "pub fn f() {}\n".to_string()));
+        assert_eq!(resolved[1].0, "missing.rs");
+    }
+
+    #[test]
+    fn test_multi_goal_requires_plan() {
+        let input = "TASK\nX\nGOALS\nFast\nCorrect\nMETA\nversion=1.1";
+
+        let err = validate_with_mode(parse_str(input).unwrap(), ValidationMode::Strict, None).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::ValidationFailure);
+        assert_eq!(err.line, Some(3));
+        assert!(err.message.contains("2 goals but no PLAN"));
+
+        let lenient = validate_with_mode(parse_str(input).unwrap(), ValidationMode::Lenient, None).unwrap();
+        assert!(lenient.warnings.iter().any(|w| w.kind == WarningKind::MissingPlan && w.line == Some(3)));
+
+        // One goal, or a PLAN present, is fine
+        let single = "TASK\nX\nGOALS\nFast\nMETA\nversion=1.1";
+        assert!(validate_with_mode(parse_str(single).unwrap(), ValidationMode::Strict, None).is_ok());
+        let planned = "TASK\nX\nGOALS\nFast\nCorrect\nPLAN\nDo it\nMETA\nversion=1.1";
+        assert!(validate_with_mode(parse_str(planned).unwrap(), ValidationMode::Strict, None).is_ok());
+    }
+
+    #[test]
+    fn test_goal_coverage() {
+        let input = "TASK\nX\nGOALS\nAll tests pass\nLatency under 50ms\nDocs updated\nVALIDATION\ncargo test passes\ncommand: ./bench.sh latency";
+        let validated = validate(parse_str(input).unwrap()).unwrap();
+
+        assert_eq!(
+            validated.goal_coverage(),
+            vec![
+                ("All tests pass".to_string(), true),
+                ("Latency under 50ms".to_string(), true),
+                ("Docs updated".to_string(), false),
+            ]
+        );
+
+        let no_validation = validate(parse_str("TASK\nX\nGOALS\nShip it").unwrap()).unwrap();
+        assert_eq!(no_validation.goal_coverage(), vec![("Ship it".to_string(), false)]);
+    }
+
+    #[test]
+    fn test_near_duplicate_warnings() {
+        let input = "TASK\nX\nGOALS\nImprove recall\nFaster builds\n\nimprove recall.\nCONSTRAINTS\nNo Mocks\nreal_dbs\nSHOULD: no_mocks";
+        let validated = validate(parse_str(input).unwrap()).unwrap();
+
+        let duplicates = validated.near_duplicate_warnings();
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(
+            duplicates[0],
+            NearDuplicate {
+                kind: BlockKind::Goals,
+                first_line: 4,
+                first: "Improve recall".to_string(),
+                second_line: 7,
+                second: "improve recall.".to_string(),
+            }
+        );
+        assert_eq!((duplicates[1].first_line, duplicates[1].second_line), (9, 11));
+        assert!(validated
+            .warning_messages()
+            .contains(&"GOALS line 7 'improve recall.' duplicates line 4 'Improve recall'".to_string()));
+
+        let distinct = validate(parse_str("TASK\nX\nGOALS\nImprove recall\nImprove precision").unwrap()).unwrap();
+        assert!(distinct.near_duplicate_warnings().is_empty());
+
+        // Skipped comment lines do not shift the reported lines
+        let config = crate::parser::ParserConfig::tolerant();
+        let commented = "TASK\nX\nGOALS\n# targets\nImprove recall\n# again\nimprove recall.";
+        let doc = crate::parser::parse_str_with_config(commented, &config).unwrap().document;
+        let duplicates = validate(doc).unwrap().near_duplicate_warnings();
+        assert_eq!((duplicates[0].first_line, duplicates[0].second_line), (5, 7));
+    }
+
+    #[test]
+    fn test_empty_canonical_constraints() {
+        let input = "TASK\nX\nCONSTRAINTS\nno_mocks\n!!!\n   \nSHOULD: ---\nreal_dbs";
+        let validated = validate(parse_str(input).unwrap()).unwrap();
+
+        let constraints = validated.constraints.as_ref().unwrap();
+        assert_eq!(constraints.canonical_rules(), vec!["no_mocks", "real_dbs"]);
+        // Whitespace-only lines are blank separators, not constraints
+        assert_eq!(
+            validated.warning_messages(),
+            vec![
+                "Constraint line '!!!' produced empty canonical form",
+                "Constraint line 'SHOULD: ---' produced empty canonical form",
+            ]
+        );
+        assert!(Semantics::from_validated(&validated).custom_constraints().is_empty());
+    }
+
+    #[test]
+    fn test_constraint_plan_conflicts() {
+        let input = "TASK\nX\nPLAN\nAdd mock for the DB\nStub the mailer\nRun tests\nSwap to mock DBs\nCONSTRAINTS\nno_mocks\nno_stubs\nreal_dbs";
+        let validated = validate(parse_str(input).unwrap()).unwrap();
+
+        assert_eq!(
+            validated.constraint_plan_conflicts(),
+            vec![
+                (0, Constraint::NoMocks),
+                (1, Constraint::NoStubs),
+                (3, Constraint::NoMocks),
+                (3, Constraint::RealDbsOnly),
+            ]
+        );
+        assert!(validated
+            .warning_messages()
+            .contains(&"PLAN step 1 conflicts with constraint 'no_mocks'; CONSTRAINTS take precedence".to_string()));
+
+        let clean = validate(parse_str("TASK\nX\nPLAN\nAdd mock\nCONSTRAINTS\nlt_50_loc").unwrap()).unwrap();
+        assert!(clean.constraint_plan_conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_context_budget() {
+        let doc = parse_str("TASK\nDo it\nCONTEXT\nabcdef\nnaïve").unwrap();
+        let validated = validate(doc).unwrap();
+        let context = validated.context.as_ref().unwrap();
+
+        // "abcdef\nnaïve": 12 chars, 13 bytes
+        assert_eq!(context.byte_len(), 13);
+        assert_eq!(context.approx_tokens(), 3);
+        assert!(validated.context_fits(3));
+        assert!(!validated.context_fits(2));
+
+        let no_context = validate(parse_str("TASK\nDo it").unwrap()).unwrap();
+        assert!(no_context.context_fits(0));
+    }
+
+    #[test]
+    fn test_plan_item_tree() {
+        // Tabs and spaces in one PLAN need tolerant mode (tab = two spaces)
+        let input = "TASK\nDo it\nPLAN\nPrepare\n  Fetch deps\n  Configure\n      Set flags\nBuild\n\tLink";
+        let parsed = crate::parser::parse_str_with_mode(input, crate::parser::ParseMode::Tolerant).unwrap();
+        let plan = validate(parsed.document).unwrap().plan.unwrap();
+
+        assert_eq!(plan.steps(), vec!["Prepare", "Fetch deps", "Configure", "Set flags", "Build", "Link"]);
+        assert_eq!(plan.items().len(), 2);
+        assert_eq!(plan.items()[0].children.len(), 2);
+        // Over-indentation is clamped to one level below the parent
+        assert_eq!(plan.items()[0].children[1].children[0].text, "Set flags");
+        assert_eq!(plan.items()[0].children[1].children[0].depth, 2);
+        assert_eq!(plan.items()[1].children[0].text, "Link");
+        assert_eq!(
+            plan.parent_indices(),
+            vec![None, Some(0), Some(0), Some(2), None, Some(4)]
+        );
+    }
+
+    #[test]
+    fn test_raw_diff_and_context_content() {
+        let input = "TASK\nDo it\nDIFF\nunified\n--- a/f.rs\n+++ b/f.rs\n@@ -1,3 +1,3 @@\n-a\n \n+b  \n c\n\nCONTEXT\n  fn main() {\n\n  }\n\nMETA\nversion=1.1";
+        let doc = parse_str(input).unwrap();
+        assert_eq!(doc.context().unwrap().raw_content(), "  fn main() {\n\n  }");
+
+        let validated = validate(doc).unwrap();
+        let diff = validated.diff.as_ref().unwrap();
+        assert_eq!(diff.changes, vec!["--- a/f.rs", "+++ b/f.rs", "@@ -1,3 +1,3 @@", "-a", " ", "+b  ", " c"]);
+
+        let hunk = &diff.parse_unified().unwrap().files[0].hunks[0];
+        assert_eq!(hunk.lines[1], DiffLine::Context(String::new()));
+        assert_eq!(hunk.lines[2], DiffLine::Added("b  ".to_string()));
+
+        let context = validated.context.as_ref().unwrap();
+        assert_eq!(context.lines, vec!["  fn main() {", "", "  }"]);
+    }
+
+    #[test]
+    fn test_diff_scope() {
+        let input = "TASK\nDo it\nCONTEXT\n@docs/README.md\nDIFF\nunified\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-a\n+b\n--- a/docs/README.md\n+++ b/docs/README.md\n--- /dev/null\n+++ b/scripts/deploy.sh\n--- a/src/../../etc/passwd\n+++ b/src/../../etc/passwd";
+        let validated = validate(parse_str(input).unwrap()).unwrap();
+
+        let warnings = validated.check_diff_scope(&[Path::new("src")]);
+        assert_eq!(
+            warnings,
+            vec![
+                "DIFF touches 'scripts/deploy.sh' outside the allowed scope",
+                "DIFF touches 'src/../../etc/passwd' outside the allowed scope",
+            ]
+        );
+
+        let raw = validate(parse_str("TASK\nDo it\nDIFF\nraw\n/etc/passwd: +1").unwrap()).unwrap();
+        assert!(raw.check_diff_scope(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_validation_conditions() {
+        let input = "TASK\nDo it\nVALIDATION\ncommand: cargo test --all\nmetric: latency < 100ms\nMetric: error rate <= 0.5 %\nmetric: throughput improved\nLatency reduced by 50%";
+        let view = validate(parse_str(input).unwrap()).unwrap().validation.unwrap();
+
+        assert_eq!(
+            view.parse_conditions(),
+            vec![
+                ValidationCondition::Command("cargo test --all".to_string()),
+                ValidationCondition::Metric {
+                    name: "latency".to_string(),
+                    comparator: Comparator::Lt,
+                    value: 100.0,
+                    unit: "ms".to_string(),
+                },
+                ValidationCondition::Metric {
+                    name: "error rate".to_string(),
+                    comparator: Comparator::Le,
+                    value: 0.5,
+                    unit: "%".to_string(),
+                },
+                ValidationCondition::FreeText("metric: throughput improved".to_string()),
+                ValidationCondition::FreeText("Latency reduced by 50%".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn test_empty_task() {
         let doc = parse_str("TASK\n\nPLAN\nStep 1").unwrap();
@@ -478,6 +2440,189 @@ mod tests {
         assert_eq!(tools.tools[2].arguments, None);
     }
 
+    #[test]
+    fn test_plan_step_markers() {
+        let input = "TASK\nX\nPLAN\n1. Read code\n2) Edit code\nStep 3: Run tests\n- Deploy\n  * Notify team\n1.5x faster builds\n4.";
+        let plan = validate(parse_str(input).unwrap()).unwrap().plan.unwrap();
+
+        assert_eq!(
+            plan.steps(),
+            vec!["Read code", "Edit code", "Run tests", "Deploy", "Notify team", "1.5x faster builds", "4."]
+        );
+        assert_eq!(plan.entries[2].raw, "Step 3: Run tests");
+        assert_eq!(plan.entries[2].description, "Run tests");
+        assert_eq!(plan.items()[3].children[0].text, "Notify team");
+
+        assert_eq!(strip_step_marker("step 12 - Ship it"), "Ship it");
+        assert_eq!(strip_step_marker("- 1. Nested marker"), "Nested marker");
+        assert_eq!(strip_step_marker("Stepwise refinement"), "Stepwise refinement");
+    }
+
+    #[test]
+    fn test_unbalanced_tool_parens() {
+        let tools_doc = |decl: &str| parse_str(&format!("TASK\nDo it\nMETA\nversion=1.1\nTOOLS\n{}", decl)).unwrap();
+
+        for decl in ["tool(", "tool(a,b", "tool(a))"] {
+            let err = validate_with_mode(tools_doc(decl), ValidationMode::Strict, None).unwrap_err();
+            assert_eq!(err.kind, ApexErrorKind::InvalidToolName);
+            assert_eq!(err.line, Some(6));
+        }
+
+        let nested = validate_with_mode(tools_doc("tool(f(x))"), ValidationMode::Strict, None).unwrap();
+        assert_eq!(nested.tools.unwrap().tools[0].arguments, Some("f(x)".to_string()));
+
+        let fixed = validate_with_mode(tools_doc("tool(a,b"), ValidationMode::Lenient, None).unwrap();
+        assert!(fixed.warnings.iter().any(|w| w.message.contains("'tool(a,b' -> 'tool(a,b)'")));
+        let tool = &fixed.tools.unwrap().tools[0];
+        assert_eq!(tool.arguments, Some("a,b".to_string()));
+        assert_eq!(tool.raw, "tool(a,b");
+
+        let empty = validate_with_mode(tools_doc("tool("), ValidationMode::Lenient, None).unwrap();
+        assert_eq!(empty.tools.unwrap().tools[0].arguments, Some(String::new()));
+
+        let inner = validate_with_mode(tools_doc("tool(f(x)"), ValidationMode::Lenient, None).unwrap();
+        assert_eq!(inner.tools.unwrap().tools[0].arguments, Some("f(x)".to_string()));
+    }
+
+    #[test]
+    fn test_continued_tool_declaration() {
+        let input = "TASK\nDo it\nTOOLS\ncode_search(query, \\\n    limit)\nread_file(path)";
+        let validated = validate(parse_str(input).unwrap()).unwrap();
+
+        let tools = validated.tools.unwrap().tools;
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0].name, "code_search");
+        assert_eq!(tools[0].arguments, Some("query, limit".to_string()));
+        assert_eq!(tools[0].span, Span::new(4, 5));
+        assert_eq!(tools[1].span, Span::line(6));
+    }
+
+    #[test]
+    fn test_validate_collecting() {
+        let registry = ToolRegistry::new();
+        let input = "TASK\n\nTASK\nSecond\nTOOLS\nfake_tool\ncode_search\nother_fake(x";
+        let (validated, errors) = validate_collecting(parse_str(input).unwrap(), ValidationMode::Strict, Some(&registry));
+
+        assert!(validated.is_none());
+        let kinds: Vec<ApexErrorKind> = errors.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ApexErrorKind::MultipleTasks,
+                ApexErrorKind::EmptyRequiredBlock,
+                ApexErrorKind::InvalidToolName,
+                ApexErrorKind::InvalidToolName,
+                ApexErrorKind::InvalidToolName,
+            ]
+        );
+        assert_eq!(errors[2].line, Some(6));
+        assert!(errors[4].message.contains("Unbalanced parentheses"));
+
+        // Fail-fast validation reports the first of them
+        let err = validate_with_mode(parse_str(input).unwrap(), ValidationMode::Strict, Some(&registry)).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::MultipleTasks);
+    }
+
+    #[test]
+    fn test_validate_collecting_recovers() {
+        let registry = ToolRegistry::new();
+        let input = "TASK\nDo it\nGOALS\nFast\nGOALS\nSafe\nTOOLS\nfake_tool\nMETA\nversion=9.0";
+        let (validated, errors) = validate_collecting(parse_str(input).unwrap(), ValidationMode::Strict, Some(&registry));
+
+        let validated = validated.unwrap();
+        assert_eq!(validated.goals.unwrap().goals, vec!["Fast"]);
+        assert_eq!(validated.tools.unwrap().tools[0].name, "fake_tool");
+        let kinds: Vec<ApexErrorKind> = errors.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![ApexErrorKind::DuplicateBlock, ApexErrorKind::InvalidToolName, ApexErrorKind::ValidationFailure]
+        );
+
+        let clean = parse_str("TASK\nDo it\nMETA\nversion=1.1").unwrap();
+        let (validated, errors) = validate_collecting(clean, ValidationMode::Strict, None);
+        assert!(validated.is_some());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_continued_tool_error_span() {
+        let input = "TASK\nDo it\nTOOLS\nfake_tool(query, \\\n    limit)";
+        let registry = ToolRegistry::new();
+        let err = validate_with_mode(parse_str(input).unwrap(), ValidationMode::Strict, Some(&registry))
+            .unwrap_err();
+
+        assert_eq!(err.line, Some(4));
+        assert_eq!(err.span(), Some(Span::new(4, 5)));
+    }
+
+    #[test]
+    fn test_validated_mode_recorded() {
+        let input = "TASK\nDo it\nMETA\nversion=1.1";
+        let legacy = validate(parse_str(input).unwrap()).unwrap();
+        assert_eq!(legacy.validated_mode, ValidationMode::Legacy);
+        assert!(!legacy.registry_checked);
+
+        let registry = ToolRegistry::new();
+        let strict = validate_with_mode(parse_str(input).unwrap(), ValidationMode::Strict, Some(&registry)).unwrap();
+        assert_eq!(strict.validated_mode, ValidationMode::Strict);
+        assert!(strict.registry_checked);
+    }
+
+    #[test]
+    fn test_when_blocks() {
+        let input = "TASK\nMigrate\nPLAN\nDiff schema\nWHEN\nschema changed\nRun migration\nVerify schema\nWHEN\ntests fail";
+        let validated = validate(parse_str(input).unwrap()).unwrap();
+
+        assert_eq!(validated.when.len(), 2);
+        assert_eq!(validated.when[0].condition, "schema changed");
+        assert_eq!(validated.when[0].steps, vec!["Run migration", "Verify schema"]);
+        assert!(validated.when[1].steps.is_empty());
+        assert_eq!(validated.warnings_of_kind(WarningKind::EmptyBlock).count(), 1);
+    }
+
+    #[test]
+    fn test_retry_block() {
+        let input = "TASK\nDeploy\nRETRY\nall: 1 retry\nstep 3: 5 retries, backoff exponential\nStep 4: 2 retries, backoff=2s";
+        let validated = validate(parse_str(input).unwrap()).unwrap();
+        let retry = validated.retry.unwrap();
+
+        assert_eq!(retry.policies.len(), 3);
+        assert_eq!(retry.policies[0].target, RetryTarget::All);
+        assert_eq!(retry.policies[1].target, RetryTarget::Step(3));
+        assert_eq!(retry.policies[1].backoff.as_deref(), Some("exponential"));
+        assert_eq!(retry.policies[2].backoff.as_deref(), Some("2s"));
+
+        assert_eq!(retry.max_retries_for(3), 5);
+        assert_eq!(retry.max_retries_for(1), 1);
+    }
+
+    #[test]
+    fn test_retry_block_malformed() {
+        let err = validate(parse_str("TASK\nDeploy\nRETRY\nstep x: 2 retries").unwrap()).unwrap_err();
+        assert_eq!(err.line, Some(4));
+        assert!(validate(parse_str("TASK\nDeploy\nRETRY\nstep 2: many").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_error_lines_after_skipped_comments() {
+        use crate::parser::{parse_str_with_config, ParserConfig};
+        let parse = |input: &str| parse_str_with_config(input, &ParserConfig::tolerant()).unwrap().document;
+
+        let doc = parse("TASK\nDeploy\nTOOLS\n# allowed tools\ntool(a,b\nMETA\nversion=1.1");
+        assert_eq!(doc.tools().unwrap().source_line(0), 5);
+        let err = validate_with_mode(doc, ValidationMode::Strict, None).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InvalidToolName);
+        assert_eq!(err.line, Some(5));
+
+        let err = validate(parse("TASK\nDeploy\nRETRY\n# per step\nstep x: 2 retries")).unwrap_err();
+        assert_eq!(err.line, Some(5));
+
+        // Leading TASK blanks dropped in tolerant mode keep their source lines too
+        let doc = parse("TASK\n\nDeploy\n# note\nnow");
+        let task = doc.task().unwrap();
+        assert_eq!((task.source_line(0), task.source_line(1)), (3, 5));
+    }
+
     #[test]
     fn test_meta_parsing() {
         let doc = parse_str("TASK\nDo it\nMETA\nversion=1.0\nauthor: Feanor\nformat = apex").unwrap();
@@ -488,4 +2633,196 @@ mod tests {
         assert_eq!(meta.entries.get("author"), Some(&"Feanor".to_string()));
         assert_eq!(meta.entries.get("format"), Some(&"apex".to_string()));
     }
+
+    #[test]
+    fn test_meta_typed_accessors() {
+        let doc = parse_str(
+            "TASK\nDo it\nMETA\nauthor=Feanor\ncreated_at: 2024-05-01T10:00:00Z\npriority=3\nweight=heavy",
+        )
+        .unwrap();
+        let meta = validate(doc).unwrap().meta.unwrap();
+
+        assert_eq!(meta.author(), Some("Feanor"));
+        assert_eq!(meta.created_at(), Some("2024-05-01T10:00:00Z"));
+        assert_eq!(meta.get_parsed::<u8>("priority"), Some(3));
+        assert_eq!(meta.get_parsed::<u8>("weight"), None);
+        assert_eq!(meta.get_parsed::<u8>("missing"), None);
+    }
+
+    #[test]
+    fn test_timestamp_parse() {
+        let ts = |s: &str| Timestamp::parse(s).map(|t| t.unix_seconds);
+        assert_eq!(ts("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(ts("2024-05-01T10:00:00Z"), Some(1_714_557_600));
+        assert_eq!(ts("2024-05-01 12:00+02:00"), Some(1_714_557_600));
+        assert_eq!(ts("2024-05-01T05:30:00.250-04:30"), Some(1_714_557_600));
+        assert_eq!(ts("2024-05-01"), ts("2024-05-01T23:59:59Z"));
+        assert_eq!(ts("2024-02-29"), ts("2024-03-01T00:00:00Z").map(|t| t - 1));
+
+        for bad in ["tomorrow", "2023-02-29", "2024-13-01", "2024-5-1", "2024-05-01T25:00Z", "2024-05-01T10:00+2"] {
+            assert_eq!(ts(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_schedule_hints() {
+        let input = "TASK\nShip\nMETA\nversion=1.1\npriority=7\ndeadline=2024-05-01T10:00:00+02:00";
+        let validated = validate_with_mode(parse_str(input).unwrap(), ValidationMode::Strict, None).unwrap();
+        assert_eq!(validated.priority, Some(7));
+        assert_eq!(validated.deadline, Timestamp::parse("2024-05-01T08:00:00Z"));
+        assert!(validated.warnings.is_empty());
+
+        assert!(!validated.is_overdue("2024-05-01T08:00:00Z"));
+        assert!(validated.is_overdue("2024-05-01T09:00:00+00:30"));
+        assert!(validated.is_overdue("2024-05-02"));
+        assert!(!validated.is_overdue("not a date"));
+
+        let input = "TASK\nShip\nMETA\npriority=urgent\ndeadline=soon";
+        let validated = validate(parse_str(input).unwrap()).unwrap();
+        assert_eq!((validated.priority, validated.deadline), (None, None));
+        assert!(!validated.is_overdue("2024-05-01"));
+        let warnings: Vec<_> = validated.warnings_of_kind(WarningKind::InvalidMetaValue).collect();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].message, "META priority 'urgent' is not a number from 0 to 255; ignored");
+        assert_eq!((warnings[0].line, warnings[1].line), (Some(4), Some(5)));
+
+        // Skipped comment lines do not shift the reported line
+        let commented = "TASK\nShip\nMETA\n# scheduling\npriority=urgent";
+        let doc = crate::parser::parse_str_with_config(commented, &crate::parser::ParserConfig::tolerant())
+            .unwrap()
+            .document;
+        let validated = validate(doc).unwrap();
+        assert_eq!(validated.warnings_of_kind(WarningKind::InvalidMetaValue).next().unwrap().line, Some(5));
+    }
+
+    #[test]
+    fn test_meta_keys_case_insensitive() {
+        for header in ["VERSION=1.1", "Version: 1.1", " version = 1.1"] {
+            let input = format!("TASK\nX\nMETA\n{}\nAuthor=Feanor", header);
+            let validated = validate(parse_str(&input).unwrap()).unwrap();
+            let meta = validated.meta.unwrap();
+
+            assert_eq!(meta.version(), Some("1.1"), "{}", header);
+            assert_eq!(meta.get(" VERSION "), Some("1.1"));
+            assert_eq!(meta.author(), Some("Feanor"));
+            assert_eq!(meta.get_parsed::<f32>("Version"), Some(1.1));
+            assert_eq!(meta.original_key("version"), header.split(['=', ':']).next().unwrap().trim());
+            assert_eq!(meta.original_key("author"), "Author");
+        }
+    }
+
+    #[test]
+    fn test_unknown_meta_keys() {
+        let input = "TASK\nX\nMETA\nverison=1.1\npriority=high\nteam=core";
+        let validated = validate_with_mode(parse_str(input).unwrap(), ValidationMode::Strict, None).unwrap();
+        assert_eq!(
+            validated.warning_messages(),
+            vec![
+                "Unknown META key 'verison', did you mean 'version'?",
+                "Unknown META key 'team'",
+                "META priority 'high' is not a number from 0 to 255; ignored",
+                "Missing version in META (v1.1 requires version=1.1)",
+            ]
+        );
+
+        // Lenient mode does not check keys
+        let lenient = validate_with_mode(parse_str(input).unwrap(), ValidationMode::Lenient, None).unwrap();
+        assert_eq!(lenient.warnings_of_kind(WarningKind::UnknownMetaKey).count(), 0);
+
+        let config = ValidationConfig {
+            extra_meta_keys: vec!["Team".to_string()],
+            reject_unknown_meta_keys: true,
+            ..ValidationConfig::default()
+        };
+        let err = validate_with_config(parse_str(input).unwrap(), ValidationMode::Strict, None, &config)
+            .unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::ValidationFailure);
+        assert_eq!(err.line, Some(4));
+        assert!(err.message.contains("verison"));
+
+        let ok = "TASK\nX\nMETA\nversion=1.1\nteam=core";
+        let validated = validate_with_config(parse_str(ok).unwrap(), ValidationMode::Strict, None, &config).unwrap();
+        assert!(validated.warnings.is_empty());
+
+        // Skipped comment lines do not shift the reported line
+        let commented = "TASK\nX\nMETA\n# provenance\nversion=1.1\nverison=1.1";
+        let doc = crate::parser::parse_str_with_config(commented, &crate::parser::ParserConfig::tolerant())
+            .unwrap()
+            .document;
+        let err = validate_with_config(doc, ValidationMode::Strict, None, &config).unwrap_err();
+        assert_eq!(err.line, Some(6));
+    }
+
+    #[test]
+    fn test_version_satisfies() {
+        let meta = |v: &str| MetaView {
+            entries: HashMap::from([("version".to_string(), v.to_string())]),
+            ..MetaView::default()
+        };
+
+        assert!(meta("1.1").version_satisfies("1.0", "1.1"));
+        assert!(meta("1.1.0").version_satisfies("1.0", "1.1"));
+        assert!(meta("1").version_satisfies("1.0", "1.1"));
+        assert!(!meta("1.2").version_satisfies("1.0", "1.1"));
+        assert!(!meta("0.9").version_satisfies("1.0", "1.1"));
+        assert!(!meta("1.x").version_satisfies("1.0", "1.1"));
+        assert!(!meta("1.1.0.4").version_satisfies("1.0", "1.1"));
+
+        assert!(!meta("1.2").is_version_compatible());
+        assert!(meta("1.0").is_version_compatible());
+    }
+
+    #[test]
+    fn test_validate_with_limits_truncates_plan() {
+        let steps: Vec<String> = (1..=1000).map(|i| format!("{}. Run check {}", i, i)).collect();
+        let input = format!("TASK\nAudit\nGOALS\nFast\nSafe\nPLAN\n{}\nMETA\nversion=1.1", steps.join("\n"));
+        let limits = ValidationLimits {
+            max_steps: 100,
+            ..ValidationLimits::default()
+        }
+        .with_truncation();
+
+        let validated =
+            validate_with_limits(parse_str(&input).unwrap(), ValidationMode::Strict, None, &limits).unwrap();
+        let plan = validated.plan.as_ref().unwrap();
+        assert_eq!(plan.steps().len(), 100);
+        assert_eq!(plan.entries.len(), 100);
+        assert_eq!(plan.items().len(), 100);
+        assert_eq!(plan.steps()[99], "Run check 100");
+        assert_eq!(validated.meta_fixes, vec!["Truncated PLAN from 1000 to 100 steps"]);
+
+        let warnings: Vec<_> = validated.warnings_of_kind(WarningKind::LimitExceeded).collect();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].message, "PLAN has 1000 steps, exceeding the limit of 100; truncated to 100");
+        assert_eq!(warnings[0].line, Some(6));
+    }
+
+    #[test]
+    fn test_validate_with_limits_without_truncation() {
+        let input = "TASK\nX\nPLAN\nA\n  A1\nB\nCONSTRAINTS\nno mocks\nSHOULD fast\nMETA\nversion=1.1";
+        let limits = ValidationLimits {
+            max_steps: 2,
+            max_constraints: 1,
+            ..ValidationLimits::default()
+        };
+
+        let err = validate_with_limits(parse_str(input).unwrap(), ValidationMode::Strict, None, &limits).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::ValidationFailure);
+        assert_eq!(err.message, "PLAN has 3 steps, exceeding the limit of 2");
+
+        let lenient = validate_with_limits(parse_str(input).unwrap(), ValidationMode::Lenient, None, &limits).unwrap();
+        assert_eq!(lenient.plan.as_ref().unwrap().steps().len(), 3);
+        assert_eq!(lenient.warnings_of_kind(WarningKind::LimitExceeded).count(), 2);
+        assert!(lenient.meta_fixes.is_empty());
+
+        // Truncation keeps the nested tree consistent with `steps`
+        let truncated =
+            validate_with_limits(parse_str(input).unwrap(), ValidationMode::Strict, None, &limits.with_truncation())
+                .unwrap();
+        let plan = truncated.plan.as_ref().unwrap();
+        assert_eq!(plan.items().len(), 1);
+        assert_eq!(plan.items()[0].children.len(), 1);
+        let constraints = truncated.constraints.as_ref().unwrap();
+        assert_eq!(constraints.canonical_rules(), vec!["no_mocks"]);
+    }
 }
@@ -0,0 +1,98 @@
+//! APEX Document Traversal
+//!
+//! [`Visitor`] walks the blocks of an [`ApexDocument`] in document order.
+//! Every block is passed to [`Visitor::visit_block`] and then to the method
+//! for its kind; all methods default to no-ops, so linters and transformers
+//! only implement what they need.
+
+use crate::ast::{ApexDocument, Block, BlockKind};
+
+/// Block visitor; see [`ApexDocument::accept`]
+pub trait Visitor {
+    /// Called for every block before the kind-specific method
+    fn visit_block(&mut self, _block: &Block) {}
+    /// TASK block
+    fn visit_task(&mut self, _block: &Block) {}
+    /// GOALS block
+    fn visit_goals(&mut self, _block: &Block) {}
+    /// PLAN block
+    fn visit_plan(&mut self, _block: &Block) {}
+    /// CONSTRAINTS block
+    fn visit_constraints(&mut self, _block: &Block) {}
+    /// VALIDATION block
+    fn visit_validation(&mut self, _block: &Block) {}
+    /// TOOLS block
+    fn visit_tools(&mut self, _block: &Block) {}
+    /// DIFF block
+    fn visit_diff(&mut self, _block: &Block) {}
+    /// CONTEXT block
+    fn visit_context(&mut self, _block: &Block) {}
+    /// META block
+    fn visit_meta(&mut self, _block: &Block) {}
+    /// WHEN block (called once per block)
+    fn visit_when(&mut self, _block: &Block) {}
+    /// RETRY block
+    fn visit_retry(&mut self, _block: &Block) {}
+    /// Registered extension block
+    fn visit_custom(&mut self, _name: &str, _block: &Block) {}
+}
+
+impl ApexDocument {
+    /// Walk all blocks in document order, dispatching by [`BlockKind`]
+    pub fn accept<V: Visitor>(&self, visitor: &mut V) {
+        for block in &self.blocks {
+            visitor.visit_block(block);
+            match &block.kind {
+                BlockKind::Task => visitor.visit_task(block),
+                BlockKind::Goals => visitor.visit_goals(block),
+                BlockKind::Plan => visitor.visit_plan(block),
+                BlockKind::Constraints => visitor.visit_constraints(block),
+                BlockKind::Validation => visitor.visit_validation(block),
+                BlockKind::Tools => visitor.visit_tools(block),
+                BlockKind::Diff => visitor.visit_diff(block),
+                BlockKind::Context => visitor.visit_context(block),
+                BlockKind::Meta => visitor.visit_meta(block),
+                BlockKind::When => visitor.visit_when(block),
+                BlockKind::Retry => visitor.visit_retry(block),
+                BlockKind::Custom(name) => visitor.visit_custom(name, block),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    /// Counts PLAN steps and WHEN steps (lines after the condition)
+    #[derive(Default)]
+    struct StepCounter {
+        blocks: usize,
+        steps: usize,
+    }
+
+    impl Visitor for StepCounter {
+        fn visit_block(&mut self, _block: &Block) {
+            self.blocks += 1;
+        }
+
+        fn visit_plan(&mut self, block: &Block) {
+            self.steps += block.content_lines().len();
+        }
+
+        fn visit_when(&mut self, block: &Block) {
+            self.steps += block.content_lines().len().saturating_sub(1);
+        }
+    }
+
+    #[test]
+    fn test_step_counter() {
+        let doc = parse_str("TASK\nX\nPLAN\nA\nB\n\nC\nWHEN\ntests fail\nRevert\nGOALS\nDone").unwrap();
+        let mut counter = StepCounter::default();
+        doc.accept(&mut counter);
+
+        assert_eq!(counter.blocks, 4);
+        assert_eq!(counter.steps, 4);
+    }
+}
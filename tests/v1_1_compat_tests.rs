@@ -0,0 +1,61 @@
+//! APEX v1.0 → v1.1 Upgrade Tests
+
+use apex_spec::{parse_str, upgrade_to_v1_1, validate_with_mode, DiffFormat, ValidationMode};
+
+#[test]
+fn test_upgrade_minimal_document() {
+    let doc = parse_str("TASK\nDo something").unwrap();
+    let (upgraded, changelog) = upgrade_to_v1_1(&doc);
+
+    assert_eq!(upgraded.version.as_deref(), Some("1.1"));
+    assert_eq!(changelog.len(), 1);
+    assert!(changelog[0].contains("Created META"));
+
+    let validated = validate_with_mode(upgraded, ValidationMode::Strict, None).unwrap();
+    assert!(validated.warnings.is_empty());
+    assert_eq!(validated.meta.unwrap().version(), Some("1.1"));
+}
+
+#[test]
+fn test_upgrade_full_v1_0_document() {
+    let input = r#"TASK
+Refactor module
+
+CONSTRAINTS
+No Mocks
+node_version: 18.x
+real_dbs
+
+DIFF
+src/lib.rs: +10 -5
+
+META
+version=1.0
+author=test
+"#;
+    let doc = parse_str(input).unwrap();
+    let (upgraded, changelog) = upgrade_to_v1_1(&doc);
+
+    let constraints = upgraded.constraints().unwrap().content_lines();
+    assert_eq!(constraints, vec!["no_mocks", "node_version: 18.x", "real_dbs"]);
+    assert!(changelog.iter().any(|c| c.contains("'No Mocks' to 'no_mocks'")));
+    assert!(changelog.iter().any(|c| c.contains("'raw' format marker")));
+    assert!(changelog.iter().any(|c| c.contains("Replaced 'version=1.0'")));
+    assert_eq!(changelog.len(), 3);
+
+    let validated = validate_with_mode(upgraded, ValidationMode::Strict, None).unwrap();
+    assert!(validated.warnings.is_empty());
+    let diff = validated.diff.unwrap();
+    assert_eq!(diff.format, DiffFormat::Raw);
+    assert_eq!(diff.changes, vec!["src/lib.rs: +10 -5"]);
+}
+
+#[test]
+fn test_upgrade_is_idempotent() {
+    let doc = parse_str("TASK\nDo something\nDIFF\nunified\n--- a/x\n+++ b/x").unwrap();
+    let (once, _) = upgrade_to_v1_1(&doc);
+    let (twice, changelog) = upgrade_to_v1_1(&once);
+
+    assert!(changelog.is_empty());
+    assert_eq!(once, twice);
+}
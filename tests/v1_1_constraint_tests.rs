@@ -1,6 +1,6 @@
 //! APEX v1.1 Constraint Canonicalization Tests
 
-use apex_spec::{canonicalize, normalize_constraint, Constraint};
+use apex_spec::{canonicalize, normalize_constraint, parse_and_validate, Constraint, Semantics, Severity};
 
 #[test]
 fn test_canonicalize_basic() {
@@ -78,3 +78,120 @@ fn test_normalize_equals_canonicalize() {
         assert_eq!(normalize_constraint(input), canonicalize(input));
     }
 }
+
+#[test]
+fn test_key_value_constraint_preserves_version() {
+    let input = r#"TASK
+Upgrade runtime
+
+CONSTRAINTS
+node_version: 18.x
+no mocks
+"#;
+    let validated = parse_and_validate(input).unwrap();
+    let rules = &validated.constraints.as_ref().unwrap().rules;
+    assert_eq!(rules[0].canonical, "node_version: 18.x");
+    assert_eq!(rules[1].canonical, "no_mocks");
+    assert_eq!(rules[1].display, "no mocks");
+
+    let sem = Semantics::from_validated(&validated);
+    assert_eq!(sem.constraint_value("node_version"), Some("18.x"));
+    assert_eq!(sem.constraint_value("Node_Version"), Some("18.x"));
+    assert!(sem.forbids_mocks());
+}
+
+#[test]
+fn test_key_value_constraint_preserves_path() {
+    let input = r#"TASK
+Move module
+
+CONSTRAINTS
+output_dir: src/app/Core Module
+"#;
+    let validated = parse_and_validate(input).unwrap();
+    let sem = Semantics::from_validated(&validated);
+
+    assert_eq!(sem.constraint_value("output_dir"), Some("src/app/Core Module"));
+    assert_eq!(sem.constraint_value("missing"), None);
+    assert_eq!(
+        sem.constraints[0],
+        Constraint::KeyValue {
+            key: "output_dir".to_string(),
+            value: "src/app/Core Module".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_deduplicated_constraints() {
+    let input = r#"TASK
+Clean up
+
+CONSTRAINTS
+No Mocks
+real_dbs
+no_mocks
+real dbs only
+NO-MOCKS
+"#;
+    let validated = parse_and_validate(input).unwrap();
+    let view = validated.constraints.as_ref().unwrap();
+    assert_eq!(view.rules.len(), 5);
+    assert_eq!(view.deduplicated(), vec!["no_mocks", "real_dbs", "real_dbs_only"]);
+
+    let sem = Semantics::from_validated(&validated);
+    assert_eq!(sem.constraints.len(), 3);
+    assert_eq!(
+        sem.unique_constraints(),
+        vec![Constraint::NoMocks, Constraint::RealDbsOnly]
+    );
+}
+
+#[test]
+fn test_conflicts_not_double_counted() {
+    let input = r#"TASK
+Clean up
+
+CONSTRAINTS
+real_dbs
+real_dbs_only
+mock_dbs
+"#;
+    let validated = parse_and_validate(input).unwrap();
+    let sem = Semantics::from_validated(&validated);
+
+    assert_eq!(sem.conflicts().len(), 1);
+}
+
+#[test]
+fn test_constraint_severity_levels() {
+    let input = r#"TASK
+Refactor
+
+CONSTRAINTS
+SHOULD: No Mocks
+MAY: < 300 LOC
+MUST: real dbs
+SHOULD: real_dbs
+require tests
+"#;
+    let validated = parse_and_validate(input).unwrap();
+    let view = validated.constraints.as_ref().unwrap();
+    assert_eq!(
+        view.rules_with_severity(),
+        vec![
+            (Severity::Should, "no_mocks".to_string()),
+            (Severity::May, "lt_300_loc".to_string()),
+            (Severity::Must, "real_dbs".to_string()),
+            (Severity::Should, "real_dbs".to_string()),
+            (Severity::Must, "require_tests".to_string()),
+        ]
+    );
+
+    let sem = Semantics::from_validated(&validated);
+    assert!(!sem.forbids_mocks());
+    assert_eq!(sem.loc_limit(), None);
+    assert!(sem.requires_real_dbs());
+    assert!(sem.requires_tests());
+    assert_eq!(sem.constraints.len(), 4);
+}
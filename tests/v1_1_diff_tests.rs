@@ -1,6 +1,6 @@
 //! APEX v1.1 DIFF Format Marker Tests
 
-use apex_spec::{parse_str, validate, DiffFormat};
+use apex_spec::{parse_and_validate, ApexErrorKind, DiffFormat, DiffLine, Semantics};
 
 #[test]
 fn test_diff_unified_marker() {
@@ -92,3 +92,136 @@ UNIFIED
 fn test_diff_format_default() {
     assert_eq!(DiffFormat::default(), DiffFormat::Unspecified);
 }
+
+#[test]
+fn test_parse_unified_hunks() {
+    let input = "TASK\nApply patch\n\nDIFF\nunified\n\
+diff --git a/src/lib.rs b/src/lib.rs\n\
+--- a/src/lib.rs\n\
++++ b/src/lib.rs\n\
+@@ -1,3 +1,4 @@\n\
++// New comment\n\
+ fn main() {\n\
+-    old();\n\
++    new();\n\
+ }\n\
+--- a/README.md\t2024-01-01\n\
++++ b/README.md\n\
+@@ -10 +10 @@ Usage\n\
+--- old heading\n\
++-- new heading\n";
+    let validated = apex_spec::parse_and_validate(input).unwrap();
+    let diff = validated.diff.unwrap().parse_unified().unwrap();
+
+    assert_eq!(diff.files.len(), 2);
+    let lib = &diff.files[0];
+    assert_eq!(lib.old_path, "a/src/lib.rs");
+    assert_eq!(lib.new_path, "b/src/lib.rs");
+    assert_eq!(lib.hunks.len(), 1);
+
+    let hunk = &lib.hunks[0];
+    assert_eq!((hunk.old_start, hunk.old_count, hunk.new_start, hunk.new_count), (1, 3, 1, 4));
+    assert_eq!(hunk.lines[0], DiffLine::Added("// New comment".to_string()));
+    assert_eq!(hunk.lines[1], DiffLine::Context("fn main() {".to_string()));
+    assert_eq!(hunk.lines[2], DiffLine::Removed("    old();".to_string()));
+    assert_eq!(hunk.lines.len(), 5);
+
+    // Omitted counts default to 1; "---" inside a hunk is a removed line
+    let readme = &diff.files[1];
+    assert_eq!(readme.old_path, "a/README.md");
+    assert_eq!(readme.hunks[0].old_count, 1);
+    assert_eq!(readme.hunks[0].lines[0], DiffLine::Removed("-- old heading".to_string()));
+    assert_eq!(readme.hunks[0].lines[1], DiffLine::Added("-- new heading".to_string()));
+}
+
+#[test]
+fn test_parse_unified_malformed_header() {
+    let input = "TASK\nApply patch\nDIFF\nunified\n--- a/x\n+++ b/x\n@@ -1,two +1 @@\n+x";
+    let validated = apex_spec::parse_and_validate(input).unwrap();
+    let err = validated.diff.unwrap().parse_unified().unwrap_err();
+
+    assert_eq!(err.kind, ApexErrorKind::ParseError);
+    assert!(err.message.contains("Malformed hunk header"));
+}
+
+#[test]
+fn test_parse_unified_requires_marker() {
+    let input = "TASK\nApply patch\nDIFF\nraw\n--- a/x\n+++ b/x";
+    let validated = apex_spec::parse_and_validate(input).unwrap();
+    assert!(validated.diff.unwrap().parse_unified().is_err());
+
+    let input = "TASK\nApply patch\nDIFF\nunified\n@@ -1 +1 @@\n+x";
+    let validated = apex_spec::parse_and_validate(input).unwrap();
+    let err = validated.diff.unwrap().parse_unified().unwrap_err();
+    assert!(err.message.contains("without file headers"));
+}
+
+#[test]
+fn test_loc_limit_against_unified_diff() {
+    let input = r#"TASK
+Small change
+
+CONSTRAINTS
+< 2 LOC
+
+DIFF
+unified
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,1 +1,3 @@
+ fn main() {}
++fn a() {}
++fn b() {}
+"#;
+    let validated = parse_and_validate(input).unwrap();
+    let sem = Semantics::from_validated(&validated);
+    let diff = validated.diff.as_ref().unwrap();
+
+    assert!(sem.check_loc_against_diff(diff).is_ok());
+
+    let over = input.replace("< 2 LOC", "< 1 LOC");
+    let validated = parse_and_validate(&over).unwrap();
+    let sem = Semantics::from_validated(&validated);
+    let err = sem.check_loc_against_diff(validated.diff.as_ref().unwrap()).unwrap_err();
+    assert_eq!(err.kind, ApexErrorKind::ConstraintViolation);
+}
+
+#[test]
+fn test_loc_limit_against_raw_diff() {
+    let input = "TASK\nX\nCONSTRAINTS\n< 2 LOC\nDIFF\nraw\nline one\n\nline two\nline three\n";
+    let validated = parse_and_validate(input).unwrap();
+    let sem = Semantics::from_validated(&validated);
+
+    assert!(sem.check_loc_against_diff(validated.diff.as_ref().unwrap()).is_err());
+
+    let no_limit = parse_and_validate("TASK\nX\nDIFF\nraw\na\nb\nc\n").unwrap();
+    let sem = Semantics::from_validated(&no_limit);
+    assert!(sem.check_loc_against_diff(no_limit.diff.as_ref().unwrap()).is_ok());
+}
+
+#[test]
+fn test_safe_refactor_flags_risky_deletions() {
+    let removed: String = (0..51).map(|i| format!("-let x{} = {};\n", i, i)).collect();
+    let input = format!(
+        "TASK\nRefactor\nCONSTRAINTS\nsafe refactor\nDIFF\nunified\n--- a/src/lib.rs\n+++ b/src/lib.rs\n\
+         @@ -1,3 +1,3 @@\n-pub fn old_name() {{}}\n+pub fn new_name() {{}}\n-pub struct Moved;\n pub fn keep() {{}}\n\
+         @@ -40,51 +40,0 @@\n{}@@ -120,1 +120,1 @@\n+pub struct Moved;\n",
+        removed
+    );
+    let validated = parse_and_validate(&input).unwrap();
+    let sem = Semantics::from_validated(&validated);
+    let warnings = sem.check_safe_refactor(validated.diff.as_ref().unwrap());
+
+    assert_eq!(
+        warnings,
+        vec![
+            "a/src/lib.rs: removes public API 'pub fn old_name() {}'",
+            "a/src/lib.rs: hunk at line 40 deletes 51 lines (more than 50)",
+        ]
+    );
+
+    // Without the constraint nothing is flagged
+    let unconstrained = parse_and_validate(&input.replace("safe refactor", "no mocks")).unwrap();
+    let sem = Semantics::from_validated(&unconstrained);
+    assert!(sem.check_safe_refactor(unconstrained.diff.as_ref().unwrap()).is_empty());
+}
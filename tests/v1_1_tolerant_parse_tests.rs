@@ -1,6 +1,6 @@
 //! APEX v1.1 Tolerant Parse Mode Tests
 
-use apex_spec::{parse_str_with_mode, ParseMode, BlockKind};
+use apex_spec::{parse_and_validate_with_mode, parse_full_with_mode, parse_str_with_mode, ParseMode};
 
 #[test]
 fn test_tolerant_accepts_lowercase_headers() {
@@ -172,3 +172,33 @@ Step 1
     assert!(result.document.task().is_some());
     assert!(result.document.plan().is_some());
 }
+
+#[test]
+fn test_full_pipeline_returns_fixes() {
+    let input = r#"task
+Do something
+
+Plan
+Step 1
+Step 2
+"#;
+    let (plan, fixes) = parse_full_with_mode(input, ParseMode::Tolerant).unwrap();
+
+    assert_eq!(plan.task, "Do something");
+    assert_eq!(plan.steps.len(), 2);
+    assert_eq!(fixes.len(), 2);
+    assert_eq!(fixes[0].line, 1);
+    assert_eq!(fixes[1].line, 4);
+}
+
+#[test]
+fn test_fixes_merged_into_meta_fixes() {
+    let input = "task\nDo something\nPLAN\nStep 1";
+    let validated = parse_and_validate_with_mode(input, ParseMode::Tolerant).unwrap();
+
+    assert_eq!(validated.meta_fixes.len(), 1);
+    assert_eq!(validated.meta_fixes[0], "line 1: Normalized header 'task' to 'TASK'");
+
+    let validated = parse_and_validate_with_mode("TASK\nDo something", ParseMode::Strict).unwrap();
+    assert!(validated.meta_fixes.is_empty());
+}
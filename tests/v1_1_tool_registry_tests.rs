@@ -1,8 +1,8 @@
 //! APEX v1.1 Tool Registry Validation Tests
 
 use apex_spec::{
-    parse_str, validate_with_mode, ValidationMode,
-    ToolRegistry, VALID_TOOLS, extract_tool_name,
+    parse_str, validate_with_mode, ValidationMode, WarningKind, ApexErrorKind,
+    ToolInvocation, ToolRegistry, VALID_TOOLS, extract_tool_name, split_tool_namespace,
 };
 
 #[test]
@@ -53,6 +53,34 @@ version=1.1
     assert!(result.unwrap_err().to_string().contains("Unknown tool"));
 }
 
+#[test]
+fn test_suggest_near_miss_names() {
+    let registry = ToolRegistry::new();
+
+    assert_eq!(registry.suggest("code_serch"), Some("code_search".to_string()));
+    assert_eq!(registry.suggest("read_fle"), Some("read_file".to_string()));
+    assert_eq!(registry.suggest("wrte_fil"), Some("write_file".to_string()));
+    assert_eq!(registry.suggest("memory.qeury"), Some("memory.query".to_string()));
+    assert_eq!(registry.suggest("basj"), Some("bash".to_string()));
+    assert_eq!(registry.suggest("completely_unrelated"), None);
+}
+
+#[test]
+fn test_strict_error_includes_suggestion() {
+    let input = r#"TASK
+Do something
+
+TOOLS
+code_serch "query"
+"#;
+    let doc = parse_str(input).unwrap();
+    let registry = ToolRegistry::new();
+    let err = validate_with_mode(doc, ValidationMode::Strict, Some(&registry)).unwrap_err();
+
+    assert!(err.message.contains("Unknown tool 'code_serch', did you mean 'code_search'?"));
+    assert_eq!(err.line, Some(5));
+}
+
 #[test]
 fn test_lenient_mode_warns_unknown_tool() {
     let input = r#"TASK
@@ -68,7 +96,8 @@ version=1.1
     let registry = ToolRegistry::new();
     let validated = validate_with_mode(doc, ValidationMode::Lenient, Some(&registry)).unwrap();
 
-    assert!(validated.warnings.iter().any(|w| w.contains("tool_degraded")));
+    assert!(validated.warnings.iter().any(|w| w.kind == WarningKind::ToolDegraded));
+    assert_eq!(validated.warning_messages(), vec!["Unknown tool 'fake_unknown_tool' (tool_degraded)"]);
 }
 
 #[test]
@@ -84,7 +113,7 @@ fake_unknown_tool(args)
     let validated = validate_with_mode(doc, ValidationMode::Legacy, Some(&registry)).unwrap();
 
     // Legacy mode does not add tool warnings
-    assert!(!validated.warnings.iter().any(|w| w.contains("tool")));
+    assert!(!validated.warnings.iter().any(|w| matches!(w.kind, WarningKind::ToolDegraded | WarningKind::UnknownTool)));
 }
 
 #[test]
@@ -114,6 +143,7 @@ fn test_extract_tool_name_simple() {
 fn test_extract_tool_name_with_args() {
     assert_eq!(extract_tool_name("code_search(query)"), "code_search");
     assert_eq!(extract_tool_name("read_file(path, opts)"), "read_file");
+    assert_eq!(extract_tool_name("memory.query(\"x\")"), "memory.query");
 }
 
 #[test]
@@ -122,6 +152,64 @@ fn test_extract_tool_name_with_string_arg() {
     assert_eq!(extract_tool_name("grep \"error\""), "grep");
 }
 
+#[test]
+fn test_dotted_tool_names() {
+    let input = "TASK\nRecall\nTOOLS\nmemory.query\nmemory.query(\"x\")\nmemory.store(key=\"a\")\ncode_search\nMETA\nversion=1.1";
+    let registry = ToolRegistry::new();
+    let validated = validate_with_mode(parse_str(input).unwrap(), ValidationMode::Strict, Some(&registry)).unwrap();
+
+    let tools = &validated.tools.as_ref().unwrap().tools;
+    let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, vec!["memory.query", "memory.query", "memory.store", "code_search"]);
+    assert_eq!(tools[0].arguments, None);
+    assert_eq!(tools[1].arguments.as_deref(), Some("\"x\""));
+    assert_eq!(tools[2].arguments.as_deref(), Some("key=\"a\""));
+
+    let namespaces: Vec<Option<String>> = tools
+        .iter()
+        .map(|t| ToolInvocation::from_declaration(t).namespace().map(str::to_string))
+        .collect();
+    assert_eq!(namespaces, vec![Some("memory".into()), Some("memory".into()), Some("memory".into()), None]);
+}
+
+#[test]
+fn test_unknown_dotted_tool_rejected() {
+    let input = "TASK\nRecall\nTOOLS\nmemory.unknown(\"x\")\nMETA\nversion=1.1";
+    let registry = ToolRegistry::new();
+    let err = validate_with_mode(parse_str(input).unwrap(), ValidationMode::Strict, Some(&registry)).unwrap_err();
+
+    assert_eq!(err.kind, ApexErrorKind::InvalidToolName);
+    assert_eq!(
+        err.message,
+        "Unknown tool 'memory.unknown', namespace provides: memory.consolidate, memory.delete, memory.query, memory.store"
+    );
+    assert_eq!(registry.unknown_tool_message("nothing.here"), "Unknown tool 'nothing.here' not in registry");
+}
+
+#[test]
+fn test_malformed_dotted_tool_name() {
+    for name in ["memory.", ".query", "memory..query(\"x\")"] {
+        let input = format!("TASK\nRecall\nTOOLS\n{}", name);
+        let err = validate_with_mode(parse_str(&input).unwrap(), ValidationMode::Strict, None).unwrap_err();
+        assert_eq!(err.kind, ApexErrorKind::InvalidToolName, "{}", name);
+        assert!(err.message.starts_with("Malformed dotted tool name"));
+        assert_eq!(err.line, Some(4));
+
+        // Lenient and legacy modes keep the tool with a warning
+        for mode in [ValidationMode::Lenient, ValidationMode::Legacy] {
+            let validated = validate_with_mode(parse_str(&input).unwrap(), mode, None).unwrap();
+            assert_eq!(validated.tools.as_ref().unwrap().tools.len(), 1, "{}", name);
+            let warning = validated.warnings.iter().find(|w| w.kind == WarningKind::MalformedToolName).unwrap();
+            assert_eq!(warning.line, Some(4));
+        }
+    }
+
+    assert_eq!(split_tool_namespace("memory.query"), Some(("memory", "query")));
+    assert_eq!(split_tool_namespace("org.memory.query"), Some(("org.memory", "query")));
+    assert_eq!(split_tool_namespace("code_search"), None);
+    assert_eq!(split_tool_namespace("memory..query"), None);
+}
+
 #[test]
 fn test_valid_tools_constant() {
     assert!(VALID_TOOLS.contains(&"code_search"));
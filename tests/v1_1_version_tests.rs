@@ -1,6 +1,6 @@
 //! APEX v1.1 Version Enforcement Tests
 
-use apex_spec::{parse_str, validate_with_mode, ValidationMode, ToolRegistry};
+use apex_spec::{parse_str, validate_with_mode, ValidationMode, WarningKind};
 
 #[test]
 fn test_legacy_mode_no_version_required() {
@@ -24,7 +24,7 @@ author=test
     let doc = parse_str(input).unwrap();
     let validated = validate_with_mode(doc, ValidationMode::Strict, None).unwrap();
 
-    assert!(validated.warnings.iter().any(|w| w.contains("Missing version")));
+    assert!(validated.warnings.iter().any(|w| w.kind == WarningKind::MissingVersion));
 }
 
 #[test]
@@ -35,7 +35,7 @@ Do something
     let doc = parse_str(input).unwrap();
     let validated = validate_with_mode(doc, ValidationMode::Strict, None).unwrap();
 
-    assert!(validated.warnings.iter().any(|w| w.contains("Missing META")));
+    assert!(validated.warnings.iter().any(|w| w.kind == WarningKind::MissingMeta));
 }
 
 #[test]
@@ -50,7 +50,7 @@ version=1.1
     let validated = validate_with_mode(doc, ValidationMode::Strict, None).unwrap();
 
     // Should not have version warning
-    assert!(!validated.warnings.iter().any(|w| w.contains("Missing version")));
+    assert!(!validated.warnings.iter().any(|w| w.kind == WarningKind::MissingVersion));
 }
 
 #[test]
@@ -91,7 +91,7 @@ Do something
     let validated = validate_with_mode(doc, ValidationMode::Lenient, None).unwrap();
 
     // Lenient mode should not add version warnings
-    assert!(!validated.warnings.iter().any(|w| w.contains("version")));
+    assert!(!validated.warnings.iter().any(|w| matches!(w.kind, WarningKind::MissingVersion | WarningKind::MissingMeta)));
 }
 
 #[test]
@@ -110,3 +110,19 @@ author=test
     assert_eq!(meta.version(), Some("1.1"));
     assert!(meta.is_version_compatible());
 }
+
+#[test]
+fn test_newer_minor_version_incompatible() {
+    let input = r#"TASK
+Do something
+
+META
+version=1.2
+"#;
+    let doc = parse_str(input).unwrap();
+    let result = validate_with_mode(doc, ValidationMode::Strict, None);
+
+    let err = result.unwrap_err();
+    assert!(err.to_string().contains("Unsupported APEX version: 1.2"));
+    assert_eq!(err.line, Some(4));
+}